@@ -6,9 +6,7 @@ use csv::Writer;
 use env_logger::Builder;
 use log::{info, warn};
 use rust_decimal::Decimal;
-use rust_decimal_macros::dec;
 use serde::{Deserialize, Serialize};
-use std::collections::BTreeMap;
 use std::error::Error;
 use std::fmt::Debug;
 use std::fs::File;
@@ -16,8 +14,7 @@ use std::sync::atomic::AtomicBool;
 
 use exrs::binance_f::api::*;
 use exrs::binance_f::market::*;
-use exrs::binance_f::rest_model::OrderBookPartial;
-use exrs::binance_f::util::get_timestamp;
+use exrs::binance_f::orderbook::{Orderbook, OrderbookManager};
 use exrs::binance_f::websockets::*;
 use exrs::binance_f::ws_model::{AggrTradesEvent, DepthOrderBookEvent};
 
@@ -69,136 +66,17 @@ type Record<'a> = (
 //     pub bids_qty: Vec<Decimal>,
 // }
 
-#[derive(Debug, Serialize, Deserialize, Clone)]
-pub struct Orderbook {
-    pub symbol: String,
-    pub timestamp: u64,
-    pub final_update_id: u64,
-    pub bids: BTreeMap<Decimal, Decimal>,
-    pub asks: BTreeMap<Decimal, Decimal>,
-}
-
-impl Orderbook {
-    pub fn new(symbol: String) -> Orderbook {
-        let now = get_timestamp().unwrap();
-        Orderbook {
-            symbol,
-            timestamp: now,
-            final_update_id: 0,
-            bids: BTreeMap::new(),
-            asks: BTreeMap::new(),
-        }
-    }
-
-    pub fn get_depth(&mut self, depth: usize) -> Option<Record> {
-        // let asks: Vec<(Decimal, Decimal)> = self.asks.iter().take(depth).rev().collect();
-        // let bids: Vec<(Decimal, Decimal)> = self.bids.iter().rev().take(depth).collect();
-        let asks_price = self.asks.keys().cloned().take(depth).collect();
-        let bids_price = self.bids.keys().cloned().rev().take(depth).collect();
-        let asks_qty = self.asks.values().cloned().take(depth).collect();
-        let bids_qty = self.bids.values().cloned().rev().take(depth).collect();
-
-        info!("asks_price {:?}", asks_price);
-        info!("bids_price {:?}", bids_price);
-        info!("asks_qty {:?}", asks_qty);
-        info!("bids_qty {:?}", bids_qty);
-
-        Some((
-            &self.symbol,
-            &self.timestamp,
-            asks_price,
-            bids_price,
-            asks_qty,
-            bids_qty,
-        ))
-    }
-
-    pub fn partial(&mut self, data: &OrderBookPartial) {
-        self.bids.clear();
-        self.asks.clear();
-        self.final_update_id = data.last_update_id;
-        self.timestamp = data.event_time;
-        for bid in &data.bids {
-            self.bids.insert(bid.price, bid.qty);
-        }
-        for ask in &data.asks {
-            self.asks.insert(ask.price, ask.qty);
-        }
-    }
-
-    pub fn update(&mut self, data: &DepthOrderBookEvent) {
-        self.final_update_id = data.final_update_id;
-        self.timestamp = data.event_time;
-        for bid in &data.bids {
-            if bid.qty == dec!(0) {
-                self.bids.remove(&bid.price);
-            } else {
-                self.bids.insert(bid.price, bid.qty);
-            }
-        }
-        for ask in &data.asks {
-            if ask.qty == dec!(0) {
-                self.asks.remove(&ask.price);
-            } else {
-                self.asks.insert(ask.price, ask.qty);
-            }
-        }
-    }
-
-    pub fn verify(&mut self, pu_id: u64, check_bid_ask_overlapping: bool) -> bool {
-        if check_bid_ask_overlapping {
-            if self.bids.len() > 0 && self.asks.len() > 0 {
-                if self.best_bid().unwrap().0 >= self.best_ask().unwrap().0 {
-                    warn!(
-                        "best bid {} >= best ask {}",
-                        self.best_bid().unwrap().0,
-                        self.best_ask().unwrap().0
-                    );
-                    return false;
-                }
-            }
-        }
-
-        self.final_update_id == pu_id
-    }
-
-    /// Returns the price of the best bid
-    pub fn bid_price(&self) -> Option<Decimal> {
-        self.bids.keys().rev().next().cloned()
-    }
-
-    /// Returns the price of the best ask
-    pub fn ask_price(&mut self) -> Option<Decimal> {
-        self.asks.keys().next().cloned()
-    }
-
-    /// Returns the midpoint between the best bid price and best ask price.
-    /// Output is not rounded to the smallest price increment.
-    pub fn mid_price(&mut self) -> Option<Decimal> {
-        Some((self.bid_price()? + self.ask_price()?) / dec!(2))
-    }
-
-    /// Returns the price and quantity of the best bid
-    /// (bid_price, bid_quantity)
-    pub fn best_bid(&mut self) -> Option<(Decimal, Decimal)> {
-        let (price, qty) = self.bids.iter().rev().next()?;
-
-        Some((*price, *qty))
-    }
-
-    /// Returns the price and quantity of the best ask
-    /// (ask_price, ask_quantity)
-    pub fn best_ask(&mut self) -> Option<(Decimal, Decimal)> {
-        let (price, qty) = self.asks.iter().next()?;
-
-        Some((*price, *qty))
-    }
-
-    /// Returns the price and quantity of the best bid and best ask
-    /// ((bid_price, bid_quantity), (ask_price, ask_quantity))
-    pub fn best_bid_and_ask(&mut self) -> Option<((Decimal, Decimal), (Decimal, Decimal))> {
-        Some((self.best_bid()?, self.best_ask()?))
-    }
+/// Top `depth` levels of `book` as a csv record
+fn get_depth(book: &Orderbook, depth: usize) -> Record<'_> {
+    let (bids, asks) = book.depth(depth);
+    (
+        &book.symbol,
+        &book.timestamp,
+        asks.iter().map(|l| l.0).collect(),
+        bids.iter().map(|l| l.0).collect(),
+        asks.iter().map(|l| l.1).collect(),
+        bids.iter().map(|l| l.1).collect(),
+    )
 }
 
 struct WebSocketHandler {
@@ -355,40 +233,23 @@ async fn run_depth(file_url: String, symbol: String) {
 
     let keep_running = AtomicBool::new(true);
     let depth = format!("{}@depth@0ms", symbol);
-    let (tx, mut rx) = tokio::sync::mpsc::channel(8192);
+    let (tx, rx) = tokio::sync::mpsc::channel(8192);
+    let (book_tx, mut book_rx) = tokio::sync::mpsc::channel(1024);
     let mut web_socket: FuturesWebSockets<DepthOrderBookEvent> = FuturesWebSockets::new(tx);
-    let mut orderbook = Orderbook::new(symbol.clone());
+    let manager = OrderbookManager::new(market, symbol.clone(), book_tx).with_publish_depth(5);
 
     web_socket.connect(&depth).await.unwrap();
 
     actix_rt::spawn(async move {
-        let partial_init: OrderBookPartial =
-            market.get_custom_depth(symbol.clone(), 1000).await.unwrap();
-        orderbook.partial(&partial_init);
-
-        loop {
-            let msg = rx.recv().await.unwrap();
-
-            if msg.final_update_id < partial_init.last_update_id {
-                continue;
-            } else if msg.first_update_id <= partial_init.last_update_id
-                && msg.final_update_id >= partial_init.last_update_id
-            {
-                orderbook.update(&msg)
-            } else if orderbook.verify(msg.previous_final_update_id, false) {
-                info!("verfiy passed");
-                orderbook.update(&msg)
-            } else {
-                warn!("verfiy failed");
-                let partial_init: OrderBookPartial =
-                    market.get_custom_depth(symbol.clone(), 1000).await.unwrap();
-                orderbook.partial(&partial_init);
-            }
-
-            let event = orderbook.get_depth(5).unwrap();
+        if let Err(e) = manager.run(rx).await {
+            warn!("order book manager Error: {}", e);
+        }
+    });
 
+    actix_rt::spawn(async move {
+        while let Some(orderbook) = book_rx.recv().await {
             if DateTime::<Utc>::from_utc(
-                NaiveDateTime::from_timestamp((msg.event_time / 1000) as i64, 0),
+                NaiveDateTime::from_timestamp((orderbook.timestamp / 1000) as i64, 0),
                 Utc,
             ) > tmr_dt
             {
@@ -400,7 +261,7 @@ async fn run_depth(file_url: String, symbol: String) {
                 web_socket_handler.write_depth_header().unwrap();
             }
 
-            if let Err(error) = web_socket_handler.write_depth_to_file(&event) {
+            if let Err(error) = web_socket_handler.write_depth_to_file(&get_depth(&orderbook, 5)) {
                 warn!("{}", error);
             };
         }
@@ -21,6 +21,7 @@ pub mod config;
 pub mod general;
 pub mod margin;
 pub mod market;
+pub mod orderbook;
 pub mod savings;
 pub mod userstream;
 pub mod websockets;
@@ -87,9 +87,11 @@ where
     }
 }
 
-#[derive(Serialize)]
+/// Order Request
+/// perform an order for the account
+#[derive(Default, Serialize)]
 #[serde(rename_all = "camelCase")]
-struct OrderRequest {
+pub struct OrderRequest {
     pub symbol: String,
     pub side: OrderSide,
     pub position_side: Option<PositionSide>,
@@ -107,6 +109,20 @@ struct OrderRequest {
     pub working_type: Option<WorkingType>,
     #[serde(serialize_with = "serialize_opt_as_uppercase")]
     pub price_protect: Option<bool>,
+    /// A unique id for the order, automatically generated if not sent.
+    pub new_client_order_id: Option<String>,
+}
+
+/// Modify the price and quantity of a live limit order
+#[derive(Default, Debug, Serialize, Deserialize, Clone)]
+#[serde(rename_all = "camelCase")]
+pub struct ModifyOrderRequest {
+    pub symbol: String,
+    pub order_id: Option<u64>,
+    pub orig_client_order_id: Option<String>,
+    pub side: OrderSide,
+    pub quantity: f64,
+    pub price: f64,
 }
 
 /// Order Cancellation Request
@@ -166,6 +182,7 @@ impl FuturesAccount {
             callback_rate: None,
             working_type: None,
             price_protect: None,
+            new_client_order_id: None,
         };
         self.post_order(order).await
     }
@@ -193,6 +210,7 @@ impl FuturesAccount {
             callback_rate: None,
             working_type: None,
             price_protect: None,
+            new_client_order_id: None,
         };
         self.post_order(order).await
     }
@@ -218,6 +236,7 @@ impl FuturesAccount {
             callback_rate: None,
             working_type: None,
             price_protect: None,
+            new_client_order_id: None,
         };
         self.post_order(order).await
     }
@@ -243,10 +262,21 @@ impl FuturesAccount {
             callback_rate: None,
             working_type: None,
             price_protect: None,
+            new_client_order_id: None,
         };
         self.post_order(order).await
     }
 
+    pub async fn place_order(&self, order: OrderRequest) -> Result<Transaction> {
+        self.post_order(order).await
+    }
+
+    pub async fn modify_order(&self, order: ModifyOrderRequest) -> Result<Transaction> {
+        self.client
+            .put_signed_p(FAPI_ORDER, order, self.recv_window)
+            .await
+    }
+
     /// Place a cancellation order
     pub async fn cancel_order(&self, o: OrderCancellation) -> Result<CanceledOrder> {
         let recv_window = o.recv_window.unwrap_or(self.recv_window);
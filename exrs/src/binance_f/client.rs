@@ -127,6 +127,26 @@ impl Client {
         Ok(t)
     }
 
+    pub async fn put_signed_p<T: de::DeserializeOwned, P: serde::Serialize>(
+        &self,
+        endpoint: &str,
+        payload: P,
+        recv_window: u64,
+    ) -> Result<T> {
+        let request = build_signed_request_p(payload, recv_window)?;
+        let url = self.sign_request(endpoint, &request);
+        let response = self
+            .inner
+            .clone()
+            .put(url.as_str())
+            .headers(self.build_headers(true)?)
+            .send()
+            .await?;
+        let string = self.handler(response).await?;
+        let t = from_str(string.as_str())?;
+        Ok(t)
+    }
+
     pub async fn delete_signed(&self, endpoint: &str, request: &str) -> Result<String> {
         let url = self.sign_request(endpoint, request);
         let response = self
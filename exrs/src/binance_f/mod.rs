@@ -20,5 +20,6 @@ pub mod api;
 pub mod config;
 pub mod general;
 pub mod market;
+pub mod orderbook;
 pub mod userstream;
 pub mod websockets;
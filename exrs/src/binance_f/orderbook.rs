@@ -0,0 +1,149 @@
+//! Local futures order book kept in sync from a depth snapshot and the diff depth stream.
+//!
+//! The sync procedure lives in `depth_book`, futures events start from the one straddling
+//! `lastUpdateId` and then require every event's `pu` to be the previous event's `u`.
+
+use async_trait::async_trait;
+use rust_decimal::Decimal;
+
+use super::errors::*;
+use super::market::FuturesMarket;
+use super::rest_model::OrderBookPartial;
+use super::ws_model::DepthOrderBookEvent;
+use crate::depth_book::{self, DepthSnapshot, DepthUpdate, DepthVenue};
+
+pub use crate::depth_book::{BookSync, Orderbook};
+
+/// Keeps a futures `Orderbook` in sync, resyncing through `FuturesMarket::get_custom_depth`
+pub type OrderbookManager = depth_book::OrderbookManager<FuturesMarket>;
+
+impl DepthUpdate for DepthOrderBookEvent {
+    fn symbol(&self) -> &str {
+        &self.symbol
+    }
+
+    fn event_time(&self) -> u64 {
+        self.event_time
+    }
+
+    fn first_update_id(&self) -> u64 {
+        self.first_update_id
+    }
+
+    fn final_update_id(&self) -> u64 {
+        self.final_update_id
+    }
+
+    fn bids(&self) -> Vec<(Decimal, Decimal)> {
+        self.bids.iter().map(|l| (l.price, l.qty)).collect()
+    }
+
+    fn asks(&self) -> Vec<(Decimal, Decimal)> {
+        self.asks.iter().map(|l| (l.price, l.qty)).collect()
+    }
+
+    fn is_stale(&self, update_id: u64) -> bool {
+        self.final_update_id < update_id
+    }
+
+    fn straddles(&self, last_update_id: u64) -> bool {
+        self.first_update_id <= last_update_id
+    }
+
+    fn follows(&self, final_update_id: u64) -> bool {
+        self.previous_final_update_id == final_update_id
+    }
+}
+
+impl DepthSnapshot for OrderBookPartial {
+    fn last_update_id(&self) -> u64 {
+        self.last_update_id
+    }
+
+    fn event_time(&self) -> Option<u64> {
+        Some(self.event_time)
+    }
+
+    fn bids(&self) -> Vec<(Decimal, Decimal)> {
+        self.bids.iter().map(|l| (l.price, l.qty)).collect()
+    }
+
+    fn asks(&self) -> Vec<(Decimal, Decimal)> {
+        self.asks.iter().map(|l| (l.price, l.qty)).collect()
+    }
+}
+
+#[async_trait]
+impl DepthVenue for FuturesMarket {
+    type Update = DepthOrderBookEvent;
+    type Snapshot = OrderBookPartial;
+    type Error = Error;
+
+    /// Limits of 5, 10, 20, 50, 100, 500 or 1000 levels
+    async fn snapshot(&self, symbol: &str, limit: u16) -> Result<OrderBookPartial> {
+        self.get_custom_depth(symbol, limit).await
+    }
+}
+#[cfg(test)]
+mod test {
+    use super::*;
+    use crate::binance_f::rest_model::{Asks, Bids};
+    use rust_decimal_macros::dec;
+
+    fn depth(first: u64, last: u64, prev: u64, bid: (Decimal, Decimal)) -> DepthOrderBookEvent {
+        DepthOrderBookEvent {
+            event_time: last,
+            symbol: "BTCUSDT".into(),
+            first_update_id: first,
+            final_update_id: last,
+            previous_final_update_id: prev,
+            bids: vec![Bids {
+                price: bid.0,
+                qty: bid.1,
+            }],
+            asks: vec![],
+        }
+    }
+
+    #[test]
+    fn test_sync_procedure() {
+        let mut book = Orderbook::new("BTCUSDT".into());
+        assert_eq!(
+            book.apply(&depth(1, 2, 0, (dec!(10), dec!(1)))),
+            BookSync::Gap
+        );
+
+        book.partial(&OrderBookPartial {
+            last_update_id: 100,
+            event_time: 0,
+            transaction_time: 0,
+            bids: vec![Bids {
+                price: dec!(10),
+                qty: dec!(1),
+            }],
+            asks: vec![Asks {
+                price: dec!(11),
+                qty: dec!(1),
+            }],
+        });
+        assert_eq!(
+            book.apply(&depth(90, 99, 89, (dec!(10), dec!(5)))),
+            BookSync::Stale
+        );
+        assert_eq!(
+            book.apply(&depth(95, 105, 94, (dec!(10), dec!(0)))),
+            BookSync::Applied
+        );
+        assert_eq!(book.bid_price(), None);
+        assert_eq!(
+            book.apply(&depth(106, 110, 105, (dec!(9), dec!(2)))),
+            BookSync::Applied
+        );
+        assert_eq!(book.best_bid(), Some((dec!(9), dec!(2))));
+        // pu must be the previous u
+        assert_eq!(
+            book.apply(&depth(115, 120, 112, (dec!(9), dec!(3)))),
+            BookSync::Gap
+        );
+    }
+}
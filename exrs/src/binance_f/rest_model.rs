@@ -382,6 +382,8 @@ pub struct Order {
 #[serde(rename_all = "camelCase")]
 pub struct Transaction {
     pub client_order_id: String,
+    #[serde(default, with = "string_or_float")]
+    pub price: f64,
     #[serde(with = "string_or_float")]
     pub cum_qty: f64,
     #[serde(with = "string_or_float")]
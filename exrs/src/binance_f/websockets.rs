@@ -1,6 +1,8 @@
 use super::config::*;
 use super::errors::*;
+use crate::supervisor::Supervised;
 
+use async_trait::async_trait;
 use awc::ws::Message;
 use log::debug;
 use std::sync::atomic::{AtomicBool, Ordering};
@@ -78,6 +80,8 @@ pub struct FuturesWebSockets<WE: serde::de::DeserializeOwned + std::fmt::Debug>
     pub socket: Option<(ClientResponse, Framed<BoxedSocket, Codec>)>,
     sender: mpsc::Sender<WE>,
     conf: Config,
+    /// What `reconnect` replays
+    endpoint: Option<String>,
 }
 
 impl<WE: serde::de::DeserializeOwned + std::fmt::Debug> FuturesWebSockets<WE> {
@@ -96,6 +100,7 @@ impl<WE: serde::de::DeserializeOwned + std::fmt::Debug> FuturesWebSockets<WE> {
             socket: None,
             sender: sender,
             conf,
+            endpoint: None,
         }
     }
 
@@ -113,6 +118,7 @@ impl<WE: serde::de::DeserializeOwned + std::fmt::Debug> FuturesWebSockets<WE> {
         match client.ws(wss).connect().await {
             Ok(answer) => {
                 self.socket = Some(answer);
+                self.endpoint = Some(endpoint.to_string());
                 Ok(())
             }
             Err(e) => Err(Error::Msg(format!("Error during handshake {}", e))),
@@ -133,6 +139,15 @@ impl<WE: serde::de::DeserializeOwned + std::fmt::Debug> FuturesWebSockets<WE> {
         &self.socket
     }
 
+    /// Connects to the last endpoint again, the streams are part of it
+    pub async fn reconnect(&mut self) -> Result<()> {
+        let endpoint = match &self.endpoint {
+            Some(endpoint) => endpoint.clone(),
+            None => return Err(Error::Msg("Never connected".to_string())),
+        };
+        self.connect(&endpoint).await
+    }
+
     pub async fn event_loop(&mut self, running: &AtomicBool) -> Result<()> {
         while running.load(Ordering::Relaxed) {
             if let Some((_, ref mut socket)) = self.socket {
@@ -171,3 +186,20 @@ impl<WE: serde::de::DeserializeOwned + std::fmt::Debug> FuturesWebSockets<WE> {
         Ok(())
     }
 }
+
+#[async_trait(?Send)]
+impl<WE: serde::de::DeserializeOwned + std::fmt::Debug> Supervised for FuturesWebSockets<WE> {
+    type Error = Error;
+
+    fn is_connected(&self) -> bool {
+        self.socket.is_some()
+    }
+
+    async fn reconnect(&mut self) -> Result<()> {
+        FuturesWebSockets::reconnect(self).await
+    }
+
+    async fn event_loop(&mut self, running: &AtomicBool) -> Result<()> {
+        FuturesWebSockets::event_loop(self, running).await
+    }
+}
@@ -0,0 +1,510 @@
+//! Local order book kept in sync from a depth snapshot and a diff depth stream, shared by the
+//! Binance spot and futures order books.
+//!
+//! Follows the procedure from the binance docs: buffer the stream, fetch a snapshot, drop
+//! events older than it, start from the event straddling it and then require every event to
+//! continue the previous one. The venues only differ in how their update ids chain, which each
+//! event type states through `DepthUpdate`. A broken chain refetches the snapshot.
+
+use std::collections::{BTreeMap, VecDeque};
+use std::fmt;
+use std::time::{Duration, Instant};
+
+use async_trait::async_trait;
+use log::{debug, warn};
+use rust_decimal::Decimal;
+use tokio::sync::mpsc;
+
+/// First pause before fetching another snapshot when the last one could not be used
+const MIN_RETRY_DELAY: Duration = Duration::from_millis(250);
+const MAX_RETRY_DELAY: Duration = Duration::from_secs(8);
+/// Events kept while out of sync, the oldest are dropped beyond it
+const MAX_PENDING: usize = 10_000;
+
+/// Outcome of applying a depth event
+#[derive(Debug, Copy, Clone, PartialEq, Eq)]
+pub enum BookSync {
+    /// The event moved the book forward
+    Applied,
+    /// The event is older than the book and was dropped
+    Stale,
+    /// The event does not continue the book, a new snapshot is needed
+    Gap,
+}
+
+/// Diff depth event with the update id rules of its venue
+pub trait DepthUpdate {
+    fn symbol(&self) -> &str;
+    fn event_time(&self) -> u64;
+    fn first_update_id(&self) -> u64;
+    fn final_update_id(&self) -> u64;
+    /// Levels as (price, quantity), a quantity of 0 removes the level
+    fn bids(&self) -> Vec<(Decimal, Decimal)>;
+    fn asks(&self) -> Vec<(Decimal, Decimal)>;
+    /// Whether a book at `update_id` already contains the event
+    fn is_stale(&self, update_id: u64) -> bool;
+    /// Whether the event is the first to apply on a snapshot at `last_update_id`
+    fn straddles(&self, last_update_id: u64) -> bool;
+    /// Whether the event directly follows the one that ended at `final_update_id`
+    fn follows(&self, final_update_id: u64) -> bool;
+}
+
+/// REST depth snapshot
+pub trait DepthSnapshot {
+    fn last_update_id(&self) -> u64;
+    /// None for venues that do not timestamp their snapshots
+    fn event_time(&self) -> Option<u64>;
+    fn bids(&self) -> Vec<(Decimal, Decimal)>;
+    fn asks(&self) -> Vec<(Decimal, Decimal)>;
+}
+
+/// Market client of a venue with the depth snapshot endpoint and the events that go with it
+#[async_trait]
+pub trait DepthVenue: Send + Sync {
+    type Update: DepthUpdate + Clone + Send + Sync;
+    type Snapshot: DepthSnapshot + Send;
+    type Error: fmt::Display + Send;
+
+    async fn snapshot(&self, symbol: &str, limit: u16) -> Result<Self::Snapshot, Self::Error>;
+}
+
+#[derive(Debug, Serialize, Deserialize, Clone)]
+pub struct Orderbook {
+    pub symbol: String,
+    pub timestamp: u64,
+    pub final_update_id: u64,
+    pub bids: BTreeMap<Decimal, Decimal>,
+    pub asks: BTreeMap<Decimal, Decimal>,
+    /// No snapshot loaded yet
+    #[serde(skip)]
+    empty: bool,
+    /// Snapshot loaded but no event applied on top of it yet
+    #[serde(skip)]
+    from_snapshot: bool,
+}
+
+fn merge(side: &mut BTreeMap<Decimal, Decimal>, levels: Vec<(Decimal, Decimal)>) {
+    for (price, qty) in levels {
+        if qty.is_zero() {
+            side.remove(&price);
+        } else {
+            side.insert(price, qty);
+        }
+    }
+}
+
+impl Orderbook {
+    pub fn new(symbol: String) -> Orderbook {
+        Orderbook {
+            symbol,
+            timestamp: 0,
+            final_update_id: 0,
+            bids: BTreeMap::new(),
+            asks: BTreeMap::new(),
+            empty: true,
+            from_snapshot: false,
+        }
+    }
+
+    /// True once a snapshot has been loaded
+    pub fn has_snapshot(&self) -> bool {
+        !self.empty
+    }
+
+    /// Drops the content, the book needs a new snapshot afterwards
+    pub fn clear(&mut self) {
+        self.bids.clear();
+        self.asks.clear();
+        self.final_update_id = 0;
+        self.empty = true;
+        self.from_snapshot = false;
+    }
+
+    /// Replaces the book with a REST depth snapshot
+    pub fn partial<S: DepthSnapshot>(&mut self, data: &S) {
+        self.bids = data.bids().into_iter().collect();
+        self.asks = data.asks().into_iter().collect();
+        self.final_update_id = data.last_update_id();
+        // without a snapshot time `timestamp` stays at the last event
+        if let Some(ts) = data.event_time() {
+            self.timestamp = ts;
+        }
+        self.empty = false;
+        self.from_snapshot = true;
+    }
+
+    /// Merges a depth event without any continuity check
+    pub fn update<E: DepthUpdate>(&mut self, data: &E) {
+        self.final_update_id = data.final_update_id();
+        self.timestamp = data.event_time();
+        merge(&mut self.bids, data.bids());
+        merge(&mut self.asks, data.asks());
+    }
+
+    /// Merges a depth event if it continues the book
+    pub fn apply<E: DepthUpdate>(&mut self, data: &E) -> BookSync {
+        if self.empty {
+            return BookSync::Gap;
+        }
+        if data.is_stale(self.final_update_id) {
+            return BookSync::Stale;
+        }
+        let continues = if self.from_snapshot {
+            data.straddles(self.final_update_id)
+        } else {
+            data.follows(self.final_update_id)
+        };
+        if !continues {
+            return BookSync::Gap;
+        }
+        self.update(data);
+        self.from_snapshot = false;
+        BookSync::Applied
+    }
+
+    /// Whether the best bid is at or above the best ask
+    pub fn is_crossed(&self) -> bool {
+        match (self.bid_price(), self.ask_price()) {
+            (Some(bid), Some(ask)) => bid >= ask,
+            _ => false,
+        }
+    }
+
+    /// Returns the price of the best bid
+    pub fn bid_price(&self) -> Option<Decimal> {
+        self.bids.keys().next_back().cloned()
+    }
+
+    /// Returns the price of the best ask
+    pub fn ask_price(&self) -> Option<Decimal> {
+        self.asks.keys().next().cloned()
+    }
+
+    /// Returns the midpoint between the best bid price and best ask price.
+    /// Output is not rounded to the smallest price increment.
+    pub fn mid_price(&self) -> Option<Decimal> {
+        Some((self.bid_price()? + self.ask_price()?) / Decimal::from(2))
+    }
+
+    /// Returns the price and quantity of the best bid
+    /// (bid_price, bid_quantity)
+    pub fn best_bid(&self) -> Option<(Decimal, Decimal)> {
+        let (price, qty) = self.bids.iter().next_back()?;
+
+        Some((*price, *qty))
+    }
+
+    /// Returns the price and quantity of the best ask
+    /// (ask_price, ask_quantity)
+    pub fn best_ask(&self) -> Option<(Decimal, Decimal)> {
+        let (price, qty) = self.asks.iter().next()?;
+
+        Some((*price, *qty))
+    }
+
+    /// Returns the price and quantity of the best bid and best ask
+    /// ((bid_price, bid_quantity), (ask_price, ask_quantity))
+    pub fn best_bid_and_ask(&self) -> Option<((Decimal, Decimal), (Decimal, Decimal))> {
+        Some((self.best_bid()?, self.best_ask()?))
+    }
+
+    /// Top `depth` bids and asks as (price, quantity), best first
+    pub fn depth(&self, depth: usize) -> (Vec<(Decimal, Decimal)>, Vec<(Decimal, Decimal)>) {
+        let bids = self
+            .bids
+            .iter()
+            .rev()
+            .take(depth)
+            .map(|(p, q)| (*p, *q))
+            .collect();
+        let asks = self
+            .asks
+            .iter()
+            .take(depth)
+            .map(|(p, q)| (*p, *q))
+            .collect();
+        (bids, asks)
+    }
+
+    /// Copy of the book cut to the top `depth` levels of each side
+    pub fn top(&self, depth: usize) -> Orderbook {
+        let (bids, asks) = self.depth(depth);
+        Orderbook {
+            symbol: self.symbol.clone(),
+            timestamp: self.timestamp,
+            final_update_id: self.final_update_id,
+            bids: bids.into_iter().collect(),
+            asks: asks.into_iter().collect(),
+            empty: self.empty,
+            from_snapshot: self.from_snapshot,
+        }
+    }
+}
+
+/// Keeps an `Orderbook` in sync from diff depth events, resyncing through the venue's depth
+/// snapshot, and publishes the top of the book after every update.
+///
+/// Events are buffered while the book is out of sync and replayed on the next snapshot. A
+/// snapshot older than the buffered events is dropped and the next one is only fetched after
+/// a growing delay, so a lagging snapshot endpoint does not get one request per event.
+pub struct OrderbookManager<V: DepthVenue> {
+    venue: V,
+    book: Orderbook,
+    snapshot_limit: u16,
+    publish_depth: usize,
+    sender: mpsc::Sender<Orderbook>,
+    pending: VecDeque<V::Update>,
+    retry_delay: Duration,
+    next_snapshot: Instant,
+}
+
+impl<V: DepthVenue> OrderbookManager<V> {
+    pub fn new(venue: V, symbol: impl Into<String>, sender: mpsc::Sender<Orderbook>) -> Self {
+        OrderbookManager {
+            venue,
+            book: Orderbook::new(symbol.into().to_uppercase()),
+            snapshot_limit: 1000,
+            publish_depth: 20,
+            sender,
+            pending: VecDeque::new(),
+            retry_delay: MIN_RETRY_DELAY,
+            next_snapshot: Instant::now(),
+        }
+    }
+
+    /// Levels requested for the snapshot, one of the limits the venue's depth endpoint accepts
+    pub fn with_snapshot_limit(mut self, limit: u16) -> Self {
+        self.snapshot_limit = limit;
+        self
+    }
+
+    /// Levels per side in the published books
+    pub fn with_publish_depth(mut self, depth: usize) -> Self {
+        self.publish_depth = depth;
+        self
+    }
+
+    pub fn book(&self) -> &Orderbook {
+        &self.book
+    }
+
+    /// Consumes depth events until the stream closes or the receiver of the books is gone
+    pub async fn run(mut self, mut rx: mpsc::Receiver<V::Update>) -> Result<(), V::Error> {
+        while let Some(event) = rx.recv().await {
+            if let Err(e) = self.on_event(&event).await {
+                warn!(
+                    "{} order book resync failed: {}, retrying",
+                    self.book.symbol, e
+                );
+            }
+            if self.sender.is_closed() {
+                break;
+            }
+        }
+        Ok(())
+    }
+
+    /// Applies one depth event, fetching a snapshot first if the book has none or the event
+    /// breaks the update chain
+    pub async fn on_event(&mut self, event: &V::Update) -> Result<(), V::Error> {
+        if event.symbol() != self.book.symbol {
+            return Ok(());
+        }
+        if self.book.has_snapshot() {
+            match self.book.apply(event) {
+                BookSync::Applied => {
+                    self.publish().await;
+                    return Ok(());
+                }
+                BookSync::Stale => return Ok(()),
+                BookSync::Gap => {
+                    warn!(
+                        "{} depth gap, U {} after u {}, resyncing",
+                        self.book.symbol,
+                        event.first_update_id(),
+                        self.book.final_update_id
+                    );
+                    self.book.clear();
+                }
+            }
+        }
+
+        if self.pending.len() == MAX_PENDING {
+            self.pending.pop_front();
+        }
+        self.pending.push_back(event.clone());
+        self.resync().await
+    }
+
+    /// Fetches a snapshot unless the retry delay is still running and replays the buffered
+    /// events on it
+    async fn resync(&mut self) -> Result<(), V::Error> {
+        if Instant::now() < self.next_snapshot {
+            return Ok(());
+        }
+        let snapshot = match self
+            .venue
+            .snapshot(&self.book.symbol, self.snapshot_limit)
+            .await
+        {
+            Ok(snapshot) => snapshot,
+            Err(e) => {
+                self.back_off();
+                return Err(e);
+            }
+        };
+        debug!(
+            "{} snapshot at {}",
+            self.book.symbol,
+            snapshot.last_update_id()
+        );
+        self.book.partial(&snapshot);
+
+        let mut applied = false;
+        while let Some(event) = self.pending.pop_front() {
+            match self.book.apply(&event) {
+                BookSync::Applied => applied = true,
+                BookSync::Stale => {}
+                BookSync::Gap => {
+                    warn!(
+                        "{} snapshot at {} behind the stream at {}, retrying in {:?}",
+                        self.book.symbol,
+                        snapshot.last_update_id(),
+                        event.first_update_id(),
+                        self.retry_delay
+                    );
+                    self.book.clear();
+                    self.pending.push_front(event);
+                    self.back_off();
+                    return Ok(());
+                }
+            }
+        }
+        self.retry_delay = MIN_RETRY_DELAY;
+        if applied {
+            self.publish().await;
+        }
+        Ok(())
+    }
+
+    fn back_off(&mut self) {
+        self.next_snapshot = Instant::now() + self.retry_delay;
+        self.retry_delay = (self.retry_delay * 2).min(MAX_RETRY_DELAY);
+    }
+
+    async fn publish(&self) {
+        if self
+            .sender
+            .send(self.book.top(self.publish_depth))
+            .await
+            .is_err()
+        {
+            debug!("{} order book receiver dropped", self.book.symbol);
+        }
+    }
+}
+
+#[cfg(test)]
+mod test {
+    use super::*;
+    use rust_decimal_macros::dec;
+    use std::sync::Mutex;
+
+    /// Update ids chain like the spot stream, each event starts right after the previous one
+    #[derive(Debug, Clone)]
+    struct Update(u64, u64);
+
+    impl DepthUpdate for Update {
+        fn symbol(&self) -> &str {
+            "BTCUSDT"
+        }
+        fn event_time(&self) -> u64 {
+            self.1
+        }
+        fn first_update_id(&self) -> u64 {
+            self.0
+        }
+        fn final_update_id(&self) -> u64 {
+            self.1
+        }
+        fn bids(&self) -> Vec<(Decimal, Decimal)> {
+            vec![(dec!(10), Decimal::from(self.1))]
+        }
+        fn asks(&self) -> Vec<(Decimal, Decimal)> {
+            vec![]
+        }
+        fn is_stale(&self, update_id: u64) -> bool {
+            self.1 <= update_id
+        }
+        fn straddles(&self, last_update_id: u64) -> bool {
+            self.0 <= last_update_id + 1
+        }
+        fn follows(&self, final_update_id: u64) -> bool {
+            self.0 == final_update_id + 1
+        }
+    }
+
+    struct Snapshot(u64);
+
+    impl DepthSnapshot for Snapshot {
+        fn last_update_id(&self) -> u64 {
+            self.0
+        }
+        fn event_time(&self) -> Option<u64> {
+            None
+        }
+        fn bids(&self) -> Vec<(Decimal, Decimal)> {
+            vec![(dec!(10), dec!(1))]
+        }
+        fn asks(&self) -> Vec<(Decimal, Decimal)> {
+            vec![]
+        }
+    }
+
+    /// Serves the queued snapshot ids and counts the requests
+    struct Venue(Mutex<(Vec<u64>, usize)>);
+
+    #[async_trait]
+    impl DepthVenue for Venue {
+        type Update = Update;
+        type Snapshot = Snapshot;
+        type Error = String;
+
+        async fn snapshot(&self, _symbol: &str, _limit: u16) -> Result<Snapshot, String> {
+            let mut state = self.0.lock().unwrap();
+            state.1 += 1;
+            Ok(Snapshot(state.0.remove(0)))
+        }
+    }
+
+    #[tokio::test]
+    async fn test_snapshot_behind_stream() {
+        let (tx, mut rx) = mpsc::channel(16);
+        let venue = Venue(Mutex::new((vec![100, 150], 0)));
+        let mut manager = OrderbookManager::new(venue, "btcusdt", tx);
+
+        // the first snapshot lags the stream, the events are kept for the next one
+        manager.on_event(&Update(120, 125)).await.unwrap();
+        assert!(!manager.book().has_snapshot());
+        for first in (126..146).step_by(5) {
+            manager.on_event(&Update(first, first + 4)).await.unwrap();
+        }
+        assert_eq!(manager.venue.0.lock().unwrap().1, 1);
+        assert_eq!(manager.pending.len(), 5);
+
+        // the next event after the delay gets a newer snapshot and catches up from the buffer
+        tokio::time::sleep(MIN_RETRY_DELAY).await;
+        manager.on_event(&Update(146, 152)).await.unwrap();
+        assert_eq!(manager.venue.0.lock().unwrap().1, 2);
+        assert!(manager.pending.is_empty());
+        assert_eq!(manager.book().final_update_id, 152);
+        assert_eq!(
+            rx.recv().await.unwrap().best_bid(),
+            Some((dec!(10), dec!(152)))
+        );
+
+        manager.on_event(&Update(153, 160)).await.unwrap();
+        assert_eq!(manager.book().final_update_id, 160);
+        assert_eq!(manager.venue.0.lock().unwrap().1, 2);
+    }
+}
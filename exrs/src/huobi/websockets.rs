@@ -1,5 +1,6 @@
 use super::config::*;
 use super::errors::*;
+use crate::supervisor::Supervised;
 use super::ws_model::WebsocketResponse;
 
 use log::debug;
@@ -7,6 +8,7 @@ use std::str::from_utf8;
 use std::sync::atomic::{AtomicBool, Ordering};
 
 use actix_codec::Framed;
+use async_trait::async_trait;
 use awc::{
     ws::{Codec, Frame, Message},
     BoxedSocket, Client, ClientResponse,
@@ -20,6 +22,9 @@ pub struct WebSockets<WE: serde::de::DeserializeOwned + std::fmt::Debug> {
     pub socket: Option<(ClientResponse, Framed<BoxedSocket, Codec>)>,
     sender: mpsc::Sender<WE>,
     conf: Config,
+    /// What `reconnect` replays
+    endpoint: Option<String>,
+    subscriptions: Vec<String>,
 }
 
 impl<WE: serde::de::DeserializeOwned + std::fmt::Debug> WebSockets<WE> {
@@ -38,6 +43,8 @@ impl<WE: serde::de::DeserializeOwned + std::fmt::Debug> WebSockets<WE> {
             socket: None,
             sender: sender,
             conf,
+            endpoint: None,
+            subscriptions: Vec::new(),
         }
     }
 
@@ -52,13 +59,21 @@ impl<WE: serde::de::DeserializeOwned + std::fmt::Debug> WebSockets<WE> {
         match client.ws(wss).connect().await {
             Ok(answer) => {
                 self.socket = Some(answer);
+                self.endpoint = Some(endpoint.to_string());
                 Ok(())
             }
             Err(e) => Err(Error::Msg(format!("Error during handshake {}", e))),
         }
     }
 
+    /// Sends a (un)subscription request, it is replayed on every `reconnect`
     pub async fn subscribe_request(&mut self, request: &str) -> Result<()> {
+        self.send_request(request).await?;
+        self.subscriptions.push(request.to_string());
+        Ok(())
+    }
+
+    async fn send_request(&mut self, request: &str) -> Result<()> {
         if let Some((_, ref mut socket)) = self.socket {
             socket.send(Message::Text(request.into())).await?;
             Ok(())
@@ -81,6 +96,19 @@ impl<WE: serde::de::DeserializeOwned + std::fmt::Debug> WebSockets<WE> {
         &self.socket
     }
 
+    /// Connects to the last endpoint again and replays the subscription requests
+    pub async fn reconnect(&mut self) -> Result<()> {
+        let endpoint = match &self.endpoint {
+            Some(endpoint) => endpoint.clone(),
+            None => return Err(Error::Msg("Never connected".to_string())),
+        };
+        self.connect(&endpoint).await?;
+        for request in self.subscriptions.clone() {
+            self.send_request(&request).await?;
+        }
+        Ok(())
+    }
+
     pub async fn event_loop(&mut self, running: &AtomicBool) -> Result<()> {
         while running.load(Ordering::Relaxed) {
             if let Some((_, ref mut socket)) = self.socket {
@@ -136,6 +164,23 @@ impl<WE: serde::de::DeserializeOwned + std::fmt::Debug> WebSockets<WE> {
     }
 }
 
+#[async_trait(?Send)]
+impl<WE: serde::de::DeserializeOwned + std::fmt::Debug> Supervised for WebSockets<WE> {
+    type Error = Error;
+
+    fn is_connected(&self) -> bool {
+        self.socket.is_some()
+    }
+
+    async fn reconnect(&mut self) -> Result<()> {
+        WebSockets::reconnect(self).await
+    }
+
+    async fn event_loop(&mut self, running: &AtomicBool) -> Result<()> {
+        WebSockets::event_loop(self, running).await
+    }
+}
+
 fn huobi_decompress(msg: Vec<u8>) -> Result<Vec<u8>> {
     let isize_start = msg.len() - 4;
     let isize_bytes = &msg[isize_start..];
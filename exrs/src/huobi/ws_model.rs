@@ -91,6 +91,9 @@ pub struct Kline {
 
 #[derive(Debug, Serialize, Deserialize, Clone)]
 pub struct TickerEvent {
+    /// market.$symbol.ticker, the tick itself has no symbol
+    #[serde(default)]
+    pub ch: String,
     #[serde(rename = "ts")]
     pub timestamp: u64,
     pub tick: Ticker,
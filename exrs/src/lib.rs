@@ -6,5 +6,9 @@ extern crate serde_qs as qs;
 
 pub mod binance;
 pub mod binance_f;
+pub mod depth_book;
 pub mod huobi;
+pub mod market_data;
 pub mod okex_v5;
+pub mod supervisor;
+pub mod trading;
@@ -0,0 +1,161 @@
+use super::{
+    Bbo, Exchange, L2Book, Level, MarketEvent, MarketEventKind, OrderUpdate, ToMarketEvents, Trade,
+};
+use crate::binance::rest_model::{Asks, Bids, OrderSide, OrderStatus};
+use crate::binance::ws_model::{WebsocketEvent, WebsocketEventUntag};
+use crate::trading::{self, parse_f64, Fill, Side};
+
+fn event(exchange_ts: u64, local_ts: u64, kind: MarketEventKind) -> MarketEvent {
+    MarketEvent {
+        exchange: Exchange::Binance,
+        exchange_ts,
+        local_ts,
+        kind,
+    }
+}
+
+fn bid_levels(bids: &[Bids]) -> Vec<Level> {
+    bids.iter()
+        .map(|b| Level::from_decimal(&b.price, &b.qty))
+        .collect()
+}
+
+fn ask_levels(asks: &[Asks]) -> Vec<Level> {
+    asks.iter()
+        .map(|a| Level::from_decimal(&a.price, &a.qty))
+        .collect()
+}
+
+/// The buyer being the maker means the seller took liquidity
+pub(crate) fn taker_side(is_buyer_maker: bool) -> Side {
+    if is_buyer_maker {
+        Side::Sell
+    } else {
+        Side::Buy
+    }
+}
+
+fn side(side: &OrderSide) -> Side {
+    match side {
+        OrderSide::Buy => Side::Buy,
+        OrderSide::Sell => Side::Sell,
+    }
+}
+
+fn order_status(status: &OrderStatus) -> trading::OrderStatus {
+    match status {
+        OrderStatus::PartialyFilled | OrderStatus::Trade => trading::OrderStatus::PartiallyFilled,
+        OrderStatus::Filled => trading::OrderStatus::Filled,
+        OrderStatus::Canceled => trading::OrderStatus::Canceled,
+        OrderStatus::Rejected => trading::OrderStatus::Rejected,
+        OrderStatus::Expired => trading::OrderStatus::Expired,
+        OrderStatus::New | OrderStatus::PendingCancel => trading::OrderStatus::New,
+    }
+}
+
+impl ToMarketEvents for WebsocketEvent {
+    fn to_market_events(&self, local_ts: u64) -> Vec<MarketEvent> {
+        let (exchange_ts, kind) = match self {
+            WebsocketEvent::AggTrade(trade) => (
+                trade.event_time,
+                MarketEventKind::Trade(Trade {
+                    symbol: trade.symbol.clone(),
+                    trade_id: trade.aggregated_trade_id.to_string(),
+                    price: parse_f64(&trade.price),
+                    qty: parse_f64(&trade.qty),
+                    taker_side: taker_side(trade.is_buyer_maker),
+                }),
+            ),
+            WebsocketEvent::Trade(trade) => (
+                trade.event_time,
+                MarketEventKind::Trade(Trade {
+                    symbol: trade.symbol.clone(),
+                    trade_id: trade.trade_id.to_string(),
+                    price: parse_f64(&trade.price),
+                    qty: parse_f64(&trade.qty),
+                    taker_side: taker_side(trade.is_buyer_maker),
+                }),
+            ),
+            WebsocketEvent::DepthOrderBook(depth) => (
+                depth.event_time,
+                MarketEventKind::L2Delta(L2Book {
+                    symbol: depth.symbol.clone(),
+                    bids: bid_levels(&depth.bids),
+                    asks: ask_levels(&depth.asks),
+                    first_update_id: Some(depth.first_update_id),
+                    last_update_id: Some(depth.final_update_id),
+                }),
+            ),
+            WebsocketEvent::OrderUpdate(order) => {
+                let fill = match order.execution_type {
+                    OrderStatus::Trade => Some(Fill {
+                        symbol: order.symbol.clone(),
+                        order_id: order.order_id.to_string(),
+                        client_order_id: order.client_order_id.clone(),
+                        trade_id: order.trade_id.to_string(),
+                        side: side(&order.side),
+                        price: order.last_executed_price,
+                        qty: order.qty_last_executed,
+                        fee: order.commission,
+                        fee_asset: order.commission_asset.clone().unwrap_or_default(),
+                        // `m` on an execution report is whether this order was the maker
+                        is_maker: order.is_buyer_maker,
+                        ts: order.trade_order_time,
+                    }),
+                    _ => None,
+                };
+                let avg_price = if order.cumulative_filled_qty > 0. {
+                    order.cumulative_quote_asset_transacted_qty / order.cumulative_filled_qty
+                } else {
+                    0.
+                };
+                let update = OrderUpdate {
+                    symbol: order.symbol.clone(),
+                    order_id: order.order_id.to_string(),
+                    client_order_id: order.client_order_id.clone(),
+                    side: side(&order.side),
+                    status: order_status(&order.current_order_status),
+                    price: order.price,
+                    qty: order.qty,
+                    filled_qty: order.cumulative_filled_qty,
+                    avg_price,
+                    fill,
+                };
+                (order.event_time, MarketEventKind::Order(update))
+            }
+            _ => return vec![],
+        };
+        vec![event(exchange_ts, local_ts, kind)]
+    }
+}
+
+impl ToMarketEvents for WebsocketEventUntag {
+    fn to_market_events(&self, local_ts: u64) -> Vec<MarketEvent> {
+        match self {
+            WebsocketEventUntag::WebsocketEvent(event) => event.to_market_events(local_ts),
+            // partial depth and book ticker carry no event time, partial depth no symbol either
+            WebsocketEventUntag::OrderBookPartial(book) => vec![event(
+                local_ts,
+                local_ts,
+                MarketEventKind::L2Snapshot(L2Book {
+                    symbol: String::new(),
+                    bids: bid_levels(&book.bids),
+                    asks: ask_levels(&book.asks),
+                    first_update_id: None,
+                    last_update_id: Some(book.last_update_id),
+                }),
+            )],
+            WebsocketEventUntag::BookTicker(ticker) => vec![event(
+                local_ts,
+                local_ts,
+                MarketEventKind::Bbo(Bbo {
+                    symbol: ticker.symbol.clone(),
+                    bid: ticker.best_bid,
+                    bid_qty: ticker.best_bid_qty,
+                    ask: ticker.best_ask,
+                    ask_qty: ticker.best_ask_qty,
+                }),
+            )],
+        }
+    }
+}
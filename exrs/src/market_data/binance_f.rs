@@ -0,0 +1,106 @@
+use super::binance::taker_side;
+use super::{
+    Bbo, Exchange, L2Book, Level, MarketEvent, MarketEventKind, OrderUpdate, PositionUpdate,
+    ToMarketEvents, Trade,
+};
+use crate::binance_f::rest_model::{Asks, Bids};
+use crate::binance_f::ws_model::FuturesWebsocketEvent;
+use crate::trading::binance_f::{order_status, side};
+use crate::trading::{parse_f64, Fill};
+
+fn event(exchange_ts: u64, local_ts: u64, kind: MarketEventKind) -> MarketEvent {
+    MarketEvent {
+        exchange: Exchange::BinanceFutures,
+        exchange_ts,
+        local_ts,
+        kind,
+    }
+}
+
+fn bid_levels(bids: &[Bids]) -> Vec<Level> {
+    bids.iter()
+        .map(|b| Level::from_decimal(&b.price, &b.qty))
+        .collect()
+}
+
+fn ask_levels(asks: &[Asks]) -> Vec<Level> {
+    asks.iter()
+        .map(|a| Level::from_decimal(&a.price, &a.qty))
+        .collect()
+}
+
+impl ToMarketEvents for FuturesWebsocketEvent {
+    fn to_market_events(&self, local_ts: u64) -> Vec<MarketEvent> {
+        match self {
+            FuturesWebsocketEvent::AggrTrades(trade) => vec![event(
+                trade.event_time,
+                local_ts,
+                MarketEventKind::Trade(Trade {
+                    symbol: trade.symbol.clone(),
+                    trade_id: trade.aggregated_trade_id.to_string(),
+                    price: parse_f64(&trade.price),
+                    qty: parse_f64(&trade.qty),
+                    taker_side: taker_side(trade.is_buyer_maker),
+                }),
+            )],
+            FuturesWebsocketEvent::BookTicker(ticker) => vec![event(
+                ticker.event_time,
+                local_ts,
+                MarketEventKind::Bbo(Bbo {
+                    symbol: ticker.symbol.clone(),
+                    bid: ticker.best_bid,
+                    bid_qty: ticker.best_bid_qty,
+                    ask: ticker.best_ask,
+                    ask_qty: ticker.best_ask_qty,
+                }),
+            )],
+            FuturesWebsocketEvent::DepthOrderBook(depth) => vec![event(
+                depth.event_time,
+                local_ts,
+                MarketEventKind::L2Delta(L2Book {
+                    symbol: depth.symbol.clone(),
+                    bids: bid_levels(&depth.bids),
+                    asks: ask_levels(&depth.asks),
+                    first_update_id: Some(depth.first_update_id),
+                    last_update_id: Some(depth.final_update_id),
+                }),
+            )],
+            FuturesWebsocketEvent::OrderTradeUpdate(update) => {
+                let order = &update.order_trade_update;
+                vec![event(
+                    update.event_time,
+                    local_ts,
+                    MarketEventKind::Order(OrderUpdate {
+                        symbol: order.symbol.clone(),
+                        order_id: order.order_id.to_string(),
+                        client_order_id: order.client_order_id.clone(),
+                        side: side(&order.side),
+                        status: order_status(&order.order_status),
+                        price: order.original_price,
+                        qty: order.original_quantity,
+                        filled_qty: order.order_filled_accumulated_quantity,
+                        avg_price: order.average_price,
+                        fill: Fill::from_binance_futures(order),
+                    }),
+                )]
+            }
+            FuturesWebsocketEvent::AccountUpdate(update) => update
+                .account_update
+                .positions
+                .iter()
+                .map(|p| {
+                    event(
+                        update.event_time,
+                        local_ts,
+                        MarketEventKind::Position(PositionUpdate {
+                            symbol: p.symbol.clone(),
+                            qty: p.position_amount,
+                            entry_price: p.entry_price,
+                        }),
+                    )
+                })
+                .collect(),
+            _ => vec![],
+        }
+    }
+}
@@ -0,0 +1,41 @@
+use super::{Bbo, Exchange, MarketEvent, MarketEventKind, ToMarketEvents};
+use crate::huobi::ws_model::WebsocketEvent;
+
+fn event(exchange_ts: u64, local_ts: u64, kind: MarketEventKind) -> MarketEvent {
+    MarketEvent {
+        exchange: Exchange::Huobi,
+        exchange_ts,
+        local_ts,
+        kind,
+    }
+}
+
+impl ToMarketEvents for WebsocketEvent {
+    fn to_market_events(&self, local_ts: u64) -> Vec<MarketEvent> {
+        match self {
+            WebsocketEvent::BBO(bbo) => vec![event(
+                bbo.tick.quote_time,
+                local_ts,
+                MarketEventKind::Bbo(Bbo {
+                    symbol: bbo.tick.symbol.clone(),
+                    bid: bbo.tick.bid,
+                    bid_qty: bbo.tick.bid_size,
+                    ask: bbo.tick.ask,
+                    ask_qty: bbo.tick.ask_size,
+                }),
+            )],
+            WebsocketEvent::Ticker(ticker) => vec![event(
+                ticker.timestamp,
+                local_ts,
+                MarketEventKind::Bbo(Bbo {
+                    symbol: ticker.ch.split('.').nth(1).unwrap_or_default().to_string(),
+                    bid: ticker.tick.bid,
+                    bid_qty: ticker.tick.bid_size,
+                    ask: ticker.tick.ask,
+                    ask_qty: ticker.tick.ask_size,
+                }),
+            )],
+            WebsocketEvent::Kline(_) => vec![],
+        }
+    }
+}
@@ -0,0 +1,139 @@
+//! Venue independent market data.
+//!
+//! Every venue specific websocket event converts into `MarketEvent`s through `ToMarketEvents`,
+//! so strategies and the recorder can consume Binance, Binance futures, OKX and Huobi streams
+//! with the same code. Prices and quantities are f64 like in `trading`.
+
+use rust_decimal::prelude::ToPrimitive;
+use rust_decimal::Decimal;
+use serde::{Deserialize, Serialize};
+
+use crate::trading::{Fill, OrderStatus, Side};
+
+mod binance;
+mod binance_f;
+mod huobi;
+mod okex_v5;
+
+#[derive(Debug, Copy, Clone, PartialEq, Eq, Serialize, Deserialize)]
+#[serde(rename_all = "snake_case")]
+pub enum Exchange {
+    Binance,
+    BinanceFutures,
+    Okex,
+    Huobi,
+}
+
+#[derive(Debug, Copy, Clone, PartialEq, Serialize, Deserialize)]
+pub struct Level {
+    pub price: f64,
+    /// 0 removes the level in a delta
+    pub qty: f64,
+}
+
+impl Level {
+    pub(crate) fn from_decimal(price: &Decimal, qty: &Decimal) -> Self {
+        Level {
+            price: price.to_f64().unwrap_or(0.),
+            qty: qty.to_f64().unwrap_or(0.),
+        }
+    }
+}
+
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct Bbo {
+    pub symbol: String,
+    pub bid: f64,
+    pub bid_qty: f64,
+    pub ask: f64,
+    pub ask_qty: f64,
+}
+
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct L2Book {
+    pub symbol: String,
+    /// Best first
+    pub bids: Vec<Level>,
+    /// Best first
+    pub asks: Vec<Level>,
+    /// Sequence range covered by the update, for venues that have one
+    pub first_update_id: Option<u64>,
+    pub last_update_id: Option<u64>,
+}
+
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct Trade {
+    pub symbol: String,
+    pub trade_id: String,
+    pub price: f64,
+    pub qty: f64,
+    /// Side of the aggressor
+    pub taker_side: Side,
+}
+
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct OrderUpdate {
+    pub symbol: String,
+    pub order_id: String,
+    pub client_order_id: String,
+    pub side: Side,
+    pub status: OrderStatus,
+    pub price: f64,
+    pub qty: f64,
+    pub filled_qty: f64,
+    pub avg_price: f64,
+    /// Set when the update reports a fill
+    pub fill: Option<Fill>,
+}
+
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct PositionUpdate {
+    pub symbol: String,
+    /// Signed position, negative when short
+    pub qty: f64,
+    pub entry_price: f64,
+}
+
+#[derive(Debug, Clone, Serialize, Deserialize)]
+#[serde(tag = "type", content = "data", rename_all = "snake_case")]
+pub enum MarketEventKind {
+    Bbo(Bbo),
+    L2Snapshot(L2Book),
+    L2Delta(L2Book),
+    Trade(Trade),
+    Order(OrderUpdate),
+    Position(PositionUpdate),
+}
+
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct MarketEvent {
+    pub exchange: Exchange,
+    /// Event time reported by the venue in ms, the local time when the venue sends none
+    pub exchange_ts: u64,
+    /// Local receive time in ms
+    pub local_ts: u64,
+    pub kind: MarketEventKind,
+}
+
+impl MarketEvent {
+    /// Receive latency in ms, 0 if the clocks are skewed the other way
+    pub fn latency(&self) -> u64 {
+        self.local_ts.saturating_sub(self.exchange_ts)
+    }
+
+    pub fn symbol(&self) -> &str {
+        match &self.kind {
+            MarketEventKind::Bbo(bbo) => &bbo.symbol,
+            MarketEventKind::L2Snapshot(book) | MarketEventKind::L2Delta(book) => &book.symbol,
+            MarketEventKind::Trade(trade) => &trade.symbol,
+            MarketEventKind::Order(order) => &order.symbol,
+            MarketEventKind::Position(position) => &position.symbol,
+        }
+    }
+}
+
+/// Conversion of a venue specific websocket event, `local_ts` is the time the message was read
+/// off the socket. Events without a normalized counterpart convert to nothing.
+pub trait ToMarketEvents {
+    fn to_market_events(&self, local_ts: u64) -> Vec<MarketEvent>;
+}
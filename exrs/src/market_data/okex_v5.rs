@@ -0,0 +1,186 @@
+use super::{
+    Bbo, Exchange, L2Book, Level, MarketEvent, MarketEventKind, OrderUpdate, PositionUpdate,
+    ToMarketEvents, Trade,
+};
+use crate::okex_v5::ws_model::{OrderBookEvent, WebsocketEvent};
+use crate::trading::okex_v5::{order_status, side};
+use crate::trading::{parse_f64, Fill};
+
+fn event(exchange_ts: u64, local_ts: u64, kind: MarketEventKind) -> MarketEvent {
+    MarketEvent {
+        exchange: Exchange::Okex,
+        exchange_ts,
+        local_ts,
+        kind,
+    }
+}
+
+/// OKX levels are [price, size, deprecated, number of orders]
+fn levels(levels: &[Vec<String>]) -> Vec<Level> {
+    levels
+        .iter()
+        .filter(|l| l.len() >= 2)
+        .map(|l| Level {
+            price: parse_f64(&l[0]),
+            qty: parse_f64(&l[1]),
+        })
+        .collect()
+}
+
+fn books(update: &OrderBookEvent, local_ts: u64) -> Vec<MarketEvent> {
+    let is_update = update.action.as_deref() == Some("update");
+    update
+        .data
+        .iter()
+        .map(|book| {
+            let symbol = if book.inst_id.is_empty() {
+                update.arg.inst_id.clone().unwrap_or_default()
+            } else {
+                book.inst_id.clone()
+            };
+            let l2 = L2Book {
+                symbol,
+                bids: levels(&book.bids),
+                asks: levels(&book.asks),
+                first_update_id: None,
+                last_update_id: None,
+            };
+            let kind = if is_update {
+                MarketEventKind::L2Delta(l2)
+            } else {
+                MarketEventKind::L2Snapshot(l2)
+            };
+            event(book.timestamp, local_ts, kind)
+        })
+        .collect()
+}
+
+impl ToMarketEvents for WebsocketEvent {
+    fn to_market_events(&self, local_ts: u64) -> Vec<MarketEvent> {
+        match self {
+            WebsocketEvent::Ticker(tickers) => tickers
+                .data
+                .iter()
+                .map(|ticker| {
+                    event(
+                        ticker.timestamp,
+                        local_ts,
+                        MarketEventKind::Bbo(Bbo {
+                            symbol: ticker.inst_id.clone(),
+                            bid: ticker.best_bid,
+                            bid_qty: ticker.best_bid_qty,
+                            ask: ticker.best_ask,
+                            ask_qty: ticker.best_ask_qty,
+                        }),
+                    )
+                })
+                .collect(),
+            WebsocketEvent::OrderBook(books_event) => books(books_event, local_ts),
+            WebsocketEvent::Trades(trades) => trades
+                .data
+                .iter()
+                .map(|trade| {
+                    event(
+                        trade.timestamp,
+                        local_ts,
+                        MarketEventKind::Trade(Trade {
+                            symbol: trade.inst_id.clone(),
+                            trade_id: trade.trade_id.clone(),
+                            price: trade.px,
+                            qty: trade.sz,
+                            taker_side: side(&trade.side),
+                        }),
+                    )
+                })
+                .collect(),
+            WebsocketEvent::Order(orders) => orders
+                .data
+                .iter()
+                .map(|order| {
+                    event(
+                        order.u_time.parse().unwrap_or(local_ts),
+                        local_ts,
+                        MarketEventKind::Order(OrderUpdate {
+                            symbol: order.inst_id.clone(),
+                            order_id: order.ord_id.clone(),
+                            client_order_id: order.cl_ord_id.clone(),
+                            side: side(&order.side),
+                            status: order_status(&order.state),
+                            price: parse_f64(&order.px),
+                            qty: parse_f64(&order.sz),
+                            filled_qty: parse_f64(&order.acc_fill_sz),
+                            avg_price: parse_f64(&order.avg_px),
+                            fill: Fill::from_okex(order),
+                        }),
+                    )
+                })
+                .collect(),
+            WebsocketEvent::Position(positions) => positions
+                .data
+                .iter()
+                .map(|p| {
+                    // in long/short mode `pos` is unsigned and the side is in `posSide`
+                    let qty = match p.pos_side.as_str() {
+                        "short" => -parse_f64(&p.pos).abs(),
+                        _ => parse_f64(&p.pos),
+                    };
+                    event(
+                        p.u_time.parse().unwrap_or(local_ts),
+                        local_ts,
+                        MarketEventKind::Position(PositionUpdate {
+                            symbol: p.inst_id.clone(),
+                            qty,
+                            entry_price: parse_f64(&p.avg_px),
+                        }),
+                    )
+                })
+                .collect(),
+            _ => vec![],
+        }
+    }
+}
+
+#[cfg(test)]
+mod test {
+    use super::*;
+    use crate::trading::Side;
+
+    fn convert(message: &str) -> Vec<MarketEvent> {
+        let event: WebsocketEvent = serde_json::from_str(message).unwrap();
+        event.to_market_events(1_650_000_000_100)
+    }
+
+    #[test]
+    fn test_okex_books_and_trades() {
+        let events = convert(
+            r#"{"arg":{"channel":"books","instId":"BTC-USDT"},"action":"update","data":[{"asks":[["41006.8","0","0","0"]],"bids":[["41006.3","0.3","0","2"]],"ts":"1650000000000","checksum":-855196043}]}"#,
+        );
+        assert_eq!(events.len(), 1);
+        assert_eq!(events[0].latency(), 100);
+        match &events[0].kind {
+            MarketEventKind::L2Delta(book) => {
+                assert_eq!(book.symbol, "BTC-USDT");
+                assert_eq!(book.asks[0].qty, 0.);
+                assert_eq!(book.bids[0].price, 41006.3);
+            }
+            kind => panic!("expected a delta, got {:?}", kind),
+        }
+
+        let events = convert(
+            r#"{"arg":{"channel":"books5","instId":"BTC-USDT"},"data":[{"asks":[["41006.8","0.6","0","1"]],"bids":[["41006.3","0.3","0","2"]],"instId":"BTC-USDT","ts":"1650000000000"}]}"#,
+        );
+        assert!(matches!(events[0].kind, MarketEventKind::L2Snapshot(_)));
+
+        let events = convert(
+            r#"{"arg":{"channel":"trades","instId":"BTC-USDT"},"data":[{"instId":"BTC-USDT","tradeId":"130639474","px":"42219.9","sz":"0.12","side":"sell","ts":"1650000000050"}]}"#,
+        );
+        match &events[0].kind {
+            MarketEventKind::Trade(trade) => {
+                assert_eq!((trade.price, trade.qty), (42219.9, 0.12));
+                assert_eq!(trade.taker_side, Side::Sell);
+            }
+            kind => panic!("expected a trade, got {:?}", kind),
+        }
+        assert_eq!(events[0].exchange_ts, 1_650_000_000_050);
+    }
+}
@@ -36,6 +36,7 @@ static API_V5_ORDERS_ALGO_PENDING: &str = "/api/v5/trade/orders-algo-pending";
 static API_V5_ORDERS_ALGO_HISTORY: &str = "/api/v5/trade/orders-algo-history";
 
 // account
+static API_V5_BALANCE: &str = "/api/v5/account/balance";
 static API_V5_POSITIONS: &str = "/api/v5/account/positions";
 static API_V5_ACCOUNT_POSITION_RISK: &str = "/api/v5/account/account-position-risk";
 static API_V5_BILLS: &str = "/api/v5/account/bills";
@@ -197,6 +198,238 @@ impl Account {
         self.client.post_signed_p(API_V5_CANCEL_ORDER, &order).await
     }
 
+    pub async fn place_order(&self, order: OrderRequest) -> Result<TransactionResponse> {
+        self.post_order(order).await
+    }
+
+    /// Place up to 20 orders in one request, e.g. both sides of a quote. Each order gets its
+    /// own `Transaction`, some may fail while the others go through.
+    pub async fn place_orders(&self, orders: Vec<OrderRequest>) -> Result<TransactionResponse> {
+        self.client
+            .post_signed_p(API_V5_BATCH_ORDERS, &orders)
+            .await
+    }
+
+    /// Amend the size and/or price of a live order
+    pub async fn amend_order(&self, request: AmendOrderRequest) -> Result<TransactionResponse> {
+        self.client
+            .post_signed_p(API_V5_AMEND_ORDER, &request)
+            .await
+    }
+
+    /// Amend up to 20 live orders in one request
+    pub async fn amend_orders(
+        &self,
+        requests: Vec<AmendOrderRequest>,
+    ) -> Result<TransactionResponse> {
+        self.client
+            .post_signed_p(API_V5_AMEND_BATCH_ORDERS, &requests)
+            .await
+    }
+
+    /// Live and partially filled orders
+    pub async fn get_orders_pending(
+        &self,
+        request: PendingOrdersRequest,
+    ) -> Result<RestResponse<OrderDetail>> {
+        self.client
+            .get_signed_p(API_V5_ORDERS_PENDING, Some(request))
+            .await
+    }
+
+    /// Canceled and filled orders of the last 7 days
+    pub async fn get_orders_history(
+        &self,
+        request: OrdersHistoryRequest,
+    ) -> Result<RestResponse<OrderDetail>> {
+        self.client
+            .get_signed_p(API_v5_ORDERS_HISTORY, Some(request))
+            .await
+    }
+
+    /// Canceled and filled orders of the last 3 months
+    pub async fn get_orders_history_archive(
+        &self,
+        request: OrdersHistoryRequest,
+    ) -> Result<RestResponse<OrderDetail>> {
+        self.client
+            .get_signed_p(API_V5_ORDERS_HISOTRY_ARCHIVE, Some(request))
+            .await
+    }
+
+    /// Fills of the last 3 days
+    pub async fn get_fills(&self, request: FillsRequest) -> Result<RestResponse<FillDetail>> {
+        self.client.get_signed_p(API_V5_FILLS, Some(request)).await
+    }
+
+    /// Fills of the last 3 months
+    pub async fn get_fills_history(
+        &self,
+        request: FillsRequest,
+    ) -> Result<RestResponse<FillDetail>> {
+        self.client
+            .get_signed_p(API_V5_FILLS_HISTORY, Some(request))
+            .await
+    }
+
+    pub async fn get_positions(
+        &self,
+        request: PositionsRequest,
+    ) -> Result<RestResponse<PositionDetail>> {
+        self.client
+            .get_signed_p(API_V5_POSITIONS, Some(request))
+            .await
+    }
+
+    /// Trading account balance, all currencies when `ccy` is None
+    pub async fn get_balance(&self, ccy: Option<String>) -> Result<RestResponse<Balance>> {
+        self.client
+            .get_signed_p(API_V5_BALANCE, Some(BalanceRequest { ccy }))
+            .await
+    }
+
+    /// Balances and positions at the same instant, `inst_type` narrows the positions
+    pub async fn get_account_position_risk(
+        &self,
+        inst_type: Option<String>,
+    ) -> Result<RestResponse<AccountPositionRisk>> {
+        self.client
+            .get_signed_p(
+                API_V5_ACCOUNT_POSITION_RISK,
+                Some(PositionsRequest {
+                    inst_type,
+                    ..Default::default()
+                }),
+            )
+            .await
+    }
+
+    /// Bills of the last 7 days
+    pub async fn get_bills(&self, request: BillsRequest) -> Result<RestResponse<Bill>> {
+        self.client.get_signed_p(API_V5_BILLS, Some(request)).await
+    }
+
+    /// Bills of the last 3 months
+    pub async fn get_bills_archive(&self, request: BillsRequest) -> Result<RestResponse<Bill>> {
+        self.client
+            .get_signed_p(API_V5_BILLS_ARCHIVE, Some(request))
+            .await
+    }
+
+    /// Account level, position mode and greeks settings
+    pub async fn get_account_config(&self) -> Result<RestResponse<AccountConfig>> {
+        let request: Option<PairQuery> = None;
+        self.client
+            .get_signed_p(API_V5_ACCOUNT_CONFIG, request)
+            .await
+    }
+
+    /// Switch between net and long/short mode, refused while positions or orders are open
+    pub async fn set_position_mode(
+        &self,
+        pos_mode: PositionMode,
+    ) -> Result<RestResponse<SetPositionModeRequest>> {
+        self.client
+            .post_signed_p(
+                API_V5_SET_POSITION_MODE,
+                &SetPositionModeRequest { pos_mode },
+            )
+            .await
+    }
+
+    pub async fn set_leverage(
+        &self,
+        request: SetLeverageRequest,
+    ) -> Result<RestResponse<Leverage>> {
+        self.client
+            .post_signed_p(API_V5_SET_LEVERAGE, &request)
+            .await
+    }
+
+    /// Largest order the available margin allows
+    pub async fn get_max_size(&self, request: MaxSizeRequest) -> Result<RestResponse<MaxSize>> {
+        self.client
+            .get_signed_p(API_V5_MAX_SIZE, Some(request))
+            .await
+    }
+
+    pub async fn get_max_avail_size(
+        &self,
+        request: MaxAvailSizeRequest,
+    ) -> Result<RestResponse<MaxAvailSize>> {
+        self.client
+            .get_signed_p(API_V5_MAX_AVAIL_SIZE, Some(request))
+            .await
+    }
+
+    /// Add margin to or reduce margin of an isolated position
+    pub async fn set_margin_balance(
+        &self,
+        request: MarginBalanceRequest,
+    ) -> Result<RestResponse<MarginBalance>> {
+        self.client
+            .post_signed_p(API_V5_MARGIN_BALANCE, &request)
+            .await
+    }
+
+    pub async fn get_leverage_info(
+        &self,
+        request: LeverageInfoRequest,
+    ) -> Result<RestResponse<Leverage>> {
+        self.client
+            .get_signed_p(API_V5_LEVERAGE_INFO, Some(request))
+            .await
+    }
+
+    pub async fn get_max_loan(&self, request: MaxLoanRequest) -> Result<RestResponse<MaxLoan>> {
+        self.client
+            .get_signed_p(API_V5_MAX_LOAN, Some(request))
+            .await
+    }
+
+    /// Fee tier and rates of the account
+    pub async fn get_trade_fee(&self, request: TradeFeeRequest) -> Result<RestResponse<TradeFee>> {
+        self.client
+            .get_signed_p(API_V5_TRADE_FEE, Some(request))
+            .await
+    }
+
+    pub async fn get_interest_accrued(
+        &self,
+        request: InterestAccruedRequest,
+    ) -> Result<RestResponse<InterestAccrued>> {
+        self.client
+            .get_signed_p(API_V5_INTEREST_ACCRUED, Some(request))
+            .await
+    }
+
+    /// Borrowing rates, all currencies when `ccy` is None
+    pub async fn get_interest_rate(
+        &self,
+        ccy: Option<String>,
+    ) -> Result<RestResponse<InterestRate>> {
+        self.client
+            .get_signed_p(API_V5_INTEREST_RATE, Some(BalanceRequest { ccy }))
+            .await
+    }
+
+    pub async fn set_greeks(
+        &self,
+        request: SetGreeksRequest,
+    ) -> Result<RestResponse<SetGreeksRequest>> {
+        self.client.post_signed_p(API_V5_SET_GREEKS, &request).await
+    }
+
+    /// Amount that can be withdrawn, all currencies when `ccy` is None
+    pub async fn get_max_withdrawal(
+        &self,
+        ccy: Option<String>,
+    ) -> Result<RestResponse<MaxWithdrawal>> {
+        self.client
+            .get_signed_p(API_V5_MAX_WITHDRAWAL, Some(BalanceRequest { ccy }))
+            .await
+    }
+
     pub async fn cancel_all_open_orders(
         &self,
         order: Vec<OrderCancellation>,
@@ -205,4 +438,52 @@ impl Account {
             .post_signed_p(API_V5_CANCEL_BATCH_ORDERS, &order)
             .await
     }
+
+    /// Place an algo order, the protective ones then live on the venue and trigger even while
+    /// disconnected
+    pub async fn place_algo_order(
+        &self,
+        order: AlgoOrderRequest,
+    ) -> Result<RestResponse<AlgoTransaction>> {
+        self.client.post_signed_p(API_V5_ORDER_ALGO, &order).await
+    }
+
+    /// Cancel up to 10 conditional, oco or trigger orders
+    pub async fn cancel_algo_orders(
+        &self,
+        orders: Vec<AlgoCancellation>,
+    ) -> Result<RestResponse<AlgoTransaction>> {
+        self.client
+            .post_signed_p(API_V5_CANCEL_ALGOS, &orders)
+            .await
+    }
+
+    /// Cancel up to 10 trailing stop, iceberg or twap orders
+    pub async fn cancel_advance_algo_orders(
+        &self,
+        orders: Vec<AlgoCancellation>,
+    ) -> Result<RestResponse<AlgoTransaction>> {
+        self.client
+            .post_signed_p(API_V5_CANCEL_ADVANCE_ALGOS, &orders)
+            .await
+    }
+
+    pub async fn get_algo_orders_pending(
+        &self,
+        request: AlgoOrdersRequest,
+    ) -> Result<RestResponse<AlgoOrderDetail>> {
+        self.client
+            .get_signed_p(API_V5_ORDERS_ALGO_PENDING, Some(request))
+            .await
+    }
+
+    /// Algo orders of the last 3 months in `request.state`
+    pub async fn get_algo_orders_history(
+        &self,
+        request: AlgoOrdersRequest,
+    ) -> Result<RestResponse<AlgoOrderDetail>> {
+        self.client
+            .get_signed_p(API_V5_ORDERS_ALGO_HISTORY, Some(request))
+            .await
+    }
 }
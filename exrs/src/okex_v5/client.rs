@@ -49,12 +49,13 @@ impl Client {
     }
 
     pub async fn get_signed(&self, endpoint: &str, request: &str) -> Result<String> {
-        let url = format!("{}{}?{}", self.host, endpoint, request);
+        let path = request_path(endpoint, request);
+        let url = format!("{}{}", self.host, path);
         let response = self
             .inner
             .clone()
             .get(url.as_str())
-            .headers(self.build_signed_headers(true, Method::GET, endpoint, request)?)
+            .headers(self.build_signed_headers(true, Method::GET, &path, "")?)
             .send()
             .await?;
 
@@ -192,16 +193,13 @@ impl Client {
         }
 
         let timestamp = Utc::now().to_rfc3339_opts(SecondsFormat::Millis, true);
-        let pre_hash = format!(
-            "{}{}{}{}",
-            timestamp,
-            method.as_str(),
+        let signature = sign(
+            &self.secret_key,
+            &timestamp,
+            &method,
             endpoint,
-            request_body
+            request_body,
         );
-        println!("pre_hash: {}", pre_hash);
-
-        let signature = base64::encode(HMAC::mac(pre_hash.as_bytes(), self.secret_key.as_bytes()));
 
         custom_headers.insert(
             HeaderName::from_static("ok-access-key"),
@@ -291,6 +289,7 @@ impl Client {
             StatusCode::INTERNAL_SERVER_ERROR => Err(Error::InternalServerError),
             StatusCode::SERVICE_UNAVAILABLE => Err(Error::ServiceUnavailable),
             StatusCode::UNAUTHORIZED => Err(Error::Unauthorized),
+            StatusCode::TOO_MANY_REQUESTS => Err(Error::TooManyRequests),
             StatusCode::BAD_REQUEST => {
                 let error: OkexContentError = response.json().await?;
                 Err(handle_content_error(error))
@@ -305,6 +304,47 @@ fn handle_content_error(error: OkexContentError) -> Error {
     match (error.code, error.msg.as_ref()) {
         (51006, error_messages::INVALID_PRICE) => Error::InvalidPrice,
         (59506, msg) => Error::InvalidListenKey(msg.to_string()),
+        (50011, _) => Error::TooManyRequests,
         _ => Error::OkexError { response: error },
     }
 }
+
+/// Path a GET request is sent to, GET requests are signed over it including the query string
+fn request_path(endpoint: &str, request: &str) -> String {
+    if request.is_empty() {
+        endpoint.to_string()
+    } else {
+        format!("{}?{}", endpoint, request)
+    }
+}
+
+/// OK-ACCESS-SIGN, base64 of the HMAC SHA256 of timestamp + method + request path + body
+fn sign(secret_key: &str, timestamp: &str, method: &Method, path: &str, body: &str) -> String {
+    let pre_hash = format!("{}{}{}{}", timestamp, method.as_str(), path, body);
+    base64::encode(HMAC::mac(pre_hash.as_bytes(), secret_key.as_bytes()))
+}
+
+#[cfg(test)]
+mod test {
+    use super::*;
+
+    #[test]
+    fn test_get_signature_covers_query() {
+        let path = request_path("/api/v5/account/balance", "ccy=BTC");
+        assert_eq!(path, "/api/v5/account/balance?ccy=BTC");
+        assert_eq!(
+            request_path("/api/v5/account/balance", ""),
+            "/api/v5/account/balance"
+        );
+        assert_eq!(
+            sign(
+                "secret",
+                "2020-12-08T09:08:57.715Z",
+                &Method::GET,
+                &path,
+                ""
+            ),
+            "wpDvCwYCprcMQsQkxWJiWy+YADoQE4ep+OEKKLimMoY="
+        );
+    }
+}
@@ -66,6 +66,22 @@ pub enum Error {
     ServiceUnavailable,
     #[error("Unauthorized")]
     Unauthorized,
+    #[error("too many requests")]
+    TooManyRequests,
+    #[error("order book {inst_id} checksum mismatch, expected {expected}, computed {computed}")]
+    ChecksumMismatch {
+        inst_id: String,
+        expected: i64,
+        computed: i64,
+    },
+    #[error("order book {inst_id} sequence gap, expected prevSeqId {expected}, got {prev_seq_id}")]
+    SequenceGap {
+        inst_id: String,
+        expected: i64,
+        prev_seq_id: i64,
+    },
+    #[error("order book {0} received an update before its snapshot")]
+    BookNotInitialized(String),
     #[error("{0}")]
     Msg(String),
 }
@@ -1,8 +1,81 @@
 use super::client::*;
 use super::errors::*;
 use super::rest_model::*;
+use super::util::get_timestamp;
+
+static API_V5_INSTRUMENTS: &str = "/api/v5/public/instruments";
+static API_V5_FUNDING_RATE: &str = "/api/v5/public/funding-rate";
+static API_V5_FUNDING_RATE_HISTORY: &str = "/api/v5/public/funding-rate-history";
+static API_V5_MARK_PRICE: &str = "/api/v5/public/mark-price";
+static API_V5_OPEN_INTEREST: &str = "/api/v5/public/open-interest";
+static API_V5_TIME: &str = "/api/v5/public/time";
+static API_V5_SYSTEM_STATUS: &str = "/api/v5/system/status";
 
 #[derive(Clone)]
 pub struct General {
     pub client: Client,
 }
+
+impl General {
+    /// Tick size, lot size, minimum size and contract value of the instruments
+    pub async fn get_instruments(
+        &self,
+        request: InstrumentsRequest,
+    ) -> Result<RestResponse<Instrument>> {
+        self.client.get_d(API_V5_INSTRUMENTS, Some(request)).await
+    }
+
+    /// Current and next funding rate of a perpetual swap
+    pub async fn get_funding_rate(&self, inst_id: String) -> Result<RestResponse<FundingRate>> {
+        self.client
+            .get_d(API_V5_FUNDING_RATE, Some(InstIdQuery { inst_id }))
+            .await
+    }
+
+    pub async fn get_funding_rate_history(
+        &self,
+        request: FundingRateHistoryRequest,
+    ) -> Result<RestResponse<FundingRateHistory>> {
+        self.client
+            .get_d(API_V5_FUNDING_RATE_HISTORY, Some(request))
+            .await
+    }
+
+    pub async fn get_mark_price(
+        &self,
+        request: InstrumentsRequest,
+    ) -> Result<RestResponse<MarkPrice>> {
+        self.client.get_d(API_V5_MARK_PRICE, Some(request)).await
+    }
+
+    pub async fn get_open_interest(
+        &self,
+        request: InstrumentsRequest,
+    ) -> Result<RestResponse<OpenInterest>> {
+        self.client.get_d(API_V5_OPEN_INTEREST, Some(request)).await
+    }
+
+    pub async fn get_server_time(&self) -> Result<RestResponse<ServerTime>> {
+        let request: Option<PairQuery> = None;
+        self.client.get_d(API_V5_TIME, request).await
+    }
+
+    /// Server time minus local time in ms, half the round trip is credited to the request
+    pub async fn get_time_offset(&self) -> Result<i64> {
+        let sent = get_timestamp()?;
+        let response = self.get_server_time().await?;
+        let received = get_timestamp()?;
+        let server = response.data.first().map(|t| t.ts).unwrap_or(received);
+        Ok(server as i64 - ((sent + received) / 2) as i64)
+    }
+
+    /// Scheduled and ongoing maintenance
+    pub async fn get_system_status(
+        &self,
+        state: Option<String>,
+    ) -> Result<RestResponse<SystemStatus>> {
+        self.client
+            .get_d(API_V5_SYSTEM_STATUS, Some(SystemStatusRequest { state }))
+            .await
+    }
+}
@@ -4,8 +4,15 @@ use super::client::*;
 use super::errors::*;
 use super::rest_model::*;
 use super::util::bool_to_string;
+use super::ws_model::{OrderBook, Ticker, Trade};
 
 static API_V5_CANDLES: &str = "/api/v5/market/candles";
+static API_V5_HISTORY_CANDLES: &str = "/api/v5/market/history-candles";
+static API_V5_TICKERS: &str = "/api/v5/market/tickers";
+static API_V5_TICKER: &str = "/api/v5/market/ticker";
+static API_V5_BOOKS: &str = "/api/v5/market/books";
+static API_V5_TRADES: &str = "/api/v5/market/trades";
+static API_V5_HISTORY_TRADES: &str = "/api/v5/market/history-trades";
 
 #[derive(Clone)]
 pub struct Market {
@@ -28,4 +35,60 @@ impl Market {
         println!("{:?}", req);
         self.client.get_d(API_V5_CANDLES, Some(req)).await
     }
-}
\ No newline at end of file
+
+    /// Candles older than the ones served by `get_candles`, at most 100 per request.
+    pub async fn get_history_candles(&self,
+        symbol:String,
+        bar:Option<String>,
+        after:Option<i64>,
+        before:Option<i64>,
+        limit:Option<u16>
+    ) -> Result<CandleResponse>
+    {
+        let req = CandleRequest {
+            symbol, bar, after, before, limit
+        };
+
+        self.client.get_d(API_V5_HISTORY_CANDLES, Some(req)).await
+    }
+
+    /// Tickers of every instrument of `inst_type`, `uly` narrows derivatives to an underlying
+    pub async fn get_tickers(
+        &self,
+        inst_type: String,
+        uly: Option<String>,
+    ) -> Result<RestResponse<Ticker>> {
+        let req = InstrumentsRequest {
+            inst_type,
+            uly,
+            inst_id: None,
+        };
+        self.client.get_d(API_V5_TICKERS, Some(req)).await
+    }
+
+    pub async fn get_ticker(&self, inst_id: String) -> Result<RestResponse<Ticker>> {
+        self.client
+            .get_d(API_V5_TICKER, Some(InstIdQuery { inst_id }))
+            .await
+    }
+
+    /// Order book snapshot, levels are [price, size, 0, orders] like on the websocket
+    pub async fn get_books(&self, request: BooksRequest) -> Result<RestResponse<OrderBook>> {
+        self.client.get_d(API_V5_BOOKS, Some(request)).await
+    }
+
+    /// Latest trades, up to 500
+    pub async fn get_trades(&self, request: TradesRequest) -> Result<RestResponse<Trade>> {
+        self.client.get_d(API_V5_TRADES, Some(request)).await
+    }
+
+    /// Trades of the last 3 months, to backfill what the websocket missed
+    pub async fn get_history_trades(
+        &self,
+        request: HistoryTradesRequest,
+    ) -> Result<RestResponse<Trade>> {
+        self.client
+            .get_d(API_V5_HISTORY_TRADES, Some(request))
+            .await
+    }
+}
@@ -8,6 +8,7 @@ pub mod config;
 pub mod general;
 pub mod margin;
 pub mod market;
+pub mod orderbook;
 pub mod rest_model;
 pub mod userstream;
 pub mod websockets;
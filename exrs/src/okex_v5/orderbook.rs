@@ -0,0 +1,428 @@
+//! Local OKX order books maintained from the books, books50-l2-tbt and books-l2-tbt channels.
+//!
+//! Snapshots replace the book, updates are merged level by level. Each push is checked against
+//! its `seqId`/`prevSeqId` and `checksum`, a book failing either is dropped and resubscribed.
+//! books5 and bbo-tbt are full snapshots on every push and work as well.
+
+use std::collections::{BTreeMap, HashMap};
+use std::str::FromStr;
+
+use log::warn;
+use rust_decimal::Decimal;
+use tokio::sync::mpsc;
+
+use super::errors::*;
+use super::ws_model::{self, Arg, OrderBookEvent, SubscriptionRequest, UnsubscriptionRequest};
+
+/// Levels covered by the OKX checksum
+const CHECKSUM_DEPTH: usize = 25;
+
+#[derive(Debug, Copy, Clone, PartialEq, Eq)]
+pub enum BookSide {
+    Bid,
+    Ask,
+}
+
+/// Size at a price, with the strings as pushed since the checksum is computed on them
+#[derive(Debug, Clone)]
+struct Level {
+    size: Decimal,
+    px: String,
+    sz: String,
+}
+
+#[derive(Debug, Clone)]
+pub struct OrderBook {
+    inst_id: String,
+    bids: BTreeMap<Decimal, Level>,
+    asks: BTreeMap<Decimal, Level>,
+    seq_id: Option<i64>,
+    timestamp: u64,
+    is_valid: bool,
+}
+
+impl OrderBook {
+    pub fn new(inst_id: impl Into<String>) -> Self {
+        OrderBook {
+            inst_id: inst_id.into(),
+            bids: BTreeMap::new(),
+            asks: BTreeMap::new(),
+            seq_id: None,
+            timestamp: 0,
+            is_valid: false,
+        }
+    }
+
+    pub fn inst_id(&self) -> &str {
+        &self.inst_id
+    }
+
+    /// Exchange time of the last applied push in ms
+    pub fn timestamp(&self) -> u64 {
+        self.timestamp
+    }
+
+    pub fn seq_id(&self) -> Option<i64> {
+        self.seq_id
+    }
+
+    /// False until the first snapshot and after a failed update
+    pub fn is_valid(&self) -> bool {
+        self.is_valid
+    }
+
+    /// Drops the content, only a snapshot makes the book valid again
+    pub fn invalidate(&mut self) {
+        self.bids.clear();
+        self.asks.clear();
+        self.seq_id = None;
+        self.is_valid = false;
+    }
+
+    /// Applies one push, `action` is the `action` of the event, None or "snapshot" replace the book.
+    /// The book is invalidated on any error.
+    pub fn apply(&mut self, action: Option<&str>, data: &ws_model::OrderBook) -> Result<()> {
+        let result = self.try_apply(action, data);
+        if result.is_err() {
+            self.invalidate();
+        }
+        result
+    }
+
+    fn try_apply(&mut self, action: Option<&str>, data: &ws_model::OrderBook) -> Result<()> {
+        if action == Some("update") {
+            if !self.is_valid {
+                return Err(Error::BookNotInitialized(self.inst_id.clone()));
+            }
+            if let (Some(expected), Some(prev_seq_id)) = (self.seq_id, data.prev_seq_id) {
+                if prev_seq_id != expected {
+                    return Err(Error::SequenceGap {
+                        inst_id: self.inst_id.clone(),
+                        expected,
+                        prev_seq_id,
+                    });
+                }
+            }
+        } else {
+            self.bids.clear();
+            self.asks.clear();
+        }
+
+        merge(&mut self.bids, &data.bids)?;
+        merge(&mut self.asks, &data.asks)?;
+
+        if let Some(expected) = data.checksum {
+            let computed = self.checksum();
+            if computed != expected {
+                return Err(Error::ChecksumMismatch {
+                    inst_id: self.inst_id.clone(),
+                    expected,
+                    computed,
+                });
+            }
+        }
+
+        self.seq_id = data.seq_id;
+        self.timestamp = data.timestamp;
+        self.is_valid = true;
+        Ok(())
+    }
+
+    /// CRC32 of `bid:size:ask:size:...` over the top 25 levels, as OKX computes it
+    pub fn checksum(&self) -> i64 {
+        let mut bids = self.bids.values().rev();
+        let mut asks = self.asks.values();
+        let mut fields: Vec<&str> = Vec::with_capacity(CHECKSUM_DEPTH * 4);
+        for _ in 0..CHECKSUM_DEPTH {
+            if let Some(bid) = bids.next() {
+                fields.push(&bid.px);
+                fields.push(&bid.sz);
+            }
+            if let Some(ask) = asks.next() {
+                fields.push(&ask.px);
+                fields.push(&ask.sz);
+            }
+        }
+        libdeflater::crc32(fields.join(":").as_bytes()) as i32 as i64
+    }
+
+    /// Best bid price and size
+    pub fn best_bid(&self) -> Option<(Decimal, Decimal)> {
+        self.bids.iter().next_back().map(|(px, l)| (*px, l.size))
+    }
+
+    /// Best ask price and size
+    pub fn best_ask(&self) -> Option<(Decimal, Decimal)> {
+        self.asks.iter().next().map(|(px, l)| (*px, l.size))
+    }
+
+    pub fn mid_price(&self) -> Option<Decimal> {
+        match (self.best_bid(), self.best_ask()) {
+            (Some((bid, _)), Some((ask, _))) => Some((bid + ask) / Decimal::from(2)),
+            _ => None,
+        }
+    }
+
+    /// Size resting at exactly `price`, zero if there is no level
+    pub fn depth_at(&self, side: BookSide, price: Decimal) -> Decimal {
+        self.side(side)
+            .get(&price)
+            .map(|l| l.size)
+            .unwrap_or(Decimal::ZERO)
+    }
+
+    /// Total size at `price` and every better level
+    pub fn cumulative_depth(&self, side: BookSide, price: Decimal) -> Decimal {
+        match side {
+            BookSide::Bid => self.bids.range(price..).map(|(_, l)| l.size).sum(),
+            BookSide::Ask => self.asks.range(..=price).map(|(_, l)| l.size).sum(),
+        }
+    }
+
+    /// Top `n` levels as (price, size), best first
+    pub fn levels(&self, side: BookSide, n: usize) -> Vec<(Decimal, Decimal)> {
+        match side {
+            BookSide::Bid => self
+                .bids
+                .iter()
+                .rev()
+                .take(n)
+                .map(|(px, l)| (*px, l.size))
+                .collect(),
+            BookSide::Ask => self
+                .asks
+                .iter()
+                .take(n)
+                .map(|(px, l)| (*px, l.size))
+                .collect(),
+        }
+    }
+
+    fn side(&self, side: BookSide) -> &BTreeMap<Decimal, Level> {
+        match side {
+            BookSide::Bid => &self.bids,
+            BookSide::Ask => &self.asks,
+        }
+    }
+}
+
+/// Levels come as [price, size, deprecated, number of orders], size 0 deletes the level
+fn merge(side: &mut BTreeMap<Decimal, Level>, levels: &[Vec<String>]) -> Result<()> {
+    for level in levels {
+        let (px, sz) = match level.as_slice() {
+            [px, sz, ..] => (px, sz),
+            _ => return Err(Error::Msg(format!("malformed book level {:?}", level))),
+        };
+        let price = Decimal::from_str(px).map_err(|_| Error::InvalidPrice)?;
+        let size = Decimal::from_str(sz).map_err(|_| Error::Msg(format!("invalid size {}", sz)))?;
+        if size.is_zero() {
+            side.remove(&price);
+        } else {
+            side.insert(
+                price,
+                Level {
+                    size,
+                    px: px.clone(),
+                    sz: sz.clone(),
+                },
+            );
+        }
+    }
+    Ok(())
+}
+
+/// Order books of every instrument subscribed on one book channel.
+///
+/// With a request sender from `WebSockets::request_sender`, a book that fails validation is
+/// unsubscribed and subscribed again to get a fresh snapshot, updates are skipped until then.
+pub struct OrderBooks {
+    channel: String,
+    books: HashMap<String, OrderBook>,
+    requests: Option<mpsc::UnboundedSender<String>>,
+}
+
+impl OrderBooks {
+    pub fn new(channel: impl Into<String>) -> Self {
+        OrderBooks {
+            channel: channel.into(),
+            books: HashMap::new(),
+            requests: None,
+        }
+    }
+
+    pub fn with_requests(mut self, requests: mpsc::UnboundedSender<String>) -> Self {
+        self.requests = Some(requests);
+        self
+    }
+
+    pub fn set_requests(&mut self, requests: mpsc::UnboundedSender<String>) {
+        self.requests = Some(requests);
+    }
+
+    pub fn channel(&self) -> &str {
+        &self.channel
+    }
+
+    pub fn book(&self, inst_id: &str) -> Option<&OrderBook> {
+        self.books.get(inst_id)
+    }
+
+    /// Subscription request for `inst_id` on this channel
+    pub fn subscribe_request(&self, inst_id: &str) -> String {
+        let request = SubscriptionRequest::new(vec![self.arg(inst_id)]);
+        serde_json::to_string(&request).unwrap()
+    }
+
+    fn unsubscribe_request(&self, inst_id: &str) -> String {
+        let request = UnsubscriptionRequest::new(vec![self.arg(inst_id)]);
+        serde_json::to_string(&request).unwrap()
+    }
+
+    fn arg(&self, inst_id: &str) -> Arg {
+        Arg::books(&self.channel, inst_id)
+    }
+
+    /// Applies a push of this channel and returns the instruments it touched. A book failing
+    /// validation is resubscribed and its error returned.
+    pub fn on_event(&mut self, event: &OrderBookEvent) -> Result<Vec<String>> {
+        if event.arg.channel != self.channel {
+            return Ok(vec![]);
+        }
+        let action = event.action.as_deref();
+        let mut touched = Vec::with_capacity(event.data.len());
+        for data in &event.data {
+            let inst_id = if data.inst_id.is_empty() {
+                event.arg.inst_id.clone().unwrap_or_default()
+            } else {
+                data.inst_id.clone()
+            };
+            let book = self
+                .books
+                .entry(inst_id.clone())
+                .or_insert_with(|| OrderBook::new(inst_id.clone()));
+
+            // waiting for the snapshot of a resubscription
+            if action == Some("update") && !book.is_valid() {
+                continue;
+            }
+            if let Err(e) = book.apply(action, data) {
+                warn!("{}, resubscribing {} {}", e, self.channel, inst_id);
+                self.resubscribe(&inst_id);
+                return Err(e);
+            }
+            touched.push(inst_id);
+        }
+        Ok(touched)
+    }
+
+    fn resubscribe(&self, inst_id: &str) {
+        if let Some(requests) = &self.requests {
+            let unsubscribe = self.unsubscribe_request(inst_id);
+            let subscribe = self.subscribe_request(inst_id);
+            if requests.send(unsubscribe).is_err() || requests.send(subscribe).is_err() {
+                warn!(
+                    "websocket gone, cannot resubscribe {} {}",
+                    self.channel, inst_id
+                );
+            }
+        }
+    }
+}
+
+#[cfg(test)]
+mod test {
+    use super::*;
+    use rust_decimal_macros::dec;
+
+    fn data(bids: &[(&str, &str)], asks: &[(&str, &str)]) -> ws_model::OrderBook {
+        let levels = |levels: &[(&str, &str)]| {
+            levels
+                .iter()
+                .map(|(px, sz)| vec![px.to_string(), sz.to_string(), "0".into(), "1".into()])
+                .collect()
+        };
+        ws_model::OrderBook {
+            asks: levels(asks),
+            bids: levels(bids),
+            inst_id: "BTC-USDT".into(),
+            timestamp: 1,
+            checksum: None,
+            prev_seq_id: None,
+            seq_id: None,
+        }
+    }
+
+    #[test]
+    fn test_checksum() {
+        // example from the OKX docs, "3366.1:7:3366.8:9:3366:6:3368:8"
+        let mut book = OrderBook::new("BTC-USDT");
+        let mut snapshot = data(
+            &[("3366.1", "7"), ("3366", "6")],
+            &[("3366.8", "9"), ("3368", "8")],
+        );
+        snapshot.checksum = Some(-1881014294);
+        book.apply(Some("snapshot"), &snapshot).unwrap();
+        assert!(book.is_valid());
+
+        let mut update = data(&[("3366.1", "0")], &[("3366.8", "2")]);
+        // "3366:6:3366.8:2:3368:8"
+        update.checksum = Some(607574175);
+        book.apply(Some("update"), &update).unwrap();
+        assert_eq!(book.best_bid(), Some((dec!(3366), dec!(6))));
+        assert_eq!(book.best_ask(), Some((dec!(3366.8), dec!(2))));
+
+        update.checksum = Some(0);
+        assert!(matches!(
+            book.apply(Some("update"), &update),
+            Err(Error::ChecksumMismatch { .. })
+        ));
+        assert!(!book.is_valid());
+    }
+
+    #[test]
+    fn test_sequence_gap() {
+        let mut book = OrderBook::new("BTC-USDT");
+        let mut snapshot = data(&[("10", "1")], &[("11", "1")]);
+        snapshot.prev_seq_id = Some(-1);
+        snapshot.seq_id = Some(5);
+        book.apply(None, &snapshot).unwrap();
+
+        let mut update = data(&[("10.5", "2")], &[]);
+        update.prev_seq_id = Some(6);
+        update.seq_id = Some(7);
+        assert!(matches!(
+            book.apply(Some("update"), &update),
+            Err(Error::SequenceGap {
+                expected: 5,
+                prev_seq_id: 6,
+                ..
+            })
+        ));
+        assert!(matches!(
+            book.apply(Some("update"), &update),
+            Err(Error::BookNotInitialized(_))
+        ));
+    }
+
+    #[test]
+    fn test_depth() {
+        let mut book = OrderBook::new("BTC-USDT");
+        book.apply(
+            None,
+            &data(
+                &[("10", "1"), ("9.5", "2"), ("9", "3")],
+                &[("11", "1.5"), ("12", "2.5")],
+            ),
+        )
+        .unwrap();
+        assert_eq!(book.mid_price(), Some(dec!(10.5)));
+        assert_eq!(book.depth_at(BookSide::Bid, dec!(9.5)), dec!(2));
+        assert_eq!(book.depth_at(BookSide::Ask, dec!(11.5)), Decimal::ZERO);
+        assert_eq!(book.cumulative_depth(BookSide::Bid, dec!(9.5)), dec!(3));
+        assert_eq!(book.cumulative_depth(BookSide::Ask, dec!(12)), dec!(4));
+        assert_eq!(
+            book.levels(BookSide::Bid, 2),
+            vec![(dec!(10), dec!(1)), (dec!(9.5), dec!(2))]
+        );
+    }
+}
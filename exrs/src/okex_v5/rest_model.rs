@@ -88,6 +88,17 @@ impl Default for TradeMode {
     }
 }
 
+impl TradeMode {
+    /// Cash for spot ids, which have a single dash (BTC-USDT), cross margin for swaps,
+    /// futures and options, which carry a suffix
+    pub fn for_inst_id(inst_id: &str) -> Self {
+        match inst_id.matches('-').count() {
+            1 => TradeMode::Cash,
+            _ => TradeMode::Cross,
+        }
+    }
+}
+
 #[derive(Debug, Serialize, Deserialize, Clone)]
 #[serde(rename_all = "snake_case")]
 pub enum MarginMode {
@@ -105,9 +116,9 @@ impl Default for MarginMode {
 #[derive(Debug, Clone, Serialize, Deserialize)]
 #[serde(rename_all = "camelCase")]
 pub struct OrderRequest {
-    #[serde(rename = "inst_id")]
+    #[serde(rename = "instId")]
     pub symbol: String,
-    #[serde(rename = "td_mode")]
+    #[serde(rename = "tdMode")]
     pub trade_mode: TradeMode,
     #[serde(rename = "ccy", skip_serializing_if = "Option::is_none")]
     pub currency: Option<String>,
@@ -118,7 +129,7 @@ pub struct OrderRequest {
     pub side: OrderSide,
     #[serde(rename = "posSide", skip_serializing_if = "Option::is_none")]
     pub position_side: Option<PositionSide>,
-    #[serde(rename = "ord_type")]
+    #[serde(rename = "ordType")]
     pub order_type: OrderType,
     #[serde(rename = "sz", with = "string_or_float")]
     pub qty: f64,
@@ -134,6 +145,15 @@ pub struct OrderRequest {
     pub target_currency: Option<String>,
 }
 
+/// Envelope shared by the v5 REST responses
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct RestResponse<T> {
+    #[serde(with = "string_or_u16")]
+    pub code: u16,
+    pub msg: String,
+    pub data: Vec<T>,
+}
+
 #[derive(Debug, Clone, Serialize, Deserialize)]
 pub struct TransactionResponse {
     #[serde(with = "string_or_u16")]
@@ -145,7 +165,10 @@ pub struct TransactionResponse {
 #[derive(Debug, Clone, Serialize, Deserialize)]
 #[serde(rename_all = "camelCase")]
 pub struct Transaction {
+    /// Missing in the results of requests rejected as a whole
+    #[serde(default)]
     pub cl_ord_id: String,
+    #[serde(default)]
     pub ord_id: String,
     #[serde(skip_serializing_if = "Option::is_none")]
     pub tag: Option<String>,
@@ -167,6 +190,546 @@ pub struct ClosePositionRequest {
     pub currency: Option<String>,
 }
 
+#[derive(Debug, Clone, Serialize, Deserialize)]
+#[serde(rename_all = "camelCase")]
+pub struct AmendOrderRequest {
+    #[serde(rename = "instId")]
+    pub symbol: String,
+    #[serde(rename = "ordId", skip_serializing_if = "Option::is_none")]
+    pub order_id: Option<String>,
+    #[serde(rename = "clOrdId", skip_serializing_if = "Option::is_none")]
+    pub client_order_id: Option<String>,
+    #[serde(
+        rename = "newSz",
+        with = "string_or_float_opt",
+        skip_serializing_if = "Option::is_none"
+    )]
+    pub new_qty: Option<f64>,
+    #[serde(
+        rename = "newPx",
+        with = "string_or_float_opt",
+        skip_serializing_if = "Option::is_none"
+    )]
+    pub new_price: Option<f64>,
+}
+
+#[derive(Debug, Clone, Default, Serialize, Deserialize)]
+#[serde(rename_all = "camelCase")]
+pub struct PendingOrdersRequest {
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub inst_type: Option<String>,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub inst_id: Option<String>,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub ord_type: Option<String>,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub state: Option<String>,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub after: Option<String>,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub before: Option<String>,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub limit: Option<u16>,
+}
+
+/// Order as returned by the order query endpoints, numbers are left as sent since most of
+/// them are empty strings until the order fills
+#[derive(Debug, Clone, Default, Serialize, Deserialize)]
+#[serde(rename_all = "camelCase", default)]
+pub struct OrderDetail {
+    pub inst_type: String,
+    pub inst_id: String,
+    pub ccy: String,
+    pub ord_id: String,
+    pub cl_ord_id: String,
+    pub tag: String,
+    pub px: String,
+    pub sz: String,
+    pub pnl: String,
+    pub ord_type: String,
+    pub side: String,
+    pub pos_side: String,
+    pub td_mode: String,
+    pub acc_fill_sz: String,
+    pub fill_px: String,
+    pub trade_id: String,
+    pub fill_sz: String,
+    pub fill_time: String,
+    pub avg_px: String,
+    pub state: String,
+    pub lever: String,
+    pub fee_ccy: String,
+    pub fee: String,
+    pub rebate_ccy: String,
+    pub rebate: String,
+    pub category: String,
+    pub u_time: String,
+    pub c_time: String,
+}
+
+/// Query of the order history, the last 7 days or the last 3 months for the archive.
+/// Pages go backwards from `after`, the order id of the oldest order of the previous page.
+#[derive(Debug, Clone, Default, Serialize, Deserialize)]
+#[serde(rename_all = "camelCase")]
+pub struct OrdersHistoryRequest {
+    /// SPOT, MARGIN, SWAP, FUTURES or OPTION
+    pub inst_type: String,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub uly: Option<String>,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub inst_id: Option<String>,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub ord_type: Option<String>,
+    /// canceled or filled
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub state: Option<String>,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub category: Option<String>,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub after: Option<String>,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub before: Option<String>,
+    /// Up to 100, 100 by default
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub limit: Option<u16>,
+}
+
+/// Query of the fills of the last 3 days or, with `fills_history`, the last 3 months.
+/// Pages go backwards from `after`, the bill id of the oldest fill of the previous page.
+#[derive(Debug, Clone, Default, Serialize, Deserialize)]
+#[serde(rename_all = "camelCase")]
+pub struct FillsRequest {
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub inst_type: Option<String>,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub uly: Option<String>,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub inst_id: Option<String>,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub ord_id: Option<String>,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub after: Option<String>,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub before: Option<String>,
+    /// Up to 100, 100 by default
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub limit: Option<u16>,
+}
+
+#[derive(Debug, Clone, Default, Serialize, Deserialize)]
+#[serde(rename_all = "camelCase", default)]
+pub struct FillDetail {
+    pub inst_type: String,
+    pub inst_id: String,
+    pub trade_id: String,
+    pub ord_id: String,
+    pub cl_ord_id: String,
+    /// Id to page with
+    pub bill_id: String,
+    pub tag: String,
+    pub fill_px: String,
+    pub fill_sz: String,
+    pub side: String,
+    pub pos_side: String,
+    /// T for taker, M for maker
+    pub exec_type: String,
+    pub fee_ccy: String,
+    /// Negative when charged, positive for rebates
+    pub fee: String,
+    pub ts: String,
+}
+
+#[derive(Debug, Clone, Default, Serialize, Deserialize)]
+#[serde(rename_all = "camelCase")]
+pub struct PositionsRequest {
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub inst_type: Option<String>,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub inst_id: Option<String>,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub pos_id: Option<String>,
+}
+
+#[derive(Debug, Clone, Default, Serialize, Deserialize)]
+#[serde(rename_all = "camelCase", default)]
+pub struct PositionDetail {
+    pub inst_type: String,
+    pub inst_id: String,
+    pub mgn_mode: String,
+    pub pos_id: String,
+    pub pos_side: String,
+    pub pos: String,
+    pub pos_ccy: String,
+    pub avail_pos: String,
+    pub avg_px: String,
+    pub upl: String,
+    pub upl_ratio: String,
+    pub lever: String,
+    pub liq_px: String,
+    pub mark_px: String,
+    pub imr: String,
+    pub margin: String,
+    pub mgn_ratio: String,
+    pub mmr: String,
+    pub notional_usd: String,
+    pub ccy: String,
+    pub last: String,
+    pub c_time: String,
+    pub u_time: String,
+}
+
+#[derive(Debug, Clone, Default, Serialize, Deserialize)]
+pub struct BalanceRequest {
+    /// Comma separated currencies, e.g. BTC,ETH
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub ccy: Option<String>,
+}
+
+#[derive(Debug, Clone, Default, Serialize, Deserialize)]
+#[serde(rename_all = "camelCase", default)]
+pub struct BalanceDetail {
+    pub ccy: String,
+    pub eq: String,
+    pub cash_bal: String,
+    pub avail_bal: String,
+    pub avail_eq: String,
+    pub frozen_bal: String,
+    pub ord_frozen: String,
+    pub upl: String,
+    pub eq_usd: String,
+    pub u_time: String,
+}
+
+#[derive(Debug, Clone, Default, Serialize, Deserialize)]
+#[serde(rename_all = "camelCase", default)]
+pub struct Balance {
+    pub total_eq: String,
+    pub iso_eq: String,
+    pub adj_eq: String,
+    pub imr: String,
+    pub mmr: String,
+    pub mgn_ratio: String,
+    pub notional_usd: String,
+    pub u_time: String,
+    pub details: Vec<BalanceDetail>,
+}
+
+#[derive(Debug, Serialize, Deserialize, Clone, Copy, PartialEq, Eq)]
+#[serde(rename_all = "snake_case")]
+pub enum PositionMode {
+    /// Separate long and short positions
+    LongShortMode,
+    /// One position per instrument, buys and sells net out
+    NetMode,
+}
+
+#[derive(Debug, Clone, Default, Serialize, Deserialize)]
+#[serde(rename_all = "camelCase", default)]
+pub struct BalanceRisk {
+    pub ccy: String,
+    pub eq: String,
+    pub dis_eq: String,
+}
+
+#[derive(Debug, Clone, Default, Serialize, Deserialize)]
+#[serde(rename_all = "camelCase", default)]
+pub struct PositionRisk {
+    pub inst_type: String,
+    pub inst_id: String,
+    pub mgn_mode: String,
+    pub pos_id: String,
+    pub pos: String,
+    pub base_bal: String,
+    pub quote_bal: String,
+    pub pos_side: String,
+    pub pos_ccy: String,
+    pub ccy: String,
+    pub notional_ccy: String,
+    pub notional_usd: String,
+}
+
+/// Balances and positions at the same instant
+#[derive(Debug, Clone, Default, Serialize, Deserialize)]
+#[serde(rename_all = "camelCase", default)]
+pub struct AccountPositionRisk {
+    pub adj_eq: String,
+    pub bal_data: Vec<BalanceRisk>,
+    pub pos_data: Vec<PositionRisk>,
+    pub ts: String,
+}
+
+/// Query of the account bills, the last 7 days or the last 3 months for the archive.
+/// Pages go backwards from `after`, the bill id of the oldest bill of the previous page.
+#[derive(Debug, Clone, Default, Serialize, Deserialize)]
+#[serde(rename_all = "camelCase")]
+pub struct BillsRequest {
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub inst_type: Option<String>,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub ccy: Option<String>,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub mgn_mode: Option<String>,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub ct_type: Option<String>,
+    /// Bill type, e.g. 2 for trades and 8 for funding
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub r#type: Option<String>,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub sub_type: Option<String>,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub after: Option<String>,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub before: Option<String>,
+    /// Up to 100, 100 by default
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub limit: Option<u16>,
+}
+
+#[derive(Debug, Clone, Default, Serialize, Deserialize)]
+#[serde(rename_all = "camelCase", default)]
+pub struct Bill {
+    pub bill_id: String,
+    pub ccy: String,
+    pub bal_chg: String,
+    pub bal: String,
+    pub r#type: String,
+    pub sub_type: String,
+    pub inst_id: String,
+    pub inst_type: String,
+    pub mgn_mode: String,
+    pub notes: String,
+    pub ord_id: String,
+    pub pnl: String,
+    pub pos_bal: String,
+    pub pos_bal_chg: String,
+    pub sz: String,
+    pub px: String,
+    pub fee: String,
+    pub from: String,
+    pub to: String,
+    pub exec_type: String,
+    pub ts: String,
+}
+
+#[derive(Debug, Clone, Default, Serialize, Deserialize)]
+#[serde(rename_all = "camelCase", default)]
+pub struct AccountConfig {
+    pub uid: String,
+    /// 1 simple, 2 single-currency margin, 3 multi-currency margin, 4 portfolio margin
+    pub acct_lv: String,
+    /// long_short_mode or net_mode
+    pub pos_mode: String,
+    pub auto_loan: String,
+    pub greeks_type: String,
+    pub level: String,
+    pub level_tmp: String,
+    pub ct_iso_mode: String,
+    pub mgn_iso_mode: String,
+}
+
+#[derive(Debug, Clone, Serialize, Deserialize)]
+#[serde(rename_all = "camelCase")]
+pub struct SetPositionModeRequest {
+    pub pos_mode: PositionMode,
+}
+
+#[derive(Debug, Clone, Serialize, Deserialize)]
+#[serde(rename_all = "camelCase")]
+pub struct SetLeverageRequest {
+    /// Either this or `ccy`, the currency sets the leverage of cross margin trading
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub inst_id: Option<String>,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub ccy: Option<String>,
+    #[serde(with = "string_or_float")]
+    pub lever: f64,
+    pub mgn_mode: MarginMode,
+    /// Isolated positions in long/short mode only
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub pos_side: Option<PositionSide>,
+}
+
+/// Leverage of an instrument or currency, as set or as read back
+#[derive(Debug, Clone, Default, Serialize, Deserialize)]
+#[serde(rename_all = "camelCase", default)]
+pub struct Leverage {
+    pub inst_id: String,
+    pub ccy: String,
+    pub mgn_mode: String,
+    pub pos_side: String,
+    pub lever: String,
+}
+
+/// Query of the largest order the margin allows, comma separated instruments for several
+#[derive(Debug, Clone, Default, Serialize, Deserialize)]
+#[serde(rename_all = "camelCase")]
+pub struct MaxSizeRequest {
+    pub inst_id: String,
+    pub td_mode: TradeMode,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub ccy: Option<String>,
+    /// Price to size at, the last price when left out
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub px: Option<String>,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub leverage: Option<String>,
+}
+
+#[derive(Debug, Clone, Default, Serialize, Deserialize)]
+#[serde(rename_all = "camelCase", default)]
+pub struct MaxSize {
+    pub inst_id: String,
+    pub ccy: String,
+    pub max_buy: String,
+    pub max_sell: String,
+}
+
+#[derive(Debug, Clone, Default, Serialize, Deserialize)]
+#[serde(rename_all = "camelCase")]
+pub struct MaxAvailSizeRequest {
+    pub inst_id: String,
+    pub td_mode: TradeMode,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub ccy: Option<String>,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub reduce_only: Option<bool>,
+}
+
+/// Size available to open or, reduce only, to close
+#[derive(Debug, Clone, Default, Serialize, Deserialize)]
+#[serde(rename_all = "camelCase", default)]
+pub struct MaxAvailSize {
+    pub inst_id: String,
+    pub avail_buy: String,
+    pub avail_sell: String,
+}
+
+#[derive(Debug, Clone, Serialize, Deserialize)]
+#[serde(rename_all = "camelCase")]
+pub struct MarginBalanceRequest {
+    pub inst_id: String,
+    pub pos_side: PositionSide,
+    /// add or reduce
+    pub r#type: String,
+    #[serde(with = "string_or_float")]
+    pub amt: f64,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub ccy: Option<String>,
+}
+
+#[derive(Debug, Clone, Default, Serialize, Deserialize)]
+#[serde(rename_all = "camelCase", default)]
+pub struct MarginBalance {
+    pub inst_id: String,
+    pub pos_side: String,
+    pub amt: String,
+    pub r#type: String,
+}
+
+#[derive(Debug, Clone, Serialize, Deserialize)]
+#[serde(rename_all = "camelCase")]
+pub struct LeverageInfoRequest {
+    pub inst_id: String,
+    pub mgn_mode: MarginMode,
+}
+
+#[derive(Debug, Clone, Default, Serialize, Deserialize)]
+#[serde(rename_all = "camelCase")]
+pub struct MaxLoanRequest {
+    pub inst_id: String,
+    pub mgn_mode: MarginMode,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub mgn_ccy: Option<String>,
+}
+
+#[derive(Debug, Clone, Default, Serialize, Deserialize)]
+#[serde(rename_all = "camelCase", default)]
+pub struct MaxLoan {
+    pub inst_id: String,
+    pub mgn_mode: String,
+    pub mgn_ccy: String,
+    pub max_loan: String,
+    pub ccy: String,
+    pub side: String,
+}
+
+#[derive(Debug, Clone, Default, Serialize, Deserialize)]
+#[serde(rename_all = "camelCase")]
+pub struct TradeFeeRequest {
+    /// SPOT, MARGIN, SWAP, FUTURES or OPTION
+    pub inst_type: String,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub inst_id: Option<String>,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub uly: Option<String>,
+}
+
+/// Fee tier of the account, rates are negative when charged and positive for rebates
+#[derive(Debug, Clone, Default, Serialize, Deserialize)]
+#[serde(rename_all = "camelCase", default)]
+pub struct TradeFee {
+    /// Fee tier, e.g. Lv1
+    pub level: String,
+    pub inst_type: String,
+    pub category: String,
+    pub maker: String,
+    pub taker: String,
+    pub delivery: String,
+    pub exercise: String,
+    pub ts: String,
+}
+
+#[derive(Debug, Clone, Default, Serialize, Deserialize)]
+#[serde(rename_all = "camelCase")]
+pub struct InterestAccruedRequest {
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub inst_id: Option<String>,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub ccy: Option<String>,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub mgn_mode: Option<String>,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub after: Option<String>,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub before: Option<String>,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub limit: Option<u16>,
+}
+
+#[derive(Debug, Clone, Default, Serialize, Deserialize)]
+#[serde(rename_all = "camelCase", default)]
+pub struct InterestAccrued {
+    pub inst_id: String,
+    pub ccy: String,
+    pub mgn_mode: String,
+    pub r#type: String,
+    pub interest: String,
+    pub interest_rate: String,
+    pub liab: String,
+    pub ts: String,
+}
+
+#[derive(Debug, Clone, Default, Serialize, Deserialize)]
+#[serde(rename_all = "camelCase", default)]
+pub struct InterestRate {
+    pub ccy: String,
+    pub interest_rate: String,
+}
+
+#[derive(Debug, Clone, Serialize, Deserialize)]
+#[serde(rename_all = "camelCase")]
+pub struct SetGreeksRequest {
+    /// PA for greeks in coins, BS for Black-Scholes greeks in dollars
+    pub greeks_type: String,
+}
+
+#[derive(Debug, Clone, Default, Serialize, Deserialize)]
+#[serde(rename_all = "camelCase", default)]
+pub struct MaxWithdrawal {
+    pub ccy: String,
+    pub max_wd: String,
+}
+
 /// Order Cancellation Request
 /// perform an order cancellation for the account
 /// only works if the parameters match an active order
@@ -201,6 +764,393 @@ pub struct OrderCancellation {
 //     pub s_msg: String,
 // }
 
+/// Algo order types, the last three are the advance algo orders
+#[derive(Debug, Serialize, Deserialize, Clone, Copy, PartialEq, Eq)]
+#[serde(rename_all = "snake_case")]
+pub enum AlgoOrderType {
+    /// One-way take profit or stop loss
+    Conditional,
+    /// One-cancels-the-other take profit and stop loss
+    Oco,
+    Trigger,
+    /// Trailing stop
+    MoveOrderStop,
+    Iceberg,
+    Twap,
+}
+
+/// Algo order placement. Only the fields of `order_type` are sent, an order price of -1
+/// executes at market once triggered.
+#[derive(Debug, Clone, Serialize, Deserialize)]
+#[serde(rename_all = "camelCase", default)]
+pub struct AlgoOrderRequest {
+    #[serde(rename = "instId")]
+    pub symbol: String,
+    #[serde(rename = "tdMode")]
+    pub trade_mode: TradeMode,
+    #[serde(rename = "ccy", skip_serializing_if = "Option::is_none")]
+    pub currency: Option<String>,
+    pub side: OrderSide,
+    #[serde(rename = "posSide", skip_serializing_if = "Option::is_none")]
+    pub position_side: Option<PositionSide>,
+    #[serde(rename = "ordType")]
+    pub order_type: AlgoOrderType,
+    /// Left out with `close_fraction` to close the whole position
+    #[serde(
+        rename = "sz",
+        with = "string_or_float_opt",
+        skip_serializing_if = "Option::is_none"
+    )]
+    pub qty: Option<f64>,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub tag: Option<String>,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub reduce_only: Option<bool>,
+    #[serde(rename = "tgtCcy", skip_serializing_if = "Option::is_none")]
+    pub target_currency: Option<String>,
+    /// 1 closes the whole position when triggered, conditional and oco orders only
+    #[serde(with = "string_or_float_opt", skip_serializing_if = "Option::is_none")]
+    pub close_fraction: Option<f64>,
+    // conditional and oco
+    #[serde(with = "string_or_float_opt", skip_serializing_if = "Option::is_none")]
+    pub tp_trigger_px: Option<f64>,
+    #[serde(with = "string_or_float_opt", skip_serializing_if = "Option::is_none")]
+    pub tp_ord_px: Option<f64>,
+    #[serde(with = "string_or_float_opt", skip_serializing_if = "Option::is_none")]
+    pub sl_trigger_px: Option<f64>,
+    #[serde(with = "string_or_float_opt", skip_serializing_if = "Option::is_none")]
+    pub sl_ord_px: Option<f64>,
+    // trigger
+    #[serde(with = "string_or_float_opt", skip_serializing_if = "Option::is_none")]
+    pub trigger_px: Option<f64>,
+    #[serde(with = "string_or_float_opt", skip_serializing_if = "Option::is_none")]
+    pub order_px: Option<f64>,
+    // move_order_stop
+    /// 0.01 is 1%, either this or `callback_spread`
+    #[serde(with = "string_or_float_opt", skip_serializing_if = "Option::is_none")]
+    pub callback_ratio: Option<f64>,
+    #[serde(with = "string_or_float_opt", skip_serializing_if = "Option::is_none")]
+    pub callback_spread: Option<f64>,
+    #[serde(with = "string_or_float_opt", skip_serializing_if = "Option::is_none")]
+    pub active_px: Option<f64>,
+    // iceberg and twap
+    /// Distance from the best price as a ratio, either this or `px_spread`
+    #[serde(with = "string_or_float_opt", skip_serializing_if = "Option::is_none")]
+    pub px_var: Option<f64>,
+    #[serde(with = "string_or_float_opt", skip_serializing_if = "Option::is_none")]
+    pub px_spread: Option<f64>,
+    /// Size of each child order
+    #[serde(with = "string_or_float_opt", skip_serializing_if = "Option::is_none")]
+    pub sz_limit: Option<f64>,
+    #[serde(with = "string_or_float_opt", skip_serializing_if = "Option::is_none")]
+    pub px_limit: Option<f64>,
+    /// Seconds between the child orders of a twap
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub time_interval: Option<String>,
+}
+
+impl Default for AlgoOrderRequest {
+    fn default() -> Self {
+        AlgoOrderRequest {
+            symbol: String::new(),
+            trade_mode: TradeMode::default(),
+            currency: None,
+            side: OrderSide::default(),
+            position_side: None,
+            order_type: AlgoOrderType::Conditional,
+            qty: None,
+            tag: None,
+            reduce_only: None,
+            target_currency: None,
+            close_fraction: None,
+            tp_trigger_px: None,
+            tp_ord_px: None,
+            sl_trigger_px: None,
+            sl_ord_px: None,
+            trigger_px: None,
+            order_px: None,
+            callback_ratio: None,
+            callback_spread: None,
+            active_px: None,
+            px_var: None,
+            px_spread: None,
+            sz_limit: None,
+            px_limit: None,
+            time_interval: None,
+        }
+    }
+}
+
+#[derive(Debug, Clone, Serialize, Deserialize)]
+#[serde(rename_all = "camelCase")]
+pub struct AlgoTransaction {
+    pub algo_id: String,
+    #[serde(with = "string_or_u16")]
+    pub s_code: u16,
+    pub s_msg: String,
+}
+
+/// Algo order cancellation, conditional, oco and trigger orders and the advance ones are
+/// canceled through different endpoints
+#[derive(Debug, Clone, Serialize, Deserialize)]
+#[serde(rename_all = "camelCase")]
+pub struct AlgoCancellation {
+    pub algo_id: String,
+    pub inst_id: String,
+}
+
+/// Query of the pending algo orders or, with `state` set, of the algo order history.
+/// Pages go backwards from `after`, the algo id of the oldest order of the previous page.
+#[derive(Debug, Clone, Serialize, Deserialize)]
+#[serde(rename_all = "camelCase")]
+pub struct AlgoOrdersRequest {
+    pub ord_type: AlgoOrderType,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub algo_id: Option<String>,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub inst_type: Option<String>,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub inst_id: Option<String>,
+    /// effective, canceled or order_failed, required by the history and ignored otherwise
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub state: Option<String>,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub after: Option<String>,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub before: Option<String>,
+    /// Up to 100, 100 by default
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub limit: Option<u16>,
+}
+
+impl AlgoOrdersRequest {
+    pub fn new(ord_type: AlgoOrderType) -> Self {
+        AlgoOrdersRequest {
+            ord_type,
+            algo_id: None,
+            inst_type: None,
+            inst_id: None,
+            state: None,
+            after: None,
+            before: None,
+            limit: None,
+        }
+    }
+}
+
+/// Algo order as returned by the query endpoints and pushed by the `orders-algo` and
+/// `algo-advance` channels, fields of other order types are left empty
+#[derive(Debug, Clone, Default, Serialize, Deserialize)]
+#[serde(rename_all = "camelCase", default)]
+pub struct AlgoOrderDetail {
+    pub inst_type: String,
+    pub inst_id: String,
+    pub ccy: String,
+    pub ord_id: String,
+    pub algo_id: String,
+    pub sz: String,
+    pub ord_type: String,
+    pub side: String,
+    pub pos_side: String,
+    pub td_mode: String,
+    pub tgt_ccy: String,
+    /// live, effective, partially_effective, canceled, order_failed or pause
+    pub state: String,
+    pub lever: String,
+    pub tp_trigger_px: String,
+    pub tp_ord_px: String,
+    pub sl_trigger_px: String,
+    pub sl_ord_px: String,
+    pub trigger_px: String,
+    pub ord_px: String,
+    pub actual_sz: String,
+    pub actual_px: String,
+    pub actual_side: String,
+    pub trigger_time: String,
+    pub px_var: String,
+    pub px_spread: String,
+    pub sz_limit: String,
+    pub px_limit: String,
+    pub time_interval: String,
+    pub callback_ratio: String,
+    pub callback_spread: String,
+    pub active_px: String,
+    pub move_trigger_px: String,
+    pub c_time: String,
+}
+
+/// Query of a single instrument
+#[derive(Debug, Clone, Default, Serialize, Deserialize)]
+#[serde(rename_all = "camelCase")]
+pub struct InstIdQuery {
+    pub inst_id: String,
+}
+
+/// Query of the instruments, tickers, mark prices or open interest of an instrument type
+#[derive(Debug, Clone, Default, Serialize, Deserialize)]
+#[serde(rename_all = "camelCase")]
+pub struct InstrumentsRequest {
+    /// SPOT, MARGIN, SWAP, FUTURES or OPTION
+    pub inst_type: String,
+    /// Required for OPTION
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub uly: Option<String>,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub inst_id: Option<String>,
+}
+
+#[derive(Debug, Clone, Default, Serialize, Deserialize)]
+#[serde(rename_all = "camelCase", default)]
+pub struct Instrument {
+    pub inst_type: String,
+    pub inst_id: String,
+    pub uly: String,
+    pub base_ccy: String,
+    pub quote_ccy: String,
+    pub settle_ccy: String,
+    /// Contract value, derivatives only
+    pub ct_val: String,
+    pub ct_mult: String,
+    pub ct_val_ccy: String,
+    /// linear or inverse
+    pub ct_type: String,
+    pub opt_type: String,
+    pub stk: String,
+    pub list_time: String,
+    pub exp_time: String,
+    pub lever: String,
+    pub tick_sz: String,
+    pub lot_sz: String,
+    pub min_sz: String,
+    pub max_lmt_sz: String,
+    pub max_mkt_sz: String,
+    pub alias: String,
+    /// live, suspend, preopen or settlement
+    pub state: String,
+}
+
+#[derive(Debug, Clone, Default, Serialize, Deserialize)]
+#[serde(rename_all = "camelCase")]
+pub struct BooksRequest {
+    pub inst_id: String,
+    /// Levels per side, up to 400, 1 by default
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub sz: Option<u16>,
+}
+
+#[derive(Debug, Clone, Default, Serialize, Deserialize)]
+#[serde(rename_all = "camelCase")]
+pub struct TradesRequest {
+    pub inst_id: String,
+    /// Up to 500, 100 by default
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub limit: Option<u16>,
+}
+
+/// Query of the trades of the last 3 months. Pages go backwards from `after`, a trade id or,
+/// with `type` 2, a timestamp.
+#[derive(Debug, Clone, Default, Serialize, Deserialize)]
+#[serde(rename_all = "camelCase")]
+pub struct HistoryTradesRequest {
+    pub inst_id: String,
+    /// 1 to page by trade id, the default, 2 by timestamp
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub r#type: Option<String>,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub after: Option<String>,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub before: Option<String>,
+    /// Up to 100, 100 by default
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub limit: Option<u16>,
+}
+
+#[derive(Debug, Clone, Default, Serialize, Deserialize)]
+#[serde(rename_all = "camelCase", default)]
+pub struct FundingRate {
+    pub inst_type: String,
+    pub inst_id: String,
+    pub funding_rate: String,
+    /// Forecast of the next period
+    pub next_funding_rate: String,
+    pub funding_time: String,
+    pub next_funding_time: String,
+}
+
+/// Query of the funding rates of the last 3 months. Pages go backwards from `after`, the
+/// funding time of the oldest rate of the previous page.
+#[derive(Debug, Clone, Default, Serialize, Deserialize)]
+#[serde(rename_all = "camelCase")]
+pub struct FundingRateHistoryRequest {
+    pub inst_id: String,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub after: Option<String>,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub before: Option<String>,
+    /// Up to 100, 100 by default
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub limit: Option<u16>,
+}
+
+#[derive(Debug, Clone, Default, Serialize, Deserialize)]
+#[serde(rename_all = "camelCase", default)]
+pub struct FundingRateHistory {
+    pub inst_type: String,
+    pub inst_id: String,
+    pub funding_rate: String,
+    /// Rate actually charged
+    pub realized_rate: String,
+    pub funding_time: String,
+}
+
+#[derive(Debug, Clone, Default, Serialize, Deserialize)]
+#[serde(rename_all = "camelCase", default)]
+pub struct MarkPrice {
+    pub inst_type: String,
+    pub inst_id: String,
+    pub mark_px: String,
+    pub ts: String,
+}
+
+#[derive(Debug, Clone, Default, Serialize, Deserialize)]
+#[serde(rename_all = "camelCase", default)]
+pub struct OpenInterest {
+    pub inst_type: String,
+    pub inst_id: String,
+    /// In contracts
+    pub oi: String,
+    pub oi_ccy: String,
+    pub ts: String,
+}
+
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct ServerTime {
+    #[serde(with = "string_or_u64")]
+    pub ts: u64,
+}
+
+#[derive(Debug, Clone, Default, Serialize, Deserialize)]
+pub struct SystemStatusRequest {
+    /// scheduled, ongoing or completed, scheduled and ongoing ones when left out
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub state: Option<String>,
+}
+
+/// Maintenance window of a service
+#[derive(Debug, Clone, Default, Serialize, Deserialize)]
+#[serde(rename_all = "camelCase", default)]
+pub struct SystemStatus {
+    pub title: String,
+    pub state: String,
+    /// ms
+    pub begin: String,
+    pub end: String,
+    pub href: String,
+    /// 0 websocket, 1 classic account, 5 unified account, 99 others
+    pub service_type: String,
+    pub system: String,
+    pub sche_desc: String,
+}
 
 #[derive(Debug, Clone, Serialize, Deserialize)]
 #[serde(rename_all = "camelCase")]
@@ -220,17 +1170,19 @@ pub struct CandleRequest {
 #[derive(Debug, Clone, Serialize, Deserialize)]
 #[serde(rename_all = "camelCase")]
 pub struct Candle {
+    /// Opening time of the candle in ms
     #[serde(with = "string_or_u64")]
-    ts: u64,
-    o: Decimal,
-    h: Decimal,
-    l: Decimal,
-    c: Decimal,
-    vol: String,
-    vol_ccy: Decimal,
-    vol_ccy_quote: Decimal,
+    pub ts: u64,
+    pub o: Decimal,
+    pub h: Decimal,
+    pub l: Decimal,
+    pub c: Decimal,
+    pub vol: String,
+    pub vol_ccy: Decimal,
+    pub vol_ccy_quote: Decimal,
+    /// 0 while the candle is still forming, 1 once it is completed
     #[serde(with = "string_or_u16")]
-    confirm: u16,
+    pub confirm: u16,
 }
 
 #[derive(Debug, Clone, Serialize, Deserialize)]
@@ -412,3 +1364,39 @@ fn default_activation_price() -> f64 {
 fn default_price_rate() -> f64 {
     0.0
 }
+
+#[cfg(test)]
+mod test {
+    use super::*;
+
+    #[test]
+    fn test_order_request_wire_names() {
+        let request = OrderRequest {
+            symbol: "BTC-USDT-SWAP".into(),
+            trade_mode: TradeMode::Cross,
+            currency: None,
+            client_order_id: Some("abc".into()),
+            tag: None,
+            side: OrderSide::Buy,
+            position_side: None,
+            order_type: OrderType::PostOnly,
+            qty: 2.,
+            price: Some(100.5),
+            reduce_only: None,
+            target_currency: None,
+        };
+        let json: serde_json::Value = serde_json::to_value(&request).unwrap();
+        assert_eq!(
+            json,
+            serde_json::json!({
+                "instId": "BTC-USDT-SWAP",
+                "tdMode": "cross",
+                "clOrdId": "abc",
+                "side": "buy",
+                "ordType": "post_only",
+                "sz": "2",
+                "px": "100.5"
+            })
+        );
+    }
+}
@@ -2,12 +2,23 @@ use crate::okex_v5::util::get_timestamp;
 
 use super::config::*;
 use super::errors::*;
-use super::rest_model::OrderType;
-use super::ws_model::{LoginConfig, LoginRequest, WebsocketResponse};
+use super::rest_model::{
+    AmendOrderRequest, OrderCancellation, OrderRequest, OrderSide, OrderType, PositionSide,
+    TradeMode,
+};
+use super::ws_model::{
+    Arg, LoginConfig, LoginRequest, SubscriptionRequest, UnsubscriptionRequest, WebsocketResponse,
+    WsOpResponse,
+};
+use crate::supervisor::Supervised;
 
+use async_trait::async_trait;
 use awc::ws::Message;
 use log::debug;
-use std::sync::atomic::{AtomicBool, Ordering};
+use std::collections::HashMap;
+use std::sync::atomic::{AtomicBool, AtomicU64, Ordering};
+use std::sync::{Arc, Mutex};
+use std::time::Duration;
 
 use actix_codec::Framed;
 use awc::{
@@ -20,13 +31,24 @@ use hmac_sha256::HMAC;
 use reqwest::Method;
 use serde::{Deserialize, Serialize};
 use serde_json::from_slice;
-use tokio::sync::mpsc;
-use uuid::Uuid;
+use tokio::sync::{mpsc, oneshot};
+
+/// How long a login waits for its response before giving up
+const LOGIN_TIMEOUT: Duration = Duration::from_secs(5);
+/// How long a trade op waits for its response
+const ACK_TIMEOUT: Duration = Duration::from_secs(5);
 
 pub struct WebSockets<WE: serde::de::DeserializeOwned + std::fmt::Debug> {
     pub socket: Option<(ClientResponse, Framed<BoxedSocket, Codec>)>,
     sender: mpsc::Sender<WE>,
     conf: Config,
+    requests_tx: mpsc::UnboundedSender<String>,
+    requests_rx: mpsc::UnboundedReceiver<String>,
+    /// What `reconnect` replays: the last endpoint, login and subscription requests
+    endpoint: Option<String>,
+    credentials: Option<(String, String, String)>,
+    subscriptions: Vec<String>,
+    trader: WsTrader,
 }
 
 impl<WE: serde::de::DeserializeOwned + std::fmt::Debug> WebSockets<WE> {
@@ -44,34 +66,91 @@ impl<WE: serde::de::DeserializeOwned + std::fmt::Debug> WebSockets<WE> {
     /// # Examples
     /// see examples/binance_WebSockets.rs
     pub fn new_with_options(sender: mpsc::Sender<WE>, conf: Config) -> WebSockets<WE> {
+        let (requests_tx, requests_rx) = mpsc::unbounded_channel();
         WebSockets {
             socket: None,
             sender: sender,
             conf,
+            trader: WsTrader::new(requests_tx.clone()),
+            requests_tx,
+            requests_rx,
+            endpoint: None,
+            credentials: None,
+            subscriptions: Vec::new(),
         }
     }
+
+    /// Requests sent here are written to the socket by `event_loop`, so other tasks can
+    /// subscribe or resubscribe while the loop owns the connection.
+    pub fn request_sender(&self) -> mpsc::UnboundedSender<String> {
+        self.requests_tx.clone()
+    }
+
+    /// Trades over this socket, which has to be the logged in private one. The responses only
+    /// arrive while `event_loop` runs.
+    pub fn trader(&self) -> WsTrader {
+        self.trader.clone()
+    }
+    fn url(&self, endpoint: &str) -> Result<String> {
+        match endpoint {
+            "public" => Ok(self.conf.ws_public_endpoint.clone()),
+            "private" => Ok(self.conf.ws_private_endpoint.clone()),
+            _ => Err(Error::Msg(format!("Unknown endpoint {}", endpoint))),
+        }
+    }
+
     /// Connect to a websocket endpoint
     pub async fn connect(&mut self, endpoint: &str) -> Result<()> {
-        let wss = match endpoint {
-            "public" => Some(&self.conf.ws_public_endpoint),
-            "private" => Some(&self.conf.ws_private_endpoint),
-            _ => None
-        };
+        let wss = self.url(endpoint)?;
 
         let client = Client::builder()
             .max_http_version(awc::http::Version::HTTP_11)
             .finish();
 
-        match client.ws(wss.unwrap()).connect().await {
+        match client.ws(wss).connect().await {
             Ok(answer) => {
                 self.socket = Some(answer);
+                self.endpoint = Some(endpoint.to_string());
                 Ok(())
             }
             Err(e) => Err(Error::Msg(format!("Error during handshake {}", e))),
         }
     }
 
+    /// Sets up the endpoint, login and subscriptions without connecting, so that a `Supervisor`
+    /// makes the first connection through `reconnect` with the same retries as the later ones
+    pub fn prepare(
+        &mut self,
+        endpoint: &str,
+        credentials: Option<(String, String, String)>,
+        subscriptions: Vec<String>,
+    ) -> Result<()> {
+        self.url(endpoint)?;
+        self.endpoint = Some(endpoint.to_string());
+        self.credentials = credentials;
+        self.subscriptions = subscriptions;
+        Ok(())
+    }
+
+    /// Sends a (un)subscription request, it is replayed on every `reconnect`
     pub async fn subscribe_request(&mut self, request: &str) -> Result<()> {
+        self.send_request(request).await?;
+        self.subscriptions.push(request.to_string());
+        Ok(())
+    }
+
+    /// Subscribes to `args`, e.g. `vec![Arg::tickers("BTC-USDT"), Arg::mark_price("BTC-USDT-SWAP")]`
+    pub async fn subscribe(&mut self, args: Vec<Arg>) -> Result<()> {
+        let request = serde_json::to_string(&SubscriptionRequest::new(args))?;
+        self.subscribe_request(&request).await
+    }
+
+    pub async fn unsubscribe(&mut self, args: Vec<Arg>) -> Result<()> {
+        let request = serde_json::to_string(&UnsubscriptionRequest::new(args))?;
+        self.subscribe_request(&request).await
+    }
+
+    async fn send_request(&mut self, request: &str) -> Result<()> {
         if let Some((_, ref mut socket)) = self.socket {
             socket.send(Message::Text(request.into())).await?;
             Ok(())
@@ -94,12 +173,25 @@ impl<WE: serde::de::DeserializeOwned + std::fmt::Debug> WebSockets<WE> {
         &self.socket
     }
 
+    /// Logs in and waits for the response, the credentials are kept for `reconnect`
     pub async fn login(
         &mut self,
         api_key: String,
         secret_key: String,
         passphrase: String,
     ) -> Result<()> {
+        self.credentials = Some((api_key, secret_key, passphrase));
+        self.send_login().await?;
+        self.wait_login().await
+    }
+
+    async fn send_login(&mut self) -> Result<()> {
+        let (api_key, secret_key, passphrase) = match &self.credentials {
+            Some((api_key, secret_key, passphrase)) => {
+                (api_key.clone(), secret_key, passphrase.clone())
+            }
+            None => return Err(Error::Msg("No login credentials".to_string())),
+        };
         // {
         //     "op": "login",
         //     "args": [
@@ -147,6 +239,55 @@ impl<WE: serde::de::DeserializeOwned + std::fmt::Debug> WebSockets<WE> {
         }
     }
 
+    /// Reads frames until the login response, private channels reject subscriptions before it
+    async fn wait_login(&mut self) -> Result<()> {
+        let socket = match self.socket {
+            Some((_, ref mut socket)) => socket,
+            None => return Err(Error::Msg("Not able to read the login response".to_string())),
+        };
+        loop {
+            let frame = match tokio::time::timeout(LOGIN_TIMEOUT, socket.next()).await {
+                Ok(Some(frame)) => frame?,
+                Ok(None) => return Err(Error::Msg("Disconnected during login".to_string())),
+                Err(_) => return Err(Error::Msg("Login response timed out".to_string())),
+            };
+            if let Frame::Text(text) = frame {
+                match from_slice::<WebsocketResponse>(&text) {
+                    Ok(WebsocketResponse::Login(response)) if response.event == "login" => {
+                        return Ok(());
+                    }
+                    Ok(WebsocketResponse::Login(response)) => {
+                        return Err(Error::Msg(format!(
+                            "Login failed, code: {}, msg: {}",
+                            response.code, response.msg
+                        )));
+                    }
+                    _ => debug!("skipped before login: {:?}", text),
+                }
+            }
+        }
+    }
+
+    /// Connects to the last endpoint again, logs in if `login` was called before and replays
+    /// the subscription requests. Trade ops still waiting for their response fail and the
+    /// queued requests are dropped, orders must not go out late.
+    pub async fn reconnect(&mut self) -> Result<()> {
+        let endpoint = match &self.endpoint {
+            Some(endpoint) => endpoint.clone(),
+            None => return Err(Error::Msg("Never connected".to_string())),
+        };
+        self.trader.fail_pending();
+        while self.requests_rx.try_recv().is_ok() {}
+        self.connect(&endpoint).await?;
+        if let Some((api_key, secret_key, passphrase)) = self.credentials.clone() {
+            self.login(api_key, secret_key, passphrase).await?;
+        }
+        for request in self.subscriptions.clone() {
+            self.send_request(&request).await?;
+        }
+        Ok(())
+    }
+
     pub async fn event_loop(&mut self, running: &AtomicBool) -> Result<()> {
         let mut interval = tokio::time::interval(tokio::time::Duration::from_millis(10000));
 
@@ -166,6 +307,8 @@ impl<WE: serde::de::DeserializeOwned + std::fmt::Debug> WebSockets<WE> {
                                             if let Err(e) = self.sender.send(event).await {
                                                 return Err(Error::Msg(format!("{:?}", e)));
                                             }
+                                        } else if let Ok(response) = from_slice::<WsOpResponse>(&text) {
+                                            self.trader.acknowledge(response);
                                         } else if let Ok(response) = from_slice::<WebsocketResponse>(&text) {
                                             println!("WebsocketResponse: {:?}", response);
                                         } else {
@@ -184,6 +327,10 @@ impl<WE: serde::de::DeserializeOwned + std::fmt::Debug> WebSockets<WE> {
                             None => return Err(Error::Msg(format!("Option::unwrap()` on a `None` value."))),
                         }
                     }
+                    Some(request) = self.requests_rx.recv() => {
+                        debug!("request: {}", request);
+                        socket.send(Message::Text(request.into())).await?;
+                    }
                     _ = interval.tick() => {
                         socket.send(Message::Ping(Bytes::from_static(b""))).await?;
                         debug!("ping: {:?}", Message::Ping(Bytes::from_static(b"")));
@@ -193,166 +340,218 @@ impl<WE: serde::de::DeserializeOwned + std::fmt::Debug> WebSockets<WE> {
         }
         Ok(())
     }
+}
 
-    // trade start from here
-    pub async fn place_order(&mut self, order: WSOrder) -> Result<()> {
-        if let Some((_, ref mut socket)) = self.socket {
-            let ws_order = WSOrderRequest {
-                id: Uuid::new_v4().to_string(),
-                op: "order".to_string(),
-                args: vec![order],
-            };
+#[async_trait(?Send)]
+impl<WE: serde::de::DeserializeOwned + std::fmt::Debug> Supervised for WebSockets<WE> {
+    type Error = Error;
 
-            let text = serde_json::to_string(&ws_order)?;
-            socket.send(Message::Text(text.into())).await?;
-            Ok(())
-        } else {
-            Err(Error::Msg("Not able to send requests".to_string()))
-        }
+    fn is_connected(&self) -> bool {
+        self.socket.is_some()
     }
 
-    pub async fn place_multipy_order(&mut self, orders: Vec<WSOrder>) -> Result<()> {
-        if let Some((_, ref mut socket)) = self.socket {
-            let ws_orders = WSOrderRequest {
-                id: Uuid::new_v4().to_string(),
-                op: "batch-orders".to_string(),
-                args: orders,
-            };
+    async fn reconnect(&mut self) -> Result<()> {
+        WebSockets::reconnect(self).await
+    }
 
-            let text = serde_json::to_string(&ws_orders)?;
-            socket.send(Message::Text(text.into())).await?;
-            Ok(())
-        } else {
-            Err(Error::Msg("Not able to send requests".to_string()))
+    async fn event_loop(&mut self, running: &AtomicBool) -> Result<()> {
+        WebSockets::event_loop(self, running).await
+    }
+}
+
+/// Sends trade ops through the event loop of a `WebSockets` and awaits their responses.
+/// Clones share the connection, every op gets its own request id.
+#[derive(Clone)]
+pub struct WsTrader {
+    requests: mpsc::UnboundedSender<String>,
+    pending: Arc<Mutex<HashMap<String, oneshot::Sender<WsOpResponse>>>>,
+    next_id: Arc<AtomicU64>,
+}
+
+impl WsTrader {
+    fn new(requests: mpsc::UnboundedSender<String>) -> Self {
+        WsTrader {
+            requests,
+            pending: Arc::new(Mutex::new(HashMap::new())),
+            next_id: Arc::new(AtomicU64::new(1)),
         }
     }
 
-    pub async fn limit_buy(
-        &mut self,
-        symbol: impl Into<String>,
-        qty: impl Into<String>,
-        price: impl Into<String>,
-        order_type: OrderType,
-    ) -> Result<()> {
-        let order = WSOrder {
-            symbol: symbol.into(),
-            trade_mode: TradeMode::Cross,
-            currency: None,
-            client_order_id: None,
-            tag: None,
-            side: OrderSide::Buy,
-            position_side: None, // None for net mode
-            order_type: order_type,
-            qty: qty.into(),
-            price: Some(price.into()), reduce_only: None,
-            target_currency: None,
-        };
-        self.place_order(order).await?;
-        Ok(())
+    pub async fn place_order(&self, order: OrderRequest) -> Result<WsOpResponse> {
+        self.request("order", vec![order]).await
     }
 
-    pub async fn limit_sell(
-        &mut self,
-        symbol: impl Into<String>,
-        qty: impl Into<String>,
-        price: impl Into<String>,
-        order_type: OrderType,
-    ) -> Result<()> {
-        let order = WSOrder {
-            symbol: symbol.into(),
-            trade_mode: TradeMode::Cross,
-            currency: None,
-            client_order_id: None,
-            tag: None,
-            side: OrderSide::Sell,
-            position_side: None, // None for net mode
-            order_type: order_type,
-            qty: qty.into(),
-            price: Some(price.into()),
-            reduce_only: None,
-            target_currency: None,
-        };
-        self.place_order(order).await?;
-        Ok(())
+    /// Up to 20 orders, each one gets its own result in the response
+    pub async fn place_orders(&self, orders: Vec<OrderRequest>) -> Result<WsOpResponse> {
+        self.request("batch-orders", orders).await
     }
 
-    pub async fn market_buy() {}
+    pub async fn cancel_order(&self, order: OrderCancellation) -> Result<WsOpResponse> {
+        self.request("cancel-order", vec![order]).await
+    }
 
-    pub async fn market_sell() {}
+    pub async fn cancel_orders(&self, orders: Vec<OrderCancellation>) -> Result<WsOpResponse> {
+        self.request("batch-cancel-orders", orders).await
+    }
 
-    pub async fn cancel_order() {}
+    pub async fn amend_order(&self, request: AmendOrderRequest) -> Result<WsOpResponse> {
+        self.request("amend-order", vec![request]).await
+    }
 
-    pub async fn amend_order() {}
+    pub async fn amend_orders(&self, requests: Vec<AmendOrderRequest>) -> Result<WsOpResponse> {
+        self.request("batch-amend-orders", requests).await
+    }
 
-    pub async fn amend_multiple_order() {}
-}
+    pub async fn limit_buy(
+        &self,
+        symbol: &str,
+        qty: f64,
+        price: f64,
+        position_side: PositionSide,
+        client_order_id: &str,
+    ) -> Result<WsOpResponse> {
+        let order = order(symbol, OrderSide::Buy, OrderType::Limit, qty, Some(price));
+        self.place_order(OrderRequest {
+            position_side: Some(position_side),
+            client_order_id: Some(client_order_id.to_string()),
+            ..order
+        })
+        .await
+    }
 
-#[derive(Deserialize, Serialize)]
-#[serde(rename_all = "snake_case")]
-pub enum PositionSide {
-    Net,
-    Long,
-    Short,
-}
+    pub async fn limit_sell(
+        &self,
+        symbol: &str,
+        qty: f64,
+        price: f64,
+        position_side: PositionSide,
+        client_order_id: &str,
+    ) -> Result<WsOpResponse> {
+        let order = order(symbol, OrderSide::Sell, OrderType::Limit, qty, Some(price));
+        self.place_order(OrderRequest {
+            position_side: Some(position_side),
+            client_order_id: Some(client_order_id.to_string()),
+            ..order
+        })
+        .await
+    }
 
-#[derive(Debug, Serialize, Deserialize, Clone)]
-#[serde(rename_all = "snake_case")]
-pub enum OrderSide {
-    Buy,
-    Sell,
-}
+    pub async fn market_buy(&self, symbol: &str, qty: f64) -> Result<WsOpResponse> {
+        let order = order(symbol, OrderSide::Buy, OrderType::Market, qty, None);
+        self.place_order(order).await
+    }
 
-/// By default, buy
-impl Default for OrderSide {
-    fn default() -> Self {
-        Self::Buy
+    pub async fn market_sell(&self, symbol: &str, qty: f64) -> Result<WsOpResponse> {
+        let order = order(symbol, OrderSide::Sell, OrderType::Market, qty, None);
+        self.place_order(order).await
+    }
+
+    /// Sends `op` and waits for the response with its id. A response with a non zero code is
+    /// still returned, like the REST answers.
+    async fn request<T: Serialize>(&self, op: &str, args: Vec<T>) -> Result<WsOpResponse> {
+        let id = self.next_id.fetch_add(1, Ordering::Relaxed).to_string();
+        let request = WSOrderRequest {
+            id: id.clone(),
+            op: op.to_string(),
+            args,
+        };
+        let text = serde_json::to_string(&request)?;
+
+        let (ack_tx, ack_rx) = oneshot::channel();
+        self.pending.lock().unwrap().insert(id.clone(), ack_tx);
+        if self.requests.send(text).is_err() {
+            self.pending.lock().unwrap().remove(&id);
+            return Err(Error::Msg(format!(
+                "Websocket gone, {} {} not sent",
+                op, id
+            )));
+        }
+        match tokio::time::timeout(ACK_TIMEOUT, ack_rx).await {
+            Ok(Ok(response)) => Ok(response),
+            Ok(Err(_)) => Err(Error::Msg(format!(
+                "Disconnected before the {} {} response",
+                op, id
+            ))),
+            Err(_) => {
+                self.pending.lock().unwrap().remove(&id);
+                Err(Error::Msg(format!("{} {} response timed out", op, id)))
+            }
+        }
+    }
+
+    fn acknowledge(&self, response: WsOpResponse) {
+        match self.pending.lock().unwrap().remove(&response.id) {
+            Some(ack) => {
+                let _ = ack.send(response);
+            }
+            None => debug!("response without a waiting request: {:?}", response),
+        }
     }
-}
 
-#[derive(Debug, Serialize, Deserialize, Clone)]
-#[serde(rename_all = "snake_case")]
-pub enum TradeMode {
-    Isolated,
-    Cross,
-    Cash,
+    /// Drops the waiting requests, their callers get an error
+    fn fail_pending(&self) {
+        self.pending.lock().unwrap().clear();
+    }
 }
 
-/// By default, Cross
-impl Default for TradeMode {
-    fn default() -> Self {
-        Self::Cross
+fn order(
+    symbol: &str,
+    side: OrderSide,
+    order_type: OrderType,
+    qty: f64,
+    price: Option<f64>,
+) -> OrderRequest {
+    OrderRequest {
+        symbol: symbol.to_string(),
+        trade_mode: TradeMode::Cash,
+        currency: None,
+        client_order_id: None,
+        tag: None,
+        side,
+        position_side: None,
+        order_type,
+        qty,
+        price,
+        reduce_only: None,
+        target_currency: None,
     }
 }
 
+/// Trade op request, `args` are the same as in the REST bodies
 #[derive(Debug, Clone, Serialize, Deserialize)]
-pub struct WSOrderRequest {
+pub struct WSOrderRequest<T> {
     pub id: String,
     pub op: String,
-    pub args: Vec<WSOrder>,
+    pub args: Vec<T>,
 }
 
-#[derive(Debug, Clone, Serialize, Deserialize)]
-#[serde(rename_all = "camelCase")]
-pub struct WSOrder {
-    #[serde(rename = "inst_id")]
-    pub symbol: String,
-    #[serde(rename = "td_mode")]
-    pub trade_mode: TradeMode,
-    #[serde(rename = "ccy")]
-    pub currency: Option<String>,
-    #[serde(rename = "clOrdId")]
-    pub client_order_id: Option<String>,
-    pub tag: Option<String>,
-    pub side: OrderSide,
-    #[serde(rename = "posSide")]
-    pub position_side: Option<String>,
-    #[serde(rename = "ord_type")]
-    pub order_type: OrderType,
-    #[serde(rename = "sz")]
-    pub qty: String,
-    #[serde(rename = "px")]
-    pub price: Option<String>,
-    pub reduce_only: Option<bool>,
-    #[serde(rename = "tgtCcy")]
-    pub target_currency: Option<String>,
+#[cfg(test)]
+mod test {
+    use super::*;
+
+    #[tokio::test]
+    async fn test_trader_acks() {
+        let (requests_tx, mut requests_rx) = mpsc::unbounded_channel();
+        let trader = WsTrader::new(requests_tx);
+        let cancel = trader.cancel_orders(vec![OrderCancellation {
+            symbol: "BTC-USDT-SWAP".to_string(),
+            order_id: Some("1".to_string()),
+            orig_client_order_id: None,
+        }]);
+        let respond = async {
+            let request: serde_json::Value =
+                serde_json::from_str(&requests_rx.recv().await.unwrap()).unwrap();
+            assert_eq!(request["op"], "batch-cancel-orders");
+            assert_eq!(request["args"][0]["ordId"], "1");
+            let response = format!(
+                r#"{{"id":{},"op":"batch-cancel-orders","code":"0","msg":"","data":[{{"clOrdId":"","ordId":"1","sCode":"0","sMsg":""}}]}}"#,
+                request["id"]
+            );
+            trader.acknowledge(serde_json::from_str(&response).unwrap());
+        };
+        let (response, _) = tokio::join!(cancel, respond);
+        let response = response.unwrap();
+        assert_eq!(response.data[0].ord_id, "1");
+        assert!(trader.pending.lock().unwrap().is_empty());
+    }
 }
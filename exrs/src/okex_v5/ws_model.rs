@@ -1,6 +1,10 @@
-use super::rest_model::{string_or_float, string_or_float_opt, string_or_u64};
+use super::rest_model::{
+    string_or_float, string_or_float_opt, string_or_u16, string_or_u64, AlgoOrderDetail, Candle,
+    SystemStatus, Transaction, TransactionResponse,
+};
+use serde::{de, Deserialize, Deserializer};
 
-#[derive(Debug, Serialize, Deserialize, Clone)]
+#[derive(Debug, Serialize, Deserialize, Clone, Default)]
 #[serde(rename_all = "camelCase")]
 pub struct Arg {
     pub channel: String,
@@ -14,18 +18,172 @@ pub struct Arg {
     pub inst_type: Option<String>,
 }
 
+impl Arg {
+    pub fn new(channel: &str) -> Self {
+        Arg {
+            channel: channel.to_string(),
+            ..Default::default()
+        }
+    }
+
+    /// `channel` of a single instrument, e.g. tickers, trades, books5 or orders
+    pub fn inst_id(channel: &str, inst_id: &str) -> Self {
+        Arg {
+            inst_id: Some(inst_id.to_string()),
+            ..Arg::new(channel)
+        }
+    }
+
+    /// `channel` of every instrument of `inst_type`, e.g. SPOT, SWAP or ANY
+    pub fn inst_type(channel: &str, inst_type: &str) -> Self {
+        Arg {
+            inst_type: Some(inst_type.to_string()),
+            ..Arg::new(channel)
+        }
+    }
+
+    pub fn instruments(inst_type: &str) -> Self {
+        Arg::inst_type("instruments", inst_type)
+    }
+
+    pub fn tickers(inst_id: &str) -> Self {
+        Arg::inst_id("tickers", inst_id)
+    }
+
+    /// `bar` like 1m, 1H or 1D
+    pub fn candles(bar: &str, inst_id: &str) -> Self {
+        Arg::inst_id(&format!("candle{}", bar), inst_id)
+    }
+
+    pub fn trades(inst_id: &str) -> Self {
+        Arg::inst_id("trades", inst_id)
+    }
+
+    /// Delivery or exercise price of the FUTURES or OPTION contracts on `uly`
+    pub fn estimated_price(inst_type: &str, uly: &str) -> Self {
+        Arg {
+            uly: Some(uly.to_string()),
+            ..Arg::inst_type("estimated-price", inst_type)
+        }
+    }
+
+    pub fn mark_price(inst_id: &str) -> Self {
+        Arg::inst_id("mark-price", inst_id)
+    }
+
+    pub fn mark_price_candles(bar: &str, inst_id: &str) -> Self {
+        Arg::inst_id(&format!("mark-price-candle{}", bar), inst_id)
+    }
+
+    pub fn price_limit(inst_id: &str) -> Self {
+        Arg::inst_id("price-limit", inst_id)
+    }
+
+    /// `channel` is books5, books, bbo-tbt, books50-l2-tbt or books-l2-tbt
+    pub fn books(channel: &str, inst_id: &str) -> Self {
+        Arg::inst_id(channel, inst_id)
+    }
+
+    pub fn option_summary(uly: &str) -> Self {
+        Arg {
+            uly: Some(uly.to_string()),
+            ..Arg::new("opt-summary")
+        }
+    }
+
+    pub fn funding_rate(inst_id: &str) -> Self {
+        Arg::inst_id("funding-rate", inst_id)
+    }
+
+    /// `index` like BTC-USDT
+    pub fn index_candles(bar: &str, index: &str) -> Self {
+        Arg::inst_id(&format!("index-candle{}", bar), index)
+    }
+
+    pub fn status() -> Self {
+        Arg::new("status")
+    }
+}
+
+/// Deserializes the `arg` of an event only when `matches` accepts its channel. The pushes of
+/// several channels share their fields, the untagged `WebsocketEvent` tells them apart by it.
+fn channel_arg<'de, D>(deserializer: D, matches: fn(&str) -> bool) -> Result<Arg, D::Error>
+where
+    D: Deserializer<'de>,
+{
+    let arg = Arg::deserialize(deserializer)?;
+    if matches(&arg.channel) {
+        Ok(arg)
+    } else {
+        Err(de::Error::custom(format!(
+            "unexpected channel {}",
+            arg.channel
+        )))
+    }
+}
+
+fn trades_arg<'de, D: Deserializer<'de>>(deserializer: D) -> Result<Arg, D::Error> {
+    channel_arg(deserializer, |c| c.starts_with("trades"))
+}
+
+fn candle_arg<'de, D: Deserializer<'de>>(deserializer: D) -> Result<Arg, D::Error> {
+    channel_arg(deserializer, |c| c.starts_with("candle"))
+}
+
+fn estimated_price_arg<'de, D: Deserializer<'de>>(deserializer: D) -> Result<Arg, D::Error> {
+    channel_arg(deserializer, |c| c == "estimated-price")
+}
+
+fn mark_price_arg<'de, D: Deserializer<'de>>(deserializer: D) -> Result<Arg, D::Error> {
+    channel_arg(deserializer, |c| c == "mark-price")
+}
+
+fn mark_price_candle_arg<'de, D: Deserializer<'de>>(deserializer: D) -> Result<Arg, D::Error> {
+    channel_arg(deserializer, |c| c.starts_with("mark-price-candle"))
+}
+
+fn price_limit_arg<'de, D: Deserializer<'de>>(deserializer: D) -> Result<Arg, D::Error> {
+    channel_arg(deserializer, |c| c == "price-limit")
+}
+
+fn option_summary_arg<'de, D: Deserializer<'de>>(deserializer: D) -> Result<Arg, D::Error> {
+    channel_arg(deserializer, |c| c == "opt-summary")
+}
+
+fn funding_rate_arg<'de, D: Deserializer<'de>>(deserializer: D) -> Result<Arg, D::Error> {
+    channel_arg(deserializer, |c| c == "funding-rate")
+}
+
+fn index_candle_arg<'de, D: Deserializer<'de>>(deserializer: D) -> Result<Arg, D::Error> {
+    channel_arg(deserializer, |c| c.starts_with("index-candle"))
+}
+
+fn status_arg<'de, D: Deserializer<'de>>(deserializer: D) -> Result<Arg, D::Error> {
+    channel_arg(deserializer, |c| c == "status")
+}
+
 #[derive(Debug, Serialize, Deserialize, Clone)]
 #[serde(untagged)]
 pub enum WebsocketEvent {
     Instruments(Box<InstrumentsEvent>),
     Ticker(Box<TickerEvent>),
     OrderBook(Box<OrderBookEvent>),
+    Trades(Box<TradesEvent>),
     Account(Box<AccountEvent>),
     Position(Box<PositionsEvent>),
     BalancePosition(Box<BalancePositionEvent>),
     Order(Box<OrderEvent>),
     AlgoOrders(Box<AlgoOrdersEvent>),
     AdvanceAlgoOrdersEvent(Box<AdvanceAlgoOrdersEvent>),
+    Candlesticks(Box<CandiesticksEvent>),
+    EstimatedPrice(Box<EstimatedPriceEvent>),
+    MarkPrice(Box<MarkPriceEvent>),
+    MarkPriceCandlesticks(Box<MarkPriceCandlesticksEvent>),
+    PriceLimit(Box<PriceLimitEvent>),
+    OptionSummary(Box<OptionSummaryEvent>),
+    FundingRate(Box<FundingRateEvent>),
+    IndexCandlesticks(Box<IndexCandlesticksEvent>),
+    Status(Box<StatusEvent>),
 }
 
 #[derive(Debug, Serialize, Deserialize, Clone)]
@@ -66,6 +224,15 @@ pub struct SubscriptionRequest {
     pub args: Vec<Arg>,
 }
 
+impl SubscriptionRequest {
+    pub fn new(args: Vec<Arg>) -> Self {
+        SubscriptionRequest {
+            op: "subscribe".to_string(),
+            args,
+        }
+    }
+}
+
 #[derive(Debug, Serialize, Deserialize, Clone)]
 #[serde(rename_all = "camelCase")]
 pub struct SubscriptionResponse {
@@ -88,12 +255,45 @@ pub struct UnsubscriptionRequest {
     pub args: Vec<Arg>,
 }
 
+impl UnsubscriptionRequest {
+    pub fn new(args: Vec<Arg>) -> Self {
+        UnsubscriptionRequest {
+            op: "unsubscribe".to_string(),
+            args,
+        }
+    }
+}
+
 #[derive(Debug, Serialize, Deserialize, Clone)]
 pub struct UnsubscriptionResponse {
     pub event: String,
     pub arg: Arg,
 }
 
+/// Response to a trade op, `id` is the one of the request
+#[derive(Debug, Serialize, Deserialize, Clone)]
+pub struct WsOpResponse {
+    pub id: String,
+    #[serde(default)]
+    pub op: String,
+    #[serde(with = "string_or_u16")]
+    pub code: u16,
+    pub msg: String,
+    /// One per order of the request, with its own sCode
+    #[serde(default)]
+    pub data: Vec<Transaction>,
+}
+
+impl From<WsOpResponse> for TransactionResponse {
+    fn from(response: WsOpResponse) -> Self {
+        TransactionResponse {
+            code: response.code,
+            msg: response.msg,
+            data: response.data,
+        }
+    }
+}
+
 // Public Channels Starts from here
 #[derive(Debug, Serialize, Deserialize, Clone)]
 #[serde(rename_all = "camelCase")]
@@ -171,32 +371,132 @@ pub struct Ticker {
 
 #[derive(Debug, Serialize, Deserialize, Clone)]
 #[serde(rename_all = "camelCase")]
-pub struct CandiesticksEvent {}
+pub struct CandiesticksEvent {
+    #[serde(deserialize_with = "candle_arg")]
+    pub arg: Arg,
+    pub data: Vec<Candle>,
+}
+
+#[derive(Debug, Serialize, Deserialize, Clone)]
+#[serde(rename_all = "camelCase")]
+pub struct TradesEvent {
+    /// Trades deserialize from the 6 element candles of the index and mark price otherwise
+    #[serde(deserialize_with = "trades_arg")]
+    pub arg: Arg,
+    pub data: Vec<Trade>,
+}
+
+#[derive(Debug, Serialize, Deserialize, Clone)]
+#[serde(rename_all = "camelCase")]
+pub struct Trade {
+    pub inst_id: String,
+    pub trade_id: String,
+    #[serde(with = "string_or_float")]
+    pub px: f64,
+    #[serde(with = "string_or_float")]
+    pub sz: f64,
+    /// Taker side of the trade
+    pub side: String,
+    #[serde(rename = "ts", with = "string_or_u64")]
+    pub timestamp: u64,
+}
+
+#[derive(Debug, Serialize, Deserialize, Clone)]
+#[serde(rename_all = "camelCase")]
+pub struct EstimatedPriceEvent {
+    #[serde(deserialize_with = "estimated_price_arg")]
+    pub arg: Arg,
+    pub data: Vec<EstimatedPrice>,
+}
+
+#[derive(Debug, Serialize, Deserialize, Clone)]
+#[serde(rename_all = "camelCase")]
+pub struct EstimatedPrice {
+    pub inst_type: String,
+    pub inst_id: String,
+    /// Estimated delivery or exercise price
+    #[serde(with = "string_or_float")]
+    pub settle_px: f64,
+    #[serde(rename = "ts", with = "string_or_u64")]
+    pub timestamp: u64,
+}
 
 #[derive(Debug, Serialize, Deserialize, Clone)]
 #[serde(rename_all = "camelCase")]
-pub struct TradesEvent {}
+pub struct MarkPriceEvent {
+    #[serde(deserialize_with = "mark_price_arg")]
+    pub arg: Arg,
+    pub data: Vec<MarkPrice>,
+}
 
 #[derive(Debug, Serialize, Deserialize, Clone)]
 #[serde(rename_all = "camelCase")]
-pub struct EstimatedPriceEvent {}
+pub struct MarkPrice {
+    pub inst_type: String,
+    pub inst_id: String,
+    #[serde(with = "string_or_float")]
+    pub mark_px: f64,
+    #[serde(rename = "ts", with = "string_or_u64")]
+    pub timestamp: u64,
+}
 
 #[derive(Debug, Serialize, Deserialize, Clone)]
 #[serde(rename_all = "camelCase")]
-pub struct MarkPriceEvent {}
+pub struct MarkPriceCandlesticksEvent {
+    #[serde(deserialize_with = "mark_price_candle_arg")]
+    pub arg: Arg,
+    pub data: Vec<PriceCandle>,
+}
+
+/// Candle of the mark price or an index, pushed as [ts, o, h, l, c, confirm]
+#[derive(Debug, Serialize, Deserialize, Clone)]
+pub struct PriceCandle {
+    /// Opening time of the candle in ms
+    #[serde(with = "string_or_u64")]
+    pub ts: u64,
+    #[serde(with = "string_or_float")]
+    pub o: f64,
+    #[serde(with = "string_or_float")]
+    pub h: f64,
+    #[serde(with = "string_or_float")]
+    pub l: f64,
+    #[serde(with = "string_or_float")]
+    pub c: f64,
+    /// 0 while the candle is still forming, 1 once it is completed
+    #[serde(default, with = "string_or_u16")]
+    pub confirm: u16,
+}
 
 #[derive(Debug, Serialize, Deserialize, Clone)]
 #[serde(rename_all = "camelCase")]
-pub struct MarkPriceCandlesticksEvent {}
+pub struct PriceLimitEvent {
+    #[serde(deserialize_with = "price_limit_arg")]
+    pub arg: Arg,
+    pub data: Vec<PriceLimit>,
+}
 
 #[derive(Debug, Serialize, Deserialize, Clone)]
 #[serde(rename_all = "camelCase")]
-pub struct PriceLimitEvent {}
+pub struct PriceLimit {
+    pub inst_id: String,
+    /// Highest price a buy order may have
+    #[serde(with = "string_or_float")]
+    pub buy_lmt: f64,
+    /// Lowest price a sell order may have
+    #[serde(with = "string_or_float")]
+    pub sell_lmt: f64,
+    #[serde(rename = "ts", with = "string_or_u64")]
+    pub timestamp: u64,
+}
 
 #[derive(Debug, Serialize, Deserialize, Clone)]
 #[serde(rename_all = "camelCase")]
 pub struct OrderBookEvent {
     pub arg: Arg,
+    /// "snapshot" or "update" on books, books50-l2-tbt and books-l2-tbt, absent on books5 and
+    /// bbo-tbt which push the whole top of the book every time
+    #[serde(default)]
+    pub action: Option<String>,
     pub data: Vec<OrderBook>,
 }
 
@@ -205,26 +505,105 @@ pub struct OrderBookEvent {
 pub struct OrderBook {
     pub asks: Vec<Vec<String>>,
     pub bids: Vec<Vec<String>>,
+    /// Only pushed on books5 and bbo-tbt, the instrument is in `arg` otherwise
+    #[serde(default)]
     pub inst_id: String,
     #[serde(rename = "ts", with = "string_or_u64")]
     pub timestamp: u64,
+    /// CRC32 of the top 25 levels as a signed integer, not pushed on bbo-tbt
+    #[serde(default)]
+    pub checksum: Option<i64>,
+    /// -1 for the snapshot on books and books-l2-tbt
+    #[serde(default)]
+    pub prev_seq_id: Option<i64>,
+    #[serde(default)]
+    pub seq_id: Option<i64>,
 }
 
 #[derive(Debug, Serialize, Deserialize, Clone)]
 #[serde(rename_all = "camelCase")]
-pub struct OptionSummaryEvent {}
+pub struct OptionSummaryEvent {
+    #[serde(deserialize_with = "option_summary_arg")]
+    pub arg: Arg,
+    pub data: Vec<OptionSummary>,
+}
 
+/// Greeks of an option, the BS ones are in dollars, the others in coins
 #[derive(Debug, Serialize, Deserialize, Clone)]
 #[serde(rename_all = "camelCase")]
-pub struct FundingRateEvent {}
+pub struct OptionSummary {
+    pub inst_type: String,
+    pub inst_id: String,
+    pub uly: String,
+    #[serde(with = "string_or_float")]
+    pub delta: f64,
+    #[serde(with = "string_or_float")]
+    pub gamma: f64,
+    #[serde(with = "string_or_float")]
+    pub vega: f64,
+    #[serde(with = "string_or_float")]
+    pub theta: f64,
+    #[serde(rename = "deltaBS", with = "string_or_float")]
+    pub delta_bs: f64,
+    #[serde(rename = "gammaBS", with = "string_or_float")]
+    pub gamma_bs: f64,
+    #[serde(rename = "vegaBS", with = "string_or_float")]
+    pub vega_bs: f64,
+    #[serde(rename = "thetaBS", with = "string_or_float")]
+    pub theta_bs: f64,
+    #[serde(with = "string_or_float")]
+    pub lever: f64,
+    #[serde(with = "string_or_float")]
+    pub mark_vol: f64,
+    /// Empty without bids
+    pub bid_vol: String,
+    /// Empty without asks
+    pub ask_vol: String,
+    pub real_vol: String,
+    #[serde(rename = "ts", with = "string_or_u64")]
+    pub timestamp: u64,
+}
+
+#[derive(Debug, Serialize, Deserialize, Clone)]
+#[serde(rename_all = "camelCase")]
+pub struct FundingRateEvent {
+    #[serde(deserialize_with = "funding_rate_arg")]
+    pub arg: Arg,
+    pub data: Vec<FundingRate>,
+}
+
+#[derive(Debug, Serialize, Deserialize, Clone)]
+#[serde(rename_all = "camelCase")]
+pub struct FundingRate {
+    pub inst_type: String,
+    pub inst_id: String,
+    #[serde(with = "string_or_float")]
+    pub funding_rate: f64,
+    /// Forecast of the next period, empty when the venue has none
+    pub next_funding_rate: String,
+    /// Settlement time of `funding_rate` in ms
+    #[serde(with = "string_or_u64")]
+    pub funding_time: u64,
+    #[serde(default)]
+    pub next_funding_time: String,
+}
 
 #[derive(Debug, Serialize, Deserialize, Clone)]
 #[serde(rename_all = "camelCase")]
-pub struct IndexCandlesticksEvent {}
+pub struct IndexCandlesticksEvent {
+    #[serde(deserialize_with = "index_candle_arg")]
+    pub arg: Arg,
+    pub data: Vec<PriceCandle>,
+}
 
+/// Pushed when a maintenance is scheduled, starts or ends
 #[derive(Debug, Serialize, Deserialize, Clone)]
 #[serde(rename_all = "camelCase")]
-pub struct StatusEvent {}
+pub struct StatusEvent {
+    #[serde(deserialize_with = "status_arg")]
+    pub arg: Arg,
+    pub data: Vec<SystemStatus>,
+}
 
 // Private Channels Starts from her
 #[derive(Debug, Serialize, Deserialize, Clone)]
@@ -313,7 +692,7 @@ pub struct PositionsEvent {
     pub arg: Arg,
     pub data: Vec<Positions>,
 }
-#[derive(Debug, Clone, Serialize, Deserialize)]
+#[derive(Debug, Clone, Serialize, Deserialize, Default)]
 #[serde(rename_all = "camelCase")]
 pub struct Positions {
     pub adl: String,
@@ -412,7 +791,7 @@ pub struct OrderEvent {
     pub data: Vec<Order>,
 }
 
-#[derive(Debug, Clone, Serialize, Deserialize)]
+#[derive(Debug, Clone, Serialize, Deserialize, Default)]
 #[serde(rename_all = "camelCase")]
 pub struct Order {
     pub inst_type: String,
@@ -496,6 +875,37 @@ pub struct AlgoOrders {
     pub c_time: String,
 }
 
+impl From<&AlgoOrders> for AlgoOrderDetail {
+    fn from(order: &AlgoOrders) -> Self {
+        AlgoOrderDetail {
+            inst_type: order.inst_type.clone(),
+            inst_id: order.inst_id.clone(),
+            ccy: order.ccy.clone(),
+            ord_id: order.ord_id.clone(),
+            algo_id: order.algo_id.clone(),
+            sz: order.sz.clone(),
+            ord_type: order.ord_type.clone(),
+            side: order.side.clone(),
+            pos_side: order.pos_side.clone(),
+            td_mode: order.td_mode.clone(),
+            tgt_ccy: order.tgt_ccy.clone(),
+            state: order.state.clone(),
+            lever: order.lever.clone(),
+            tp_trigger_px: order.tp_trigger_px.clone(),
+            tp_ord_px: order.tp_ord_px.clone(),
+            sl_trigger_px: order.sl_trigger_px.clone(),
+            trigger_px: order.trigger_px.clone(),
+            ord_px: order.ord_px.clone(),
+            actual_sz: order.actual_sz.clone(),
+            actual_px: order.actual_px.clone(),
+            actual_side: order.actual_side.clone(),
+            trigger_time: order.trigger_time.clone(),
+            c_time: order.c_time.clone(),
+            ..Default::default()
+        }
+    }
+}
+
 #[derive(Debug, Clone, Serialize, Deserialize)]
 #[serde(rename_all = "camelCase")]
 pub struct AdvanceAlgoOrdersEvent {
@@ -537,3 +947,83 @@ pub struct AdvanceAlgoOrders {
     pub trigger_px: String,
     pub trigger_time: String,
 }
+
+impl From<&AdvanceAlgoOrders> for AlgoOrderDetail {
+    fn from(order: &AdvanceAlgoOrders) -> Self {
+        AlgoOrderDetail {
+            inst_type: order.inst_type.clone(),
+            inst_id: order.inst_id.clone(),
+            ccy: order.ccy.clone(),
+            algo_id: order.algo_id.clone(),
+            sz: order.sz.clone(),
+            ord_type: order.ord_type.clone(),
+            side: order.side.clone(),
+            pos_side: order.pos_side.clone(),
+            td_mode: order.td_mode.clone(),
+            state: order.state.clone(),
+            lever: order.lever.clone(),
+            tp_trigger_px: order.tp_trigger_px.clone(),
+            tp_ord_px: order.tp_ord_px.clone(),
+            sl_trigger_px: order.sl_trigger_px.clone(),
+            sl_ord_px: order.sl_ord_px.clone(),
+            trigger_px: order.trigger_px.clone(),
+            ord_px: order.ord_px.clone(),
+            actual_sz: order.actual_sz.clone(),
+            actual_px: order.actual_px.clone(),
+            actual_side: order.actual_side.clone(),
+            trigger_time: order.trigger_time.clone(),
+            px_var: order.px_var.clone(),
+            px_spread: order.px_spread.clone(),
+            sz_limit: order.sz_limit.clone(),
+            px_limit: order.px_limit.clone(),
+            time_interval: order.time_interval.clone(),
+            c_time: order.c_time.clone(),
+            ..Default::default()
+        }
+    }
+}
+
+#[cfg(test)]
+mod test {
+    use super::WebsocketEvent;
+
+    fn parse(push: &str) -> WebsocketEvent {
+        serde_json::from_str(push).unwrap()
+    }
+
+    #[test]
+    fn public_channels_serde() {
+        let event = parse(
+            r#"{"arg":{"channel":"mark-price","instId":"BTC-USDT-SWAP"},"data":[{"instType":"SWAP","instId":"BTC-USDT-SWAP","markPx":"42310.6","ts":"1630049139746"}]}"#,
+        );
+        assert!(matches!(event, WebsocketEvent::MarkPrice(e) if e.data[0].mark_px == 42310.6));
+
+        let event = parse(
+            r#"{"arg":{"channel":"candle1D","instId":"BTC-USDT"},"data":[["1597026383085","8533.02","8553.74","8527.17","8548.26","45247","529.5858061","529.58","0"]]}"#,
+        );
+        assert!(matches!(event, WebsocketEvent::Candlesticks(_)));
+
+        // mark price and index candles only differ by channel
+        let candle = r#"[["1597026383085","3.721","3.743","3.677","3.708","1"]]"#;
+        let event = parse(&format!(
+            r#"{{"arg":{{"channel":"index-candle30m","instId":"BTC-USDT"}},"data":{}}}"#,
+            candle
+        ));
+        assert!(matches!(event, WebsocketEvent::IndexCandlesticks(e) if e.data[0].confirm == 1));
+        let event = parse(&format!(
+            r#"{{"arg":{{"channel":"mark-price-candle1m","instId":"BTC-USDT-SWAP"}},"data":{}}}"#,
+            candle
+        ));
+        assert!(matches!(event, WebsocketEvent::MarkPriceCandlesticks(_)));
+
+        let event = parse(
+            r#"{"arg":{"channel":"funding-rate","instId":"BTC-USD-SWAP"},"data":[{"fundingRate":"0.0001875391284828","fundingTime":"1700726400000","instId":"BTC-USD-SWAP","instType":"SWAP","nextFundingRate":"","nextFundingTime":"1700755200000"}]}"#,
+        );
+        assert!(matches!(event, WebsocketEvent::FundingRate(_)));
+
+        let event = parse(
+            r#"{"arg":{"channel":"status"},"data":[{"title":"Spot System Upgrade","state":"scheduled","begin":"1610019546","href":"","end":"1610019546","serviceType":"1","system":"classic","scheDesc":"","ts":"1597026383085"}]}"#,
+        );
+        assert!(matches!(event, WebsocketEvent::Status(_)));
+    }
+}
@@ -0,0 +1,254 @@
+//! Reconnecting websocket connections.
+//!
+//! A `Supervisor` owns a connection and runs its event loop. When the loop ends with an error
+//! it reconnects with exponential backoff, lets the connection replay its login and
+//! subscriptions through `Supervised::reconnect` and reports every state change on a channel,
+//! so strategies can pull their quotes while a feed is down.
+
+use std::fmt::Display;
+use std::sync::atomic::{AtomicBool, Ordering};
+use std::time::Duration;
+
+use async_trait::async_trait;
+use log::{info, warn};
+use serde::{Deserialize, Serialize};
+use tokio::sync::mpsc;
+
+#[derive(Debug, Copy, Clone, PartialEq, Eq, Serialize, Deserialize)]
+#[serde(rename_all = "snake_case")]
+pub enum ConnectionState {
+    /// Connecting, logging in and subscribing
+    Connecting,
+    /// Login and subscriptions replayed, events are flowing
+    Connected,
+    /// The connection dropped or a reconnect attempt failed
+    Disconnected,
+}
+
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct ConnectionEvent {
+    /// Name given to the supervisor, e.g. "okex private"
+    pub name: String,
+    pub state: ConnectionState,
+    /// Failed reconnect attempts since the last successful connection
+    pub attempt: u32,
+}
+
+/// Exponential backoff, doubling from `initial` up to `max`
+#[derive(Debug, Clone)]
+pub struct Backoff {
+    initial: Duration,
+    max: Duration,
+    current: Duration,
+}
+
+impl Backoff {
+    pub fn new(initial: Duration, max: Duration) -> Self {
+        Backoff {
+            initial,
+            max,
+            current: initial,
+        }
+    }
+
+    /// Delay to wait now, the following one is twice as long
+    pub fn next_delay(&mut self) -> Duration {
+        let delay = self.current;
+        self.current = std::cmp::min(self.current * 2, self.max);
+        delay
+    }
+
+    pub fn reset(&mut self) {
+        self.current = self.initial;
+    }
+}
+
+impl Default for Backoff {
+    fn default() -> Self {
+        Backoff::new(Duration::from_millis(500), Duration::from_secs(30))
+    }
+}
+
+/// A connection that remembers how it was set up
+#[async_trait(?Send)]
+pub trait Supervised {
+    type Error: Display;
+
+    /// True while the socket is open
+    fn is_connected(&self) -> bool;
+
+    /// Opens the socket again on the last endpoint and replays login and subscriptions
+    async fn reconnect(&mut self) -> Result<(), Self::Error>;
+
+    /// Forwards events until `running` is cleared or the connection fails
+    async fn event_loop(&mut self, running: &AtomicBool) -> Result<(), Self::Error>;
+}
+
+pub struct Supervisor<S: Supervised> {
+    name: String,
+    socket: S,
+    backoff: Backoff,
+    states: Option<mpsc::UnboundedSender<ConnectionEvent>>,
+}
+
+impl<S: Supervised> Supervisor<S> {
+    /// Supervises `socket`, which may already be connected, logged in and subscribed. One that
+    /// is not gets its first connection from `run` with the same backoff as the reconnects.
+    pub fn new(name: impl Into<String>, socket: S) -> Self {
+        Supervisor {
+            name: name.into(),
+            socket,
+            backoff: Backoff::default(),
+            states: None,
+        }
+    }
+
+    pub fn with_backoff(mut self, backoff: Backoff) -> Self {
+        self.backoff = backoff;
+        self
+    }
+
+    /// Connection state changes are sent here
+    pub fn with_states(mut self, states: mpsc::UnboundedSender<ConnectionEvent>) -> Self {
+        self.states = Some(states);
+        self
+    }
+
+    pub fn socket(&self) -> &S {
+        &self.socket
+    }
+
+    pub fn socket_mut(&mut self) -> &mut S {
+        &mut self.socket
+    }
+
+    /// Runs the event loop until `running` is cleared, reconnecting whenever it fails
+    pub async fn run(&mut self, running: &AtomicBool) {
+        let mut attempt = 0;
+        let mut connected = self.socket.is_connected();
+        if connected {
+            self.notify(ConnectionState::Connected, attempt);
+        }
+        while running.load(Ordering::Relaxed) {
+            if !connected {
+                self.notify(ConnectionState::Connecting, attempt);
+                if let Err(e) = self.socket.reconnect().await {
+                    attempt += 1;
+                    let delay = self.backoff.next_delay();
+                    warn!(
+                        "{} reconnect attempt {} failed: {}, retrying in {:?}",
+                        self.name, attempt, e, delay
+                    );
+                    self.notify(ConnectionState::Disconnected, attempt);
+                    tokio::time::sleep(delay).await;
+                    continue;
+                }
+                info!(
+                    "{} reconnected after {} failed attempts",
+                    self.name, attempt
+                );
+                attempt = 0;
+                self.backoff.reset();
+                self.notify(ConnectionState::Connected, attempt);
+            }
+
+            match self.socket.event_loop(running).await {
+                Ok(()) if !running.load(Ordering::Relaxed) => break,
+                Ok(()) => warn!("{} event loop stopped, reconnecting", self.name),
+                Err(e) => warn!("{} event loop error: {}, reconnecting", self.name, e),
+            }
+            connected = false;
+            self.notify(ConnectionState::Disconnected, attempt);
+            tokio::time::sleep(self.backoff.next_delay()).await;
+        }
+    }
+
+    fn notify(&self, state: ConnectionState, attempt: u32) {
+        if let Some(states) = &self.states {
+            // nobody listening is fine, the supervisor keeps the connection up regardless
+            let _ = states.send(ConnectionEvent {
+                name: self.name.clone(),
+                state,
+                attempt,
+            });
+        }
+    }
+}
+
+#[cfg(test)]
+mod test {
+    use super::*;
+
+    #[test]
+    fn test_backoff() {
+        let mut backoff = Backoff::new(Duration::from_millis(100), Duration::from_millis(350));
+        assert_eq!(backoff.next_delay(), Duration::from_millis(100));
+        assert_eq!(backoff.next_delay(), Duration::from_millis(200));
+        assert_eq!(backoff.next_delay(), Duration::from_millis(350));
+        assert_eq!(backoff.next_delay(), Duration::from_millis(350));
+        backoff.reset();
+        assert_eq!(backoff.next_delay(), Duration::from_millis(100));
+    }
+
+    /// Fails the first `failures` connection attempts, the event loop ends the run
+    struct FlakySocket {
+        failures: u32,
+        connected: bool,
+    }
+
+    #[async_trait(?Send)]
+    impl Supervised for FlakySocket {
+        type Error = String;
+
+        fn is_connected(&self) -> bool {
+            self.connected
+        }
+
+        async fn reconnect(&mut self) -> Result<(), String> {
+            if self.failures > 0 {
+                self.failures -= 1;
+                return Err("refused".to_string());
+            }
+            self.connected = true;
+            Ok(())
+        }
+
+        async fn event_loop(&mut self, running: &AtomicBool) -> Result<(), String> {
+            running.store(false, Ordering::Relaxed);
+            Ok(())
+        }
+    }
+
+    #[tokio::test]
+    async fn test_first_connection() {
+        let (states_tx, mut states_rx) = mpsc::unbounded_channel();
+        let socket = FlakySocket {
+            failures: 2,
+            connected: false,
+        };
+        let mut supervisor = Supervisor::new("flaky", socket)
+            .with_backoff(Backoff::new(
+                Duration::from_millis(1),
+                Duration::from_millis(1),
+            ))
+            .with_states(states_tx);
+        supervisor.run(&AtomicBool::new(true)).await;
+        assert!(supervisor.socket().connected);
+
+        let mut states = Vec::new();
+        while let Ok(event) = states_rx.try_recv() {
+            states.push((event.state, event.attempt));
+        }
+        assert_eq!(
+            states,
+            vec![
+                (ConnectionState::Connecting, 0),
+                (ConnectionState::Disconnected, 1),
+                (ConnectionState::Connecting, 1),
+                (ConnectionState::Disconnected, 2),
+                (ConnectionState::Connecting, 2),
+                (ConnectionState::Connected, 0),
+            ]
+        );
+    }
+}
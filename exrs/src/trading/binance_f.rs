@@ -0,0 +1,229 @@
+use async_trait::async_trait;
+
+use super::{
+    Balance, Error, Fill, NewOrder, OpenOrder, OrderAck, OrderStatus, OrderType, Position, Result,
+    Side, TradingClient, Venue,
+};
+use crate::binance_f::account::{
+    FuturesAccount, ModifyOrderRequest, OrderCancellation, OrderRequest, PositionSide,
+};
+use crate::binance_f::rest_model::{self, TimeInForce, Transaction};
+use crate::binance_f::ws_model::OrderTradeUpdate;
+
+pub(crate) fn side(side: &str) -> Side {
+    match side {
+        "SELL" => Side::Sell,
+        _ => Side::Buy,
+    }
+}
+
+fn order_side(side: Side) -> rest_model::OrderSide {
+    match side {
+        Side::Buy => rest_model::OrderSide::Buy,
+        Side::Sell => rest_model::OrderSide::Sell,
+    }
+}
+
+pub(crate) fn order_status(status: &str) -> OrderStatus {
+    match status {
+        "PARTIALLY_FILLED" => OrderStatus::PartiallyFilled,
+        "FILLED" => OrderStatus::Filled,
+        "CANCELED" => OrderStatus::Canceled,
+        "REJECTED" => OrderStatus::Rejected,
+        "EXPIRED" => OrderStatus::Expired,
+        _ => OrderStatus::New,
+    }
+}
+
+fn ack(tx: Transaction) -> OrderAck {
+    OrderAck {
+        order_id: tx.order_id.to_string(),
+        client_order_id: tx.client_order_id,
+    }
+}
+
+impl From<&Transaction> for OpenOrder {
+    fn from(tx: &Transaction) -> Self {
+        OpenOrder {
+            symbol: tx.symbol.clone(),
+            order_id: tx.order_id.to_string(),
+            client_order_id: tx.client_order_id.clone(),
+            side: side(&tx.side),
+            price: tx.price,
+            qty: tx.orig_qty,
+            filled_qty: tx.executed_qty,
+            avg_price: tx.avg_price,
+            status: order_status(&tx.status),
+            update_time: tx.update_time,
+        }
+    }
+}
+
+impl Fill {
+    /// Fill carried by an ORDER_TRADE_UPDATE, None if the update is not a trade
+    pub fn from_binance_futures(update: &OrderTradeUpdate) -> Option<Fill> {
+        if update.execution_type != "TRADE" {
+            return None;
+        }
+        Some(Fill {
+            symbol: update.symbol.clone(),
+            order_id: update.order_id.to_string(),
+            client_order_id: update.client_order_id.clone(),
+            trade_id: update.trade_id.to_string(),
+            side: side(&update.side),
+            price: update.last_filled_price,
+            qty: update.order_last_filled_quantity,
+            fee: update.commission.unwrap_or(0.),
+            fee_asset: update.commission_asset.clone().unwrap_or_default(),
+            is_maker: update.is_maker,
+            ts: update.order_trade_time,
+        })
+    }
+}
+
+impl FuturesAccount {
+    async fn find_open_order(&self, symbol: &str, client_order_id: &str) -> Result<Transaction> {
+        self.get_all_open_orders(symbol)
+            .await?
+            .into_iter()
+            .find(|tx| tx.client_order_id == client_order_id)
+            .ok_or_else(|| Error::OrderNotFound(client_order_id.to_string()))
+    }
+}
+
+#[async_trait]
+impl TradingClient for FuturesAccount {
+    fn venue(&self) -> Venue {
+        Venue::BinanceFutures
+    }
+
+    async fn place_order(&self, order: NewOrder) -> Result<OrderAck> {
+        let (order_type, time_in_force) = match order.order_type {
+            OrderType::Limit => (rest_model::OrderType::Limit, Some(TimeInForce::GTC)),
+            // GTX is binance's post only
+            OrderType::PostOnly => (rest_model::OrderType::Limit, Some(TimeInForce::GTX)),
+            OrderType::Market => (rest_model::OrderType::Market, None),
+        };
+        let request = OrderRequest {
+            symbol: order.symbol,
+            side: order_side(order.side),
+            order_type,
+            time_in_force,
+            qty: Some(order.qty),
+            reduce_only: if order.reduce_only { Some(true) } else { None },
+            price: match order.order_type {
+                OrderType::Market => None,
+                _ => order.price,
+            },
+            new_client_order_id: order.client_order_id,
+            ..Default::default()
+        };
+        Ok(ack(FuturesAccount::place_order(self, request).await?))
+    }
+
+    async fn cancel_order(&self, symbol: &str, client_order_id: &str) -> Result<()> {
+        FuturesAccount::cancel_order(
+            self,
+            OrderCancellation {
+                symbol: symbol.to_string(),
+                orig_client_order_id: Some(client_order_id.to_string()),
+                ..Default::default()
+            },
+        )
+        .await?;
+        Ok(())
+    }
+
+    async fn cancel_all(&self, symbol: &str) -> Result<()> {
+        self.cancel_all_open_orders(symbol).await?;
+        Ok(())
+    }
+
+    async fn amend_order(
+        &self,
+        symbol: &str,
+        client_order_id: &str,
+        new_qty: Option<f64>,
+        new_price: Option<f64>,
+    ) -> Result<OrderAck> {
+        // binance wants side, quantity and price on every modification
+        let current = self.find_open_order(symbol, client_order_id).await?;
+        let request = ModifyOrderRequest {
+            symbol: symbol.to_string(),
+            order_id: None,
+            orig_client_order_id: Some(client_order_id.to_string()),
+            side: order_side(side(&current.side)),
+            quantity: new_qty.unwrap_or(current.orig_qty),
+            price: new_price.unwrap_or(current.price),
+        };
+        Ok(ack(self.modify_order(request).await?))
+    }
+
+    async fn close_position(&self, symbol: &str) -> Result<()> {
+        for position in self.position_information(symbol).await? {
+            if position.position_amount == 0. {
+                continue;
+            }
+            let side = if position.position_amount > 0. {
+                rest_model::OrderSide::Sell
+            } else {
+                rest_model::OrderSide::Buy
+            };
+            // reduceOnly is rejected in hedge mode, the position side does the job there
+            let (position_side, reduce_only) = match position.position_side.as_str() {
+                "LONG" => (Some(PositionSide::Long), None),
+                "SHORT" => (Some(PositionSide::Short), None),
+                _ => (None, Some(true)),
+            };
+            let request = OrderRequest {
+                symbol: symbol.to_string(),
+                side,
+                position_side,
+                order_type: rest_model::OrderType::Market,
+                qty: Some(position.position_amount.abs()),
+                reduce_only,
+                ..Default::default()
+            };
+            FuturesAccount::place_order(self, request).await?;
+        }
+        Ok(())
+    }
+
+    async fn positions(&self, symbol: &str) -> Result<Vec<Position>> {
+        Ok(self
+            .position_information(symbol)
+            .await?
+            .iter()
+            .map(|p| Position {
+                symbol: p.symbol.clone(),
+                qty: p.position_amount,
+                entry_price: p.entry_price,
+                mark_price: p.mark_price,
+                unrealized_pnl: p.unrealized_profit,
+            })
+            .collect())
+    }
+
+    async fn balances(&self) -> Result<Vec<Balance>> {
+        Ok(self
+            .account_balance()
+            .await?
+            .iter()
+            .map(|b| Balance {
+                asset: b.asset.clone(),
+                total: b.balance,
+                available: b.available_balance,
+                unrealized_pnl: b.cross_unrealized_pnl,
+            })
+            .collect())
+    }
+
+    async fn open_orders(&self, symbol: &str) -> Result<Vec<OpenOrder>> {
+        Ok(self
+            .get_all_open_orders(symbol)
+            .await?
+            .iter()
+            .map(OpenOrder::from)
+            .collect())
+    }
+}
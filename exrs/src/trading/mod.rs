@@ -0,0 +1,243 @@
+//! Venue independent trading interface.
+//!
+//! Strategies talk to `TradingClient` with the unified types below, `connect` picks the venue
+//! implementation from config.
+
+use async_trait::async_trait;
+use serde::{Deserialize, Serialize};
+use thiserror::Error;
+
+pub(crate) mod binance_f;
+pub(crate) mod okex_v5;
+
+#[derive(Debug, Copy, Clone, PartialEq, Eq, Serialize, Deserialize)]
+#[serde(rename_all = "snake_case")]
+pub enum Venue {
+    Okex,
+    BinanceFutures,
+}
+
+#[derive(Debug, Copy, Clone, PartialEq, Eq, Serialize, Deserialize)]
+#[serde(rename_all = "snake_case")]
+pub enum Side {
+    Buy,
+    Sell,
+}
+
+impl Side {
+    /// +1 for buys, -1 for sells
+    pub fn sign(&self) -> f64 {
+        match self {
+            Side::Buy => 1.,
+            Side::Sell => -1.,
+        }
+    }
+}
+
+#[derive(Debug, Copy, Clone, PartialEq, Eq, Serialize, Deserialize)]
+#[serde(rename_all = "snake_case")]
+pub enum OrderType {
+    Limit,
+    /// Limit order rejected instead of taking liquidity
+    PostOnly,
+    Market,
+}
+
+#[derive(Debug, Copy, Clone, PartialEq, Eq, Serialize, Deserialize)]
+#[serde(rename_all = "snake_case")]
+pub enum OrderStatus {
+    New,
+    PartiallyFilled,
+    Filled,
+    Canceled,
+    Rejected,
+    Expired,
+}
+
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct NewOrder {
+    pub symbol: String,
+    pub side: Side,
+    pub order_type: OrderType,
+    pub qty: f64,
+    /// Ignored for market orders
+    pub price: Option<f64>,
+    pub client_order_id: Option<String>,
+    pub reduce_only: bool,
+}
+
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct OrderAck {
+    pub order_id: String,
+    pub client_order_id: String,
+}
+
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct OpenOrder {
+    pub symbol: String,
+    pub order_id: String,
+    pub client_order_id: String,
+    pub side: Side,
+    pub price: f64,
+    pub qty: f64,
+    pub filled_qty: f64,
+    pub avg_price: f64,
+    pub status: OrderStatus,
+    pub update_time: u64,
+}
+
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct Fill {
+    pub symbol: String,
+    pub order_id: String,
+    pub client_order_id: String,
+    pub trade_id: String,
+    pub side: Side,
+    pub price: f64,
+    pub qty: f64,
+    /// Fee paid in `fee_asset`, negative for rebates
+    pub fee: f64,
+    pub fee_asset: String,
+    pub is_maker: bool,
+    pub ts: u64,
+}
+
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct Position {
+    pub symbol: String,
+    /// Signed position, negative when short
+    pub qty: f64,
+    pub entry_price: f64,
+    pub mark_price: f64,
+    pub unrealized_pnl: f64,
+}
+
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct Balance {
+    pub asset: String,
+    pub total: f64,
+    pub available: f64,
+    pub unrealized_pnl: f64,
+}
+
+#[derive(Error, Debug)]
+pub enum Error {
+    #[error(transparent)]
+    Okex(#[from] crate::okex_v5::errors::Error),
+    #[error(transparent)]
+    BinanceFutures(#[from] crate::binance_f::errors::Error),
+    #[error("order rejected, code: {code}, msg: {msg}")]
+    Rejected { code: String, msg: String },
+    #[error("order not found: {0}")]
+    OrderNotFound(String),
+}
+
+pub type Result<T> = core::result::Result<T, Error>;
+
+#[async_trait]
+pub trait TradingClient: Send + Sync {
+    fn venue(&self) -> Venue;
+
+    async fn place_order(&self, order: NewOrder) -> Result<OrderAck>;
+
+    async fn cancel_order(&self, symbol: &str, client_order_id: &str) -> Result<()>;
+
+    /// Cancels every open order on `symbol`
+    async fn cancel_all(&self, symbol: &str) -> Result<()>;
+
+    /// Changes quantity and/or price of a live order, keeping what is None
+    async fn amend_order(
+        &self,
+        symbol: &str,
+        client_order_id: &str,
+        new_qty: Option<f64>,
+        new_price: Option<f64>,
+    ) -> Result<OrderAck>;
+
+    /// Flattens the position on `symbol` with a market order
+    async fn close_position(&self, symbol: &str) -> Result<()>;
+
+    async fn positions(&self, symbol: &str) -> Result<Vec<Position>>;
+
+    async fn balances(&self) -> Result<Vec<Balance>>;
+
+    async fn open_orders(&self, symbol: &str) -> Result<Vec<OpenOrder>>;
+
+    async fn limit_order(
+        &self,
+        symbol: &str,
+        side: Side,
+        qty: f64,
+        price: f64,
+        client_order_id: &str,
+    ) -> Result<OrderAck> {
+        self.place_order(NewOrder {
+            symbol: symbol.to_string(),
+            side,
+            order_type: OrderType::Limit,
+            qty,
+            price: Some(price),
+            client_order_id: Some(client_order_id.to_string()),
+            reduce_only: false,
+        })
+        .await
+    }
+
+    async fn market_order(&self, symbol: &str, side: Side, qty: f64) -> Result<OrderAck> {
+        self.place_order(NewOrder {
+            symbol: symbol.to_string(),
+            side,
+            order_type: OrderType::Market,
+            qty,
+            price: None,
+            client_order_id: None,
+            reduce_only: false,
+        })
+        .await
+    }
+}
+
+/// Credentials and environment for `connect`, `passphrase` is only used by OKX.
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct VenueConfig {
+    pub venue: Venue,
+    pub api_key: Option<String>,
+    pub secret_key: Option<String>,
+    pub passphrase: Option<String>,
+    pub is_testnet: bool,
+}
+
+pub fn connect(config: &VenueConfig) -> Box<dyn TradingClient> {
+    match config.venue {
+        Venue::Okex => {
+            use crate::okex_v5::api::Okex;
+            let api_config = crate::okex_v5::config::Config::new(config.is_testnet);
+            let account: crate::okex_v5::account::Account = Okex::new(
+                config.api_key.clone(),
+                config.secret_key.clone(),
+                config.passphrase.clone(),
+                &api_config,
+            );
+            Box::new(account)
+        }
+        Venue::BinanceFutures => {
+            use crate::binance_f::api::BinanceF;
+            let api_config = if config.is_testnet {
+                crate::binance_f::config::Config::testnet()
+            } else {
+                crate::binance_f::config::Config::default()
+            };
+            let account: crate::binance_f::account::FuturesAccount = BinanceF::new_with_config(
+                config.api_key.clone(),
+                config.secret_key.clone(),
+                &api_config,
+            );
+            Box::new(account)
+        }
+    }
+}
+
+/// Parses the numeric strings venues send, empty strings are 0
+pub(crate) fn parse_f64(s: &str) -> f64 {
+    s.parse().unwrap_or(0.)
+}
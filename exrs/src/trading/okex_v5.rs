@@ -0,0 +1,341 @@
+use async_trait::async_trait;
+
+use super::{
+    parse_f64, Balance, Error, Fill, NewOrder, OpenOrder, OrderAck, OrderStatus, OrderType,
+    Position, Result, Side, TradingClient, Venue,
+};
+use crate::okex_v5::account::Account;
+use crate::okex_v5::rest_model::{
+    self, AmendOrderRequest, OrderCancellation, OrderDetail, OrderRequest, PendingOrdersRequest,
+    PositionsRequest, TradeMode, TransactionResponse,
+};
+use crate::okex_v5::ws_model;
+
+/// OKX accepts at most 20 orders per batch cancel
+const BATCH_LIMIT: usize = 20;
+
+pub(crate) fn side(side: &str) -> Side {
+    match side {
+        "sell" => Side::Sell,
+        _ => Side::Buy,
+    }
+}
+
+pub(crate) fn order_status(state: &str) -> OrderStatus {
+    match state {
+        "partially_filled" => OrderStatus::PartiallyFilled,
+        "filled" => OrderStatus::Filled,
+        "canceled" => OrderStatus::Canceled,
+        _ => OrderStatus::New,
+    }
+}
+
+impl OrderAck {
+    /// Ack of the first order of a REST or websocket trade response, checks both the response
+    /// code and the per order sCode
+    pub fn from_okex(response: TransactionResponse) -> Result<OrderAck> {
+        match response.data.into_iter().next() {
+            Some(tx) if tx.s_code == 0 => Ok(OrderAck {
+                order_id: tx.ord_id,
+                client_order_id: tx.cl_ord_id,
+            }),
+            Some(tx) => Err(Error::Rejected {
+                code: tx.s_code.to_string(),
+                msg: tx.s_msg,
+            }),
+            None => Err(Error::Rejected {
+                code: response.code.to_string(),
+                msg: response.msg,
+            }),
+        }
+    }
+}
+
+/// Net mode request, the trade mode follows from the instrument id
+impl From<NewOrder> for OrderRequest {
+    fn from(order: NewOrder) -> Self {
+        let order_type = match order.order_type {
+            OrderType::Limit => rest_model::OrderType::Limit,
+            OrderType::PostOnly => rest_model::OrderType::PostOnly,
+            OrderType::Market => rest_model::OrderType::Market,
+        };
+        OrderRequest {
+            trade_mode: TradeMode::for_inst_id(&order.symbol),
+            symbol: order.symbol,
+            currency: None,
+            client_order_id: order.client_order_id,
+            tag: None,
+            side: match order.side {
+                Side::Buy => rest_model::OrderSide::Buy,
+                Side::Sell => rest_model::OrderSide::Sell,
+            },
+            position_side: None,
+            order_type,
+            qty: order.qty,
+            price: match order.order_type {
+                OrderType::Market => None,
+                _ => order.price,
+            },
+            reduce_only: if order.reduce_only { Some(true) } else { None },
+            target_currency: None,
+        }
+    }
+}
+
+impl From<&OrderDetail> for OpenOrder {
+    fn from(o: &OrderDetail) -> Self {
+        OpenOrder {
+            symbol: o.inst_id.clone(),
+            order_id: o.ord_id.clone(),
+            client_order_id: o.cl_ord_id.clone(),
+            side: side(&o.side),
+            price: parse_f64(&o.px),
+            qty: parse_f64(&o.sz),
+            filled_qty: parse_f64(&o.acc_fill_sz),
+            avg_price: parse_f64(&o.avg_px),
+            status: order_status(&o.state),
+            update_time: o.u_time.parse().unwrap_or(0),
+        }
+    }
+}
+
+impl Fill {
+    /// Fill carried by an update of the orders channel, None if the update is not a fill
+    pub fn from_okex(order: &ws_model::Order) -> Option<Fill> {
+        let qty = parse_f64(&order.fill_sz);
+        if qty == 0. {
+            return None;
+        }
+        Some(Fill {
+            symbol: order.inst_id.clone(),
+            order_id: order.ord_id.clone(),
+            client_order_id: order.cl_ord_id.clone(),
+            trade_id: order.trade_id.clone(),
+            side: side(&order.side),
+            price: parse_f64(&order.fill_px),
+            qty,
+            // OKX reports charges as negative amounts
+            fee: -parse_f64(&order.fill_fee),
+            fee_asset: order.fill_fee_ccy.clone(),
+            is_maker: order.exec_type == "M",
+            ts: order.fill_time.parse().unwrap_or(0),
+        })
+    }
+}
+
+impl Account {
+    async fn pending_orders(&self, symbol: &str) -> Result<Vec<OrderDetail>> {
+        let response = self
+            .get_orders_pending(PendingOrdersRequest {
+                inst_id: Some(symbol.to_string()),
+                ..Default::default()
+            })
+            .await?;
+        if response.code != 0 {
+            return Err(Error::Rejected {
+                code: response.code.to_string(),
+                msg: response.msg,
+            });
+        }
+        Ok(response.data)
+    }
+}
+
+#[async_trait]
+impl TradingClient for Account {
+    fn venue(&self) -> Venue {
+        Venue::Okex
+    }
+
+    async fn place_order(&self, order: NewOrder) -> Result<OrderAck> {
+        OrderAck::from_okex(Account::place_order(self, order.into()).await?)
+    }
+
+    async fn cancel_order(&self, symbol: &str, client_order_id: &str) -> Result<()> {
+        let response = self
+            .cancel_order(OrderCancellation {
+                symbol: symbol.to_string(),
+                order_id: None,
+                orig_client_order_id: Some(client_order_id.to_string()),
+            })
+            .await?;
+        OrderAck::from_okex(response).map(|_| ())
+    }
+
+    async fn cancel_all(&self, symbol: &str) -> Result<()> {
+        let orders = self.pending_orders(symbol).await?;
+        for chunk in orders.chunks(BATCH_LIMIT) {
+            let cancellations = chunk
+                .iter()
+                .map(|o| OrderCancellation {
+                    symbol: o.inst_id.clone(),
+                    order_id: Some(o.ord_id.clone()),
+                    orig_client_order_id: None,
+                })
+                .collect();
+            let response = self.cancel_all_open_orders(cancellations).await?;
+            if let Some(tx) = response.data.iter().find(|tx| tx.s_code != 0) {
+                return Err(Error::Rejected {
+                    code: tx.s_code.to_string(),
+                    msg: tx.s_msg.clone(),
+                });
+            }
+        }
+        Ok(())
+    }
+
+    async fn amend_order(
+        &self,
+        symbol: &str,
+        client_order_id: &str,
+        new_qty: Option<f64>,
+        new_price: Option<f64>,
+    ) -> Result<OrderAck> {
+        let response = Account::amend_order(
+            self,
+            AmendOrderRequest {
+                symbol: symbol.to_string(),
+                order_id: None,
+                client_order_id: Some(client_order_id.to_string()),
+                new_qty,
+                new_price,
+            },
+        )
+        .await?;
+        OrderAck::from_okex(response)
+    }
+
+    async fn close_position(&self, symbol: &str) -> Result<()> {
+        let response = Account::close_position(self, symbol, None).await?;
+        if response.code != 0 {
+            return Err(Error::Rejected {
+                code: response.code.to_string(),
+                msg: response.msg,
+            });
+        }
+        Ok(())
+    }
+
+    async fn positions(&self, symbol: &str) -> Result<Vec<Position>> {
+        let response = self
+            .get_positions(PositionsRequest {
+                inst_id: Some(symbol.to_string()),
+                ..Default::default()
+            })
+            .await?;
+        Ok(response
+            .data
+            .iter()
+            .map(|p| {
+                // in long/short mode `pos` is unsigned and the side is in `posSide`
+                let qty = match p.pos_side.as_str() {
+                    "short" => -parse_f64(&p.pos).abs(),
+                    _ => parse_f64(&p.pos),
+                };
+                Position {
+                    symbol: p.inst_id.clone(),
+                    qty,
+                    entry_price: parse_f64(&p.avg_px),
+                    mark_price: parse_f64(&p.mark_px),
+                    unrealized_pnl: parse_f64(&p.upl),
+                }
+            })
+            .collect())
+    }
+
+    async fn balances(&self) -> Result<Vec<Balance>> {
+        let response = self.get_balance(None).await?;
+        Ok(response
+            .data
+            .iter()
+            .flat_map(|b| b.details.iter())
+            .map(|d| Balance {
+                asset: d.ccy.clone(),
+                total: parse_f64(&d.eq),
+                available: parse_f64(&d.avail_bal),
+                unrealized_pnl: parse_f64(&d.upl),
+            })
+            .collect())
+    }
+
+    async fn open_orders(&self, symbol: &str) -> Result<Vec<OpenOrder>> {
+        let orders = self.pending_orders(symbol).await?;
+        Ok(orders.iter().map(OpenOrder::from).collect())
+    }
+}
+
+#[cfg(test)]
+mod test {
+    use super::*;
+    use crate::okex_v5::rest_model::Transaction;
+
+    fn response(s_code: u16) -> TransactionResponse {
+        TransactionResponse {
+            code: if s_code == 0 { 0 } else { 1 },
+            msg: String::new(),
+            data: vec![Transaction {
+                cl_ord_id: "c1".into(),
+                ord_id: "1".into(),
+                tag: None,
+                s_code,
+                s_msg: "Insufficient balance".into(),
+            }],
+        }
+    }
+
+    #[test]
+    fn test_okex_mapping() {
+        assert!(matches!(
+            TradeMode::for_inst_id("BTC-USDT"),
+            TradeMode::Cash
+        ));
+        assert!(matches!(
+            TradeMode::for_inst_id("BTC-USDT-SWAP"),
+            TradeMode::Cross
+        ));
+
+        let ack = OrderAck::from_okex(response(0)).unwrap();
+        assert_eq!(
+            (ack.order_id.as_str(), ack.client_order_id.as_str()),
+            ("1", "c1")
+        );
+        match OrderAck::from_okex(response(51008)) {
+            Err(Error::Rejected { code, .. }) => assert_eq!(code, "51008"),
+            other => panic!("expected a rejection, got {:?}", other),
+        }
+
+        let order = OpenOrder::from(&OrderDetail {
+            inst_id: "BTC-USDT-SWAP".into(),
+            side: "sell".into(),
+            px: "100.5".into(),
+            sz: "3".into(),
+            acc_fill_sz: "1".into(),
+            avg_px: "".into(),
+            state: "partially_filled".into(),
+            u_time: "1650000000000".into(),
+            ..Default::default()
+        });
+        assert_eq!(order.side, Side::Sell);
+        assert_eq!(order.status, OrderStatus::PartiallyFilled);
+        assert_eq!((order.price, order.qty, order.filled_qty), (100.5, 3., 1.));
+        assert_eq!((order.avg_price, order.update_time), (0., 1650000000000));
+
+        let mut update = ws_model::Order {
+            inst_id: "BTC-USDT-SWAP".into(),
+            side: "buy".into(),
+            fill_sz: "0".into(),
+            ..Default::default()
+        };
+        assert!(Fill::from_okex(&update).is_none());
+        update.fill_sz = "2".into();
+        update.fill_px = "99".into();
+        update.fill_fee = "-0.1".into();
+        update.exec_type = "M".into();
+        let fill = Fill::from_okex(&update).unwrap();
+        assert_eq!(
+            (fill.side, fill.price, fill.qty, fill.fee),
+            (Side::Buy, 99., 2., 0.1)
+        );
+        assert!(fill.is_maker);
+    }
+}
@@ -0,0 +1,276 @@
+use super::clock::SimulatedClock;
+use super::exchange::SimulatedExchange;
+use crate::strategies::okex_account::OkexAccount;
+
+use async_trait::async_trait;
+use exrs::okex_v5::{
+    errors::{Error, Result},
+    rest_model::{
+        AlgoCancellation, AlgoOrderRequest, AlgoTransaction, OrderCancellation, OrderSide,
+        RestResponse, Transaction, TransactionResponse,
+    },
+};
+use exrs::trading::{
+    self, Balance, NewOrder, OpenOrder, OrderAck, OrderType, Position, Side, TradingClient, Venue,
+};
+use serde::Serialize;
+use std::sync::atomic::{AtomicU64, Ordering};
+use std::sync::{Arc, Mutex};
+
+#[derive(Debug, Copy, Clone, Serialize)]
+#[serde(rename_all = "snake_case")]
+pub enum OrderAction {
+    Place,
+    Cancel,
+    ClosePosition,
+}
+
+/// A request the strategy sent to the simulated account.
+#[derive(Debug, Clone, Serialize)]
+pub struct SentOrder {
+    pub ts: u64,
+    pub action: OrderAction,
+    pub inst_id: String,
+    pub ord_id: String,
+    pub cl_ord_id: Option<String>,
+    pub side: Option<OrderSide>,
+    pub qty: Option<f64>,
+    pub price: Option<f64>,
+}
+
+/// Stand-in for `okex_v5::account::Account`, captures every request and routes it to the
+/// simulated exchange instead of sending it.
+#[derive(Clone)]
+pub struct SimulatedAccount {
+    clock: SimulatedClock,
+    exchange: Arc<Mutex<SimulatedExchange>>,
+    next_ord_id: Arc<AtomicU64>,
+    sent_orders: Arc<Mutex<Vec<SentOrder>>>,
+}
+
+impl SimulatedAccount {
+    pub fn new(clock: SimulatedClock, exchange: Arc<Mutex<SimulatedExchange>>) -> Self {
+        SimulatedAccount {
+            clock,
+            exchange,
+            next_ord_id: Arc::new(AtomicU64::new(1)),
+            sent_orders: Arc::new(Mutex::new(Vec::new())),
+        }
+    }
+
+    pub fn sent_orders(&self) -> Vec<SentOrder> {
+        self.sent_orders.lock().unwrap().clone()
+    }
+
+    fn next_ord_id(&self) -> String {
+        self.next_ord_id.fetch_add(1, Ordering::Relaxed).to_string()
+    }
+
+    fn record(&self, order: SentOrder, s_code: u16) -> Transaction {
+        let tx = Transaction {
+            cl_ord_id: order.cl_ord_id.clone().unwrap_or_default(),
+            ord_id: order.ord_id.clone(),
+            tag: None,
+            s_code,
+            s_msg: String::new(),
+        };
+        self.sent_orders.lock().unwrap().push(order);
+        tx
+    }
+
+    fn place(
+        &self,
+        symbol: &str,
+        side: OrderSide,
+        qty: f64,
+        price: f64,
+        cl_ord_id: &str,
+    ) -> Transaction {
+        let ts = self.clock.now();
+        let ord_id = self.next_ord_id();
+        let sim_side = match side {
+            OrderSide::Buy => Side::Buy,
+            OrderSide::Sell => Side::Sell,
+        };
+        self.exchange
+            .lock()
+            .unwrap()
+            .submit(ts, symbol, &ord_id, cl_ord_id, sim_side, price, qty);
+
+        self.record(
+            SentOrder {
+                ts,
+                action: OrderAction::Place,
+                inst_id: symbol.to_string(),
+                ord_id,
+                cl_ord_id: Some(cl_ord_id.to_string()),
+                side: Some(side),
+                qty: Some(qty),
+                price: Some(price),
+            },
+            0,
+        )
+    }
+
+    fn cancel(&self, order: OrderCancellation) -> Transaction {
+        let ts = self.clock.now();
+        let cl_ord_id = order.orig_client_order_id.unwrap_or_default();
+        let found = self.exchange.lock().unwrap().cancel(ts, &cl_ord_id);
+
+        self.record(
+            SentOrder {
+                ts,
+                action: OrderAction::Cancel,
+                inst_id: order.symbol,
+                ord_id: order.order_id.unwrap_or_default(),
+                cl_ord_id: Some(cl_ord_id),
+                side: None,
+                qty: None,
+                price: None,
+            },
+            if found { 0 } else { ORDER_NOT_EXIST },
+        )
+    }
+}
+
+/// OKX sCode for cancelling an order that is already gone.
+const ORDER_NOT_EXIST: u16 = 51400;
+/// OKX sCode for closing a position that does not exist.
+const POSITION_NOT_EXIST: u16 = 51023;
+
+fn accepted(data: Vec<Transaction>) -> TransactionResponse {
+    TransactionResponse {
+        code: 0,
+        msg: String::new(),
+        data,
+    }
+}
+
+fn not_simulated(what: &str) -> trading::Error {
+    Error::Msg(format!("{} is not simulated", what)).into()
+}
+
+#[async_trait]
+impl TradingClient for SimulatedAccount {
+    fn venue(&self) -> Venue {
+        Venue::Okex
+    }
+
+    /// Limit orders rest on the simulated exchange, market orders are not simulated
+    async fn place_order(&self, order: NewOrder) -> trading::Result<OrderAck> {
+        let price = match (order.order_type, order.price) {
+            (OrderType::Market, _) | (_, None) => return Err(not_simulated("market order")),
+            (_, Some(price)) => price,
+        };
+        let side = match order.side {
+            Side::Buy => OrderSide::Buy,
+            Side::Sell => OrderSide::Sell,
+        };
+        let cl_ord_id = order.client_order_id.unwrap_or_default();
+        let tx = self.place(&order.symbol, side, order.qty, price, &cl_ord_id);
+        Ok(OrderAck {
+            order_id: tx.ord_id,
+            client_order_id: tx.cl_ord_id,
+        })
+    }
+
+    async fn cancel_order(&self, symbol: &str, client_order_id: &str) -> trading::Result<()> {
+        let tx = self.cancel(OrderCancellation {
+            symbol: symbol.to_string(),
+            order_id: None,
+            orig_client_order_id: Some(client_order_id.to_string()),
+        });
+        OrderAck::from_okex(accepted(vec![tx])).map(|_| ())
+    }
+
+    async fn cancel_all(&self, _symbol: &str) -> trading::Result<()> {
+        Err(not_simulated("cancel all"))
+    }
+
+    async fn amend_order(
+        &self,
+        _symbol: &str,
+        _client_order_id: &str,
+        _new_qty: Option<f64>,
+        _new_price: Option<f64>,
+    ) -> trading::Result<OrderAck> {
+        Err(not_simulated("amend"))
+    }
+
+    async fn close_position(&self, symbol: &str) -> trading::Result<()> {
+        let ts = self.clock.now();
+        let ord_id = self.next_ord_id();
+        let closed = self
+            .exchange
+            .lock()
+            .unwrap()
+            .close_position(ts, symbol, &ord_id);
+
+        let tx = self.record(
+            SentOrder {
+                ts,
+                action: OrderAction::ClosePosition,
+                inst_id: symbol.to_string(),
+                ord_id,
+                cl_ord_id: None,
+                side: None,
+                qty: None,
+                price: None,
+            },
+            if closed { 0 } else { POSITION_NOT_EXIST },
+        );
+        OrderAck::from_okex(accepted(vec![tx])).map(|_| ())
+    }
+
+    async fn positions(&self, symbol: &str) -> trading::Result<Vec<Position>> {
+        let (qty, entry_price) = self.exchange.lock().unwrap().position(symbol);
+        Ok(vec![Position {
+            symbol: symbol.to_string(),
+            qty,
+            entry_price,
+            mark_price: 0.,
+            unrealized_pnl: 0.,
+        }])
+    }
+
+    async fn balances(&self) -> trading::Result<Vec<Balance>> {
+        Err(not_simulated("balances"))
+    }
+
+    async fn open_orders(&self, _symbol: &str) -> trading::Result<Vec<OpenOrder>> {
+        Err(not_simulated("open orders"))
+    }
+}
+
+#[async_trait]
+impl OkexAccount for SimulatedAccount {
+    async fn cancel_all_open_orders(
+        &self,
+        orders: Vec<OrderCancellation>,
+    ) -> Result<TransactionResponse> {
+        let data = orders.into_iter().map(|o| self.cancel(o)).collect();
+        Ok(accepted(data))
+    }
+
+    /// Algo orders are not simulated, venue side stops have nothing to trigger them here
+    async fn place_algo_order(
+        &self,
+        _order: AlgoOrderRequest,
+    ) -> Result<RestResponse<AlgoTransaction>> {
+        Err(Error::Msg("algo orders are not simulated".to_string()))
+    }
+
+    async fn cancel_algo_orders(
+        &self,
+        _orders: Vec<AlgoCancellation>,
+    ) -> Result<RestResponse<AlgoTransaction>> {
+        Err(Error::Msg("algo orders are not simulated".to_string()))
+    }
+
+    async fn cancel_advance_algo_orders(
+        &self,
+        _orders: Vec<AlgoCancellation>,
+    ) -> Result<RestResponse<AlgoTransaction>> {
+        Err(Error::Msg("algo orders are not simulated".to_string()))
+    }
+}
@@ -0,0 +1,342 @@
+use anyhow::{bail, Result};
+use exrs::okex_v5::errors::Error;
+use exrs::okex_v5::market::Market;
+use exrs::okex_v5::rest_model::Candle;
+use log::{info, warn};
+use std::fs::{self, OpenOptions};
+use std::io::{Read, Write};
+use std::path::{Path, PathBuf};
+use std::time::Duration;
+
+/// history-candles is limited to 20 requests per 2 seconds
+const REQUEST_INTERVAL: Duration = Duration::from_millis(110);
+const RATE_LIMITED_BACKOFF: Duration = Duration::from_secs(2);
+const MAX_RETRIES: u32 = 5;
+const PAGE_LIMIT: u64 = 100;
+
+#[derive(Debug, Copy, Clone, PartialEq)]
+pub enum CandleFormat {
+    Csv,
+    JsonLines,
+}
+
+impl CandleFormat {
+    pub fn from_name(name: &str) -> Result<Self> {
+        match name {
+            "csv" => Ok(CandleFormat::Csv),
+            "jsonl" | "json" => Ok(CandleFormat::JsonLines),
+            _ => bail!("unknown candle format {}", name),
+        }
+    }
+
+    fn extension(&self) -> &'static str {
+        match self {
+            CandleFormat::Csv => "csv",
+            CandleFormat::JsonLines => "jsonl",
+        }
+    }
+}
+
+/// Duration of an OKX bar in ms, months are taken as 31 days which only widens the request window.
+pub fn bar_millis(bar: &str) -> Result<u64> {
+    let bar = bar.trim_end_matches("utc");
+    let (n, unit) = bar.split_at(bar.len().saturating_sub(1));
+    let n: u64 = match n.parse() {
+        Ok(n) => n,
+        Err(_) => bail!("invalid bar {}", bar),
+    };
+    let unit = match unit {
+        "s" => 1_000,
+        "m" => 60_000,
+        "H" => 3_600_000,
+        "D" => 86_400_000,
+        "W" => 7 * 86_400_000,
+        "M" => 31 * 86_400_000,
+        _ => bail!("invalid bar {}", bar),
+    };
+    Ok(n * unit)
+}
+
+/// Downloads confirmed candles of one instrument and bar into a single file, appending to what
+/// is already there so an interrupted download picks up after the last stored candle.
+pub struct CandleDownloader {
+    market: Market,
+    output_dir: PathBuf,
+    format: CandleFormat,
+}
+
+impl CandleDownloader {
+    pub fn new(
+        market: Market,
+        output_dir: impl Into<PathBuf>,
+        format: CandleFormat,
+    ) -> Result<Self> {
+        let output_dir = output_dir.into();
+        fs::create_dir_all(&output_dir)?;
+        Ok(CandleDownloader {
+            market,
+            output_dir,
+            format,
+        })
+    }
+
+    pub fn path(&self, inst_id: &str, bar: &str) -> PathBuf {
+        self.output_dir
+            .join(format!("{}_{}.{}", inst_id, bar, self.format.extension()))
+    }
+
+    /// Fetches candles opening in `[start, end)`, returns the number of candles written.
+    pub async fn download(&self, inst_id: &str, bar: &str, start: u64, end: u64) -> Result<usize> {
+        let bar_ms = bar_millis(bar)?;
+        let path = self.path(inst_id, bar);
+
+        let last_ts = last_stored_ts(&path, self.format)?;
+        let mut cursor = match last_ts {
+            Some(ts) if ts + 1 > start => {
+                info!("{} {} resumes after {}", inst_id, bar, ts);
+                ts + 1
+            }
+            _ => start,
+        };
+        let mut last_ts = last_ts.unwrap_or(0);
+        let mut written = 0;
+
+        while cursor < end {
+            let window_end = end.min(cursor + bar_ms * PAGE_LIMIT);
+            // `after` returns candles older than it and `before` newer than it, both exclusive
+            let page = self
+                .fetch_page(inst_id, bar, cursor as i64 - 1, window_end as i64)
+                .await?;
+
+            let (candles, forming) = confirmed_candles(page, cursor, window_end, last_ts);
+            if let Some(candle) = candles.last() {
+                last_ts = candle.ts;
+            }
+            written += self.append(&path, &candles)?;
+
+            if forming {
+                break;
+            }
+            cursor = window_end;
+            actix_rt::time::sleep(REQUEST_INTERVAL).await;
+        }
+
+        info!(
+            "{} {} downloaded {} candles into {:?}",
+            inst_id, bar, written, path
+        );
+        Ok(written)
+    }
+
+    async fn fetch_page(
+        &self,
+        inst_id: &str,
+        bar: &str,
+        before: i64,
+        after: i64,
+    ) -> Result<Vec<Candle>> {
+        let mut retries = 0;
+        loop {
+            let result = self
+                .market
+                .get_history_candles(
+                    inst_id.to_string(),
+                    Some(bar.to_string()),
+                    Some(after),
+                    Some(before),
+                    Some(PAGE_LIMIT as u16),
+                )
+                .await;
+
+            match result {
+                Ok(response) if response.code == 0 => return Ok(response.data),
+                Ok(response) => bail!(
+                    "get_history_candles failed: {} {}",
+                    response.code,
+                    response.msg
+                ),
+                Err(Error::TooManyRequests) if retries < MAX_RETRIES => {
+                    retries += 1;
+                    warn!(
+                        "rate limited on {} {}, backing off {}...",
+                        inst_id, bar, retries
+                    );
+                    actix_rt::time::sleep(RATE_LIMITED_BACKOFF).await;
+                }
+                Err(e) if retries < MAX_RETRIES => {
+                    retries += 1;
+                    warn!("get_history_candles Error: {}, retry {}...", e, retries);
+                    actix_rt::time::sleep(RATE_LIMITED_BACKOFF * retries).await;
+                }
+                Err(e) => return Err(e.into()),
+            }
+        }
+    }
+
+    fn append(&self, path: &Path, candles: &[Candle]) -> Result<usize> {
+        if candles.is_empty() {
+            return Ok(0);
+        }
+        let is_new = fs::metadata(path).map(|m| m.len() == 0).unwrap_or(true);
+        let file = OpenOptions::new().create(true).append(true).open(path)?;
+
+        match self.format {
+            CandleFormat::Csv => {
+                let mut wrt = csv::WriterBuilder::new()
+                    .has_headers(is_new)
+                    .from_writer(file);
+                for candle in candles {
+                    wrt.serialize(candle)?;
+                }
+                wrt.flush()?;
+            }
+            CandleFormat::JsonLines => {
+                let mut file = file;
+                for candle in candles {
+                    let line = serde_json::to_string(candle)?;
+                    file.write_all(line.as_bytes())?;
+                    file.write_all(b"\n")?;
+                }
+                file.flush()?;
+            }
+        }
+        Ok(candles.len())
+    }
+}
+
+/// Candles of a page opening in `[cursor, window_end)` and after `last_ts`, in ascending order
+/// and cut before the one still forming. The flag tells whether a forming candle was cut.
+fn confirmed_candles(
+    page: Vec<Candle>,
+    cursor: u64,
+    window_end: u64,
+    last_ts: u64,
+) -> (Vec<Candle>, bool) {
+    let mut candles: Vec<Candle> = page
+        .into_iter()
+        .filter(|c| c.ts >= cursor && c.ts < window_end && c.ts > last_ts)
+        .collect();
+    candles.sort_by_key(|c| c.ts);
+    candles.dedup_by_key(|c| c.ts);
+
+    // the candle still forming is the newest one, nothing after it can be confirmed yet
+    let forming = candles.iter().position(|c| c.confirm == 0);
+    if let Some(idx) = forming {
+        candles.truncate(idx);
+    }
+    (candles, forming.is_some())
+}
+
+fn parse_candle(line: &str, format: CandleFormat) -> Option<Candle> {
+    match format {
+        CandleFormat::Csv => csv::ReaderBuilder::new()
+            .has_headers(false)
+            .from_reader(line.as_bytes())
+            .deserialize()
+            .next()?
+            .ok(),
+        CandleFormat::JsonLines => serde_json::from_str(line).ok(),
+    }
+}
+
+/// Opening time of the newest candle already in `path`, candles are stored in ascending order.
+/// What follows the last complete candle, a line cut by an interrupted write, is truncated so
+/// the download appends right after it.
+fn last_stored_ts(path: &Path, format: CandleFormat) -> Result<Option<u64>> {
+    let mut file = match OpenOptions::new().read(true).write(true).open(path) {
+        Ok(file) => file,
+        Err(e) if e.kind() == std::io::ErrorKind::NotFound => return Ok(None),
+        Err(e) => return Err(e.into()),
+    };
+    let mut content = String::new();
+    file.read_to_string(&mut content)?;
+
+    let mut last = None;
+    let mut valid_len = 0;
+    let mut offset = 0;
+    for (idx, line) in content.split_inclusive('\n').enumerate() {
+        offset += line.len();
+        if !line.ends_with('\n') {
+            break;
+        }
+        match parse_candle(line.trim_end(), format) {
+            Some(candle) => {
+                last = Some(candle.ts);
+                valid_len = offset;
+            }
+            // the csv header
+            None if idx == 0 && format == CandleFormat::Csv => valid_len = offset,
+            None => {}
+        }
+    }
+
+    if valid_len < content.len() {
+        warn!(
+            "{:?} has {} bytes after the last complete candle, truncating",
+            path,
+            content.len() - valid_len
+        );
+        file.set_len(valid_len as u64)?;
+    }
+    Ok(last)
+}
+
+#[cfg(test)]
+mod test {
+    use super::*;
+
+    fn candle(ts: u64, confirm: u16) -> Candle {
+        serde_json::from_value(serde_json::json!({
+            "ts": ts.to_string(), "o": "1", "h": "2", "l": "0.5", "c": "1.5", "vol": "10",
+            "volCcy": "10", "volCcyQuote": "15", "confirm": confirm.to_string()
+        }))
+        .unwrap()
+    }
+
+    #[test]
+    fn test_confirmed_candles() {
+        let page = vec![
+            candle(400, 0),
+            candle(300, 1),
+            candle(100, 1),
+            candle(200, 1),
+            candle(200, 1),
+            candle(0, 1),
+        ];
+        let (candles, forming) = confirmed_candles(page, 100, 500, 100);
+        let ts: Vec<u64> = candles.iter().map(|c| c.ts).collect();
+        assert_eq!(ts, vec![200, 300]);
+        assert!(forming);
+
+        let (candles, forming) = confirmed_candles(vec![candle(500, 1)], 100, 500, 0);
+        assert!(candles.is_empty() && !forming);
+    }
+
+    #[test]
+    fn test_resume_after_partial_line() {
+        let dir = std::env::temp_dir().join(format!("candles_test_{}", std::process::id()));
+        fs::create_dir_all(&dir).unwrap();
+        let path = dir.join("BTC-USDT_1m.csv");
+        let complete = "ts,o,h,l,c,vol,volCcy,volCcyQuote,confirm\n\
+                        60000,1,2,0.5,1.5,10,10,15,1\n\
+                        120000,1,2,0.5,1.5,10,10,15,1\n";
+        fs::write(&path, format!("{}180000,1,2", complete)).unwrap();
+        assert_eq!(
+            last_stored_ts(&path, CandleFormat::Csv).unwrap(),
+            Some(120000)
+        );
+        assert_eq!(fs::read_to_string(&path).unwrap(), complete);
+
+        let path = dir.join("BTC-USDT_1m.jsonl");
+        let complete = format!("{}\n", serde_json::to_string(&candle(60000, 1)).unwrap());
+        fs::write(&path, format!("{}{{\"ts\":\"120", complete)).unwrap();
+        assert_eq!(
+            last_stored_ts(&path, CandleFormat::JsonLines).unwrap(),
+            Some(60000)
+        );
+        assert_eq!(fs::read_to_string(&path).unwrap(), complete);
+
+        fs::remove_dir_all(&dir).unwrap();
+        assert_eq!(last_stored_ts(&path, CandleFormat::Csv).unwrap(), None);
+    }
+}
@@ -0,0 +1,26 @@
+use std::sync::atomic::{AtomicU64, Ordering};
+use std::sync::Arc;
+
+/// Millisecond clock driven by the replayed events instead of the wall clock.
+/// Clones share the same time, so the engine and the simulated account always agree.
+#[derive(Debug, Clone, Default)]
+pub struct SimulatedClock {
+    now: Arc<AtomicU64>,
+}
+
+impl SimulatedClock {
+    pub fn new(start: u64) -> Self {
+        SimulatedClock {
+            now: Arc::new(AtomicU64::new(start)),
+        }
+    }
+
+    pub fn now(&self) -> u64 {
+        self.now.load(Ordering::Relaxed)
+    }
+
+    /// Moves the clock forward, never backwards, so out of order input can not rewind time.
+    pub fn advance_to(&self, ts: u64) {
+        self.now.fetch_max(ts, Ordering::Relaxed);
+    }
+}
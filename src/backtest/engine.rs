@@ -0,0 +1,280 @@
+use super::account::{SentOrder, SimulatedAccount};
+use super::clock::SimulatedClock;
+use super::exchange::SimulatedExchange;
+use super::replay::RecordedEvent;
+use crate::config::BacktestConfig;
+use crate::portfolio::Holding;
+use crate::strategies::avellaneda_stoikov_okex::AvellanedaStoikov;
+
+use exrs::okex_v5::orderbook::OrderBooks;
+use exrs::okex_v5::ws_model::WebsocketEvent;
+use log::{info, warn};
+use std::collections::HashMap;
+use std::sync::{Arc, Mutex};
+
+#[derive(Debug, Clone)]
+pub struct BacktestReport {
+    pub events: usize,
+    pub start_ts: u64,
+    pub end_ts: u64,
+    pub orders: Vec<SentOrder>,
+    /// Net position and average entry price per instrument at the end of the replay
+    pub positions: Vec<(String, f64, f64)>,
+    /// Positions and PnL the strategy built from its fills
+    pub holdings: Vec<Holding>,
+}
+
+/// Drives an `AvellanedaStoikov` with recorded events on a simulated clock.
+///
+/// Market data goes through the simulated exchange before the strategy sees it, the order and
+/// position updates of the strategy's own orders come from the exchange, recorded private
+/// events are dropped. Book pushes are applied to local order books per channel first, so the
+/// incremental channels reach the exchange as full books.
+pub struct BacktestEngine {
+    clock: SimulatedClock,
+    exchange: Arc<Mutex<SimulatedExchange>>,
+    account: SimulatedAccount,
+}
+
+impl BacktestEngine {
+    pub fn new(start_ts: u64, config: &BacktestConfig) -> Self {
+        let clock = SimulatedClock::new(start_ts);
+        let exchange = Arc::new(Mutex::new(SimulatedExchange::new(config)));
+        let account = SimulatedAccount::new(clock.clone(), exchange.clone());
+        BacktestEngine {
+            clock,
+            exchange,
+            account,
+        }
+    }
+
+    pub fn clock(&self) -> SimulatedClock {
+        self.clock.clone()
+    }
+
+    /// Account to hand to `AvellanedaStoikov::with_account`, it shares the engine's clock.
+    pub fn account(&self) -> SimulatedAccount {
+        self.account.clone()
+    }
+
+    pub async fn run<I>(
+        &self,
+        strategy: &mut AvellanedaStoikov<SimulatedAccount>,
+        events: I,
+    ) -> BacktestReport
+    where
+        I: IntoIterator<Item = RecordedEvent>,
+    {
+        let start_ts = self.clock.now();
+        let mut count = 0;
+        let mut inst_ids = Vec::new();
+        let mut books: HashMap<String, OrderBooks> = HashMap::new();
+
+        for recorded in events {
+            self.clock.advance_to(recorded.local_ts);
+            let now = self.clock.now();
+
+            let forward = {
+                let mut exchange = self.exchange.lock().unwrap();
+                exchange.advance(now);
+                match &recorded.event {
+                    WebsocketEvent::OrderBook(book_event) => {
+                        if let Some(inst_id) = &book_event.arg.inst_id {
+                            if !inst_ids.contains(inst_id) {
+                                inst_ids.push(inst_id.clone());
+                            }
+                        }
+                        let channel = &book_event.arg.channel;
+                        let books = books
+                            .entry(channel.clone())
+                            .or_insert_with(|| OrderBooks::new(channel.clone()));
+                        match books.on_event(book_event) {
+                            Ok(touched) => {
+                                for inst_id in touched {
+                                    if let Some(book) = books.book(&inst_id) {
+                                        exchange.on_book(book, now);
+                                    }
+                                }
+                            }
+                            // no resubscription in a replay, the book waits for the next snapshot
+                            Err(e) => warn!("backtest book dropped, {}", e),
+                        }
+                        true
+                    }
+                    WebsocketEvent::Trades(trades_event) => {
+                        for trade in trades_event.data.iter() {
+                            exchange.on_trade(trade, now);
+                        }
+                        true
+                    }
+                    WebsocketEvent::Order(_)
+                    | WebsocketEvent::Position(_)
+                    | WebsocketEvent::BalancePosition(_)
+                    | WebsocketEvent::Account(_) => false,
+                    _ => true,
+                }
+            };
+
+            self.dispatch_exchange_events(strategy).await;
+            if forward {
+                strategy.on_event(recorded.event).await;
+            }
+            // the orders the strategy sent on this event reach the exchange before the next one
+            strategy.settle().await;
+            count += 1;
+
+            if count % 100_000 == 0 {
+                info!(
+                    "backtest replayed {} events, clock {}",
+                    count,
+                    self.clock.now()
+                );
+            }
+        }
+        strategy.settle().await;
+        self.dispatch_exchange_events(strategy).await;
+
+        let exchange = self.exchange.lock().unwrap();
+        let positions = inst_ids
+            .into_iter()
+            .map(|inst_id| {
+                let (pos, avg_px) = exchange.position(&inst_id);
+                (inst_id, pos, avg_px)
+            })
+            .collect();
+
+        BacktestReport {
+            events: count,
+            start_ts,
+            end_ts: self.clock.now(),
+            orders: self.account.sent_orders(),
+            positions,
+            holdings: strategy.portfolio().holdings().cloned().collect(),
+        }
+    }
+
+    async fn dispatch_exchange_events(&self, strategy: &mut AvellanedaStoikov<SimulatedAccount>) {
+        let events = self.exchange.lock().unwrap().drain_events();
+        for event in events {
+            strategy.on_event(event).await;
+        }
+    }
+}
+
+#[cfg(test)]
+mod test {
+    use super::*;
+    use crate::backtest::account::OrderAction;
+    use crate::config::OkexConfig;
+    use crate::instruments::Instrument;
+    use crate::oms::OrderState;
+
+    /// books5 push of a one level book, with the receive time as exchange time
+    fn book(ts: u64, bid: f64, ask: f64) -> RecordedEvent {
+        let event = serde_json::json!({
+            "arg": {"channel": "books5", "instId": "BTC-USDT"},
+            "data": [{
+                "asks": [[ask.to_string(), "5", "0", "1"]],
+                "bids": [[bid.to_string(), "5", "0", "1"]],
+                "instId": "BTC-USDT",
+                "ts": ts.to_string()
+            }]
+        });
+        RecordedEvent {
+            local_ts: ts,
+            event: serde_json::from_value(event).unwrap(),
+        }
+    }
+
+    #[actix_rt::test]
+    async fn test_run() {
+        let config: OkexConfig = serde_json::from_value(serde_json::json!({
+            "base_asset": "BTC",
+            "quote_asset": "USDT",
+            "order_qty": 1,
+            "n_spreads": 10,
+            "estimate_window": 2000,
+            "period": 1000,
+            "sigma_tick_period": 50,
+            "gamma": 0.2,
+            "sigma_multiplier": 1,
+            "stoploss": 0.5,
+            "stoploss_sleep": 300000,
+            "stopprofit": 0.5,
+            "trailing_stop": 0.5,
+            "q_max": 10,
+            "is_testnet": true
+        }))
+        .unwrap();
+        let backtest = BacktestConfig {
+            data_files: vec![],
+            orders_output: String::new(),
+            order_entry_latency: 10,
+            cancel_latency: 10,
+            maker_fee: -0.0001,
+            taker_fee: 0.0005,
+        };
+        let start_ts = 1_000_000;
+        let engine = BacktestEngine::new(start_ts, &backtest);
+        let mut strategy = AvellanedaStoikov::with_account(
+            config,
+            engine.account(),
+            Instrument::with_tick_size("BTC-USDT", 0.1),
+            start_ts,
+        );
+
+        // a book every 100 ms for 6 s, the mid swings by a few ticks
+        let events: Vec<RecordedEvent> = (0..60)
+            .map(|i| {
+                let mid = 100. + [0., 0.3, 0.6, 0.3, 0., -0.3][i % 6];
+                book(start_ts + 100 * i as u64, mid - 0.05, mid + 0.05)
+            })
+            .collect();
+        let report = engine.run(&mut strategy, events).await;
+
+        assert_eq!(report.events, 60);
+        assert_eq!(report.start_ts, start_ts);
+        assert_eq!(report.end_ts, start_ts + 5_900);
+        assert_eq!(engine.clock().now(), start_ts + 5_900);
+
+        // nothing is sent before the estimation window is full, every request carries the
+        // replayed time of the event it was sent on
+        assert!(report.orders.iter().all(|o| o.ts > start_ts + 2_000));
+        assert!(report.orders.windows(2).all(|w| w[0].ts <= w[1].ts));
+        assert!(report.orders.iter().all(|o| o.ts % 100 == 0));
+
+        // one quote per side every period, the previous ones are canceled first
+        let mut quote_times: Vec<u64> = report
+            .orders
+            .iter()
+            .filter(|o| matches!(o.action, OrderAction::Place))
+            .map(|o| o.ts)
+            .collect();
+        assert_eq!(quote_times.len() % 2, 0);
+        quote_times.dedup();
+        assert!(quote_times.len() >= 3);
+        // the quote timer counts whole seconds
+        assert!(quote_times.windows(2).all(|w| w[1] / 1_000 > w[0] / 1_000));
+        let orders = strategy.orders();
+        let orders = orders.lock().await;
+        for ts in quote_times.iter().skip(1) {
+            let canceled: Vec<&SentOrder> = report
+                .orders
+                .iter()
+                .filter(|o| o.ts == *ts && matches!(o.action, OrderAction::Cancel))
+                .collect();
+            assert_eq!(canceled.len(), 2);
+            for cancel in canceled {
+                let order = orders.order(cancel.cl_ord_id.as_ref().unwrap()).unwrap();
+                assert_eq!(order.state, OrderState::Canceled);
+                // the cancel latency is over by the next book 100 ms later
+                assert_eq!(order.update_time, ts + 100);
+            }
+        }
+        let last = report.orders.iter().rev().take(2);
+        for sent in last {
+            let order = orders.order(sent.cl_ord_id.as_ref().unwrap()).unwrap();
+            assert_eq!(order.state, OrderState::Live);
+        }
+    }
+}
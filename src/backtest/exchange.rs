@@ -0,0 +1,646 @@
+use crate::config::BacktestConfig;
+
+use exrs::okex_v5::orderbook::{BookSide, OrderBook};
+use exrs::okex_v5::ws_model::{
+    Arg, Order, OrderEvent, Positions, PositionsEvent, Trade, WebsocketEvent,
+};
+use exrs::trading::Side;
+use log::debug;
+use rust_decimal::prelude::ToPrimitive;
+use std::collections::HashMap;
+
+/// Levels per side of the local book the matching looks at
+const BOOK_DEPTH: usize = 25;
+
+fn side_str(side: Side) -> &'static str {
+    match side {
+        Side::Buy => "buy",
+        Side::Sell => "sell",
+    }
+}
+
+#[derive(Debug, Clone)]
+struct RestingOrder {
+    ord_id: String,
+    cl_ord_id: String,
+    inst_id: String,
+    side: Side,
+    px: f64,
+    sz: f64,
+    acc_fill_sz: f64,
+    /// Sum of price times quantity over the fills so far
+    fill_value: f64,
+    c_time: u64,
+    active_at: u64,
+    is_active: bool,
+    cancel_at: Option<u64>,
+    /// Estimated quantity queued in front of this order at its price level
+    queue_ahead: f64,
+    /// Visible quantity at the order's price level at the last book update
+    level_qty: f64,
+}
+
+impl RestingOrder {
+    fn leaves(&self) -> f64 {
+        self.sz - self.acc_fill_sz
+    }
+
+    /// Volume weighted price of the fills so far
+    fn avg_px(&self) -> f64 {
+        if self.acc_fill_sz > 0. {
+            self.fill_value / self.acc_fill_sz
+        } else {
+            0.
+        }
+    }
+}
+
+/// One fill of an order as reported in the order update
+struct Execution {
+    sz: f64,
+    px: f64,
+    fee: f64,
+    is_maker: bool,
+}
+
+/// Simulated OKX matching for the strategy's own orders.
+///
+/// Limit orders rest at their price behind the quantity visible at that level when they go live,
+/// the queue in front only shrinks through trade prints at the level or through proportional
+/// depletion of the level in later book snapshots. Orders fill completely once the market trades
+/// or quotes through their price. Order entry and cancels only take effect after the configured
+/// latency, so a quote can still be filled while its cancel is in flight.
+///
+/// Books are kept per instrument from the local `OrderBook` the engine maintains, an update
+/// only matches the orders of its own instrument.
+pub struct SimulatedExchange {
+    entry_latency: u64,
+    cancel_latency: u64,
+    maker_fee: f64,
+    taker_fee: f64,
+    orders: Vec<RestingOrder>,
+    books: HashMap<String, Levels>,
+    positions: Vec<(String, f64, f64)>,
+    next_trade_id: u64,
+    outbox: Vec<WebsocketEvent>,
+}
+
+/// (price, size) of the levels of one side
+type SideLevels = [(f64, f64)];
+
+/// Top of the book of one instrument as (price, size), best first
+#[derive(Debug, Clone, Default)]
+struct Levels {
+    bids: Vec<(f64, f64)>,
+    asks: Vec<(f64, f64)>,
+}
+
+impl Levels {
+    fn from_book(book: &OrderBook) -> Self {
+        let levels = |side| {
+            book.levels(side, BOOK_DEPTH)
+                .into_iter()
+                .filter_map(|(px, sz)| Some((px.to_f64()?, sz.to_f64()?)))
+                .collect()
+        };
+        Levels {
+            bids: levels(BookSide::Bid),
+            asks: levels(BookSide::Ask),
+        }
+    }
+
+    /// Levels on the side of an order and on the opposite one
+    fn sides(&self, side: Side) -> (&SideLevels, &SideLevels) {
+        match side {
+            Side::Buy => (&self.bids, &self.asks),
+            Side::Sell => (&self.asks, &self.bids),
+        }
+    }
+}
+
+fn same_price(a: f64, b: f64) -> bool {
+    (a - b).abs() <= 1e-9 * a.abs().max(b.abs()).max(1.)
+}
+
+impl SimulatedExchange {
+    pub fn new(config: &BacktestConfig) -> Self {
+        SimulatedExchange {
+            entry_latency: config.order_entry_latency,
+            cancel_latency: config.cancel_latency,
+            maker_fee: config.maker_fee,
+            taker_fee: config.taker_fee,
+            orders: Vec::new(),
+            books: HashMap::new(),
+            positions: Vec::new(),
+            next_trade_id: 1,
+            outbox: Vec::new(),
+        }
+    }
+
+    /// Accepts a new limit order, it reaches the book after the order entry latency.
+    #[allow(clippy::too_many_arguments)]
+    pub fn submit(
+        &mut self,
+        now: u64,
+        inst_id: &str,
+        ord_id: &str,
+        cl_ord_id: &str,
+        side: Side,
+        px: f64,
+        sz: f64,
+    ) {
+        self.orders.push(RestingOrder {
+            ord_id: ord_id.to_string(),
+            cl_ord_id: cl_ord_id.to_string(),
+            inst_id: inst_id.to_string(),
+            side,
+            px,
+            sz,
+            acc_fill_sz: 0.,
+            fill_value: 0.,
+            c_time: now,
+            active_at: now + self.entry_latency,
+            is_active: false,
+            cancel_at: None,
+            queue_ahead: 0.,
+            level_qty: 0.,
+        });
+    }
+
+    /// Requests a cancel by client order id, it takes effect after the cancel latency.
+    pub fn cancel(&mut self, now: u64, cl_ord_id: &str) -> bool {
+        let cancel_at = now + self.cancel_latency;
+        match self.orders.iter_mut().find(|o| o.cl_ord_id == cl_ord_id) {
+            Some(order) => {
+                order.cancel_at = Some(order.cancel_at.map_or(cancel_at, |t| t.min(cancel_at)));
+                true
+            }
+            None => false,
+        }
+    }
+
+    /// Flattens the position with a market order against the last seen top of book of the
+    /// instrument.
+    pub fn close_position(&mut self, now: u64, inst_id: &str, ord_id: &str) -> bool {
+        let pos = self.position(inst_id).0;
+        let side = if pos > 0. {
+            Side::Sell
+        } else if pos < 0. {
+            Side::Buy
+        } else {
+            return false;
+        };
+        let touch = self
+            .books
+            .get(inst_id)
+            .and_then(|levels| levels.sides(side).1.first().copied());
+
+        match touch {
+            Some((px, _)) => {
+                let mut order = RestingOrder {
+                    ord_id: ord_id.to_string(),
+                    cl_ord_id: String::new(),
+                    inst_id: inst_id.to_string(),
+                    side,
+                    px,
+                    sz: pos.abs(),
+                    acc_fill_sz: 0.,
+                    fill_value: 0.,
+                    c_time: now,
+                    active_at: now,
+                    is_active: true,
+                    cancel_at: None,
+                    queue_ahead: 0.,
+                    level_qty: 0.,
+                };
+                self.fill(&mut order, pos.abs(), px, now, "market", false);
+                true
+            }
+            None => false,
+        }
+    }
+
+    /// Net position and average entry price of an instrument.
+    pub fn position(&self, inst_id: &str) -> (f64, f64) {
+        self.positions
+            .iter()
+            .find(|p| p.0 == inst_id)
+            .map_or((0., 0.), |p| (p.1, p.2))
+    }
+
+    /// Applies pending order entries and cancels that are due at `now`.
+    pub fn advance(&mut self, now: u64) {
+        let mut orders = std::mem::take(&mut self.orders);
+
+        for order in orders.iter_mut() {
+            if !order.is_active && order.active_at <= now {
+                order.is_active = true;
+                self.on_order_live(order, now);
+            }
+        }
+
+        orders.retain(|order| match order.cancel_at {
+            Some(t) if t <= now && order.leaves() > 0. => {
+                self.push_order(order, now, "canceled", "limit", None);
+                false
+            }
+            _ => order.leaves() > 0.,
+        });
+
+        self.orders = orders;
+    }
+
+    /// Matches the orders of the book's instrument against its new top levels.
+    pub fn on_book(&mut self, book: &OrderBook, now: u64) {
+        let levels = Levels::from_book(book);
+        self.books
+            .insert(book.inst_id().to_string(), levels.clone());
+        self.advance(now);
+
+        let mut orders = std::mem::take(&mut self.orders);
+        for order in orders
+            .iter_mut()
+            .filter(|o| o.is_active && o.inst_id == book.inst_id())
+        {
+            let (same, opposite) = levels.sides(order.side);
+            let sign = order.side.sign();
+
+            // the opposite side is quoted at or through our price, the order has been taken
+            if let Some(&(px, _)) = opposite.first() {
+                if sign * (order.px - px) >= 0. {
+                    let qty = order.leaves();
+                    self.fill(order, qty, order.px, now, "limit", true);
+                    continue;
+                }
+            }
+
+            let level = same.iter().find(|l| same_price(l.0, order.px));
+            let in_range = match (same.first(), same.last()) {
+                (Some(best), Some(worst)) => {
+                    sign * (best.0 - order.px) >= 0. && sign * (order.px - worst.0) >= 0.
+                }
+                _ => false,
+            };
+            match level {
+                Some(&(_, qty)) => {
+                    if qty < order.level_qty && order.level_qty > 0. {
+                        order.queue_ahead *= qty / order.level_qty;
+                    }
+                    order.queue_ahead = order.queue_ahead.min(qty);
+                    order.level_qty = qty;
+                }
+                // level disappeared while still inside the visible depth, everyone ahead left
+                None if in_range => {
+                    order.queue_ahead = 0.;
+                    order.level_qty = 0.;
+                }
+                None => {}
+            }
+        }
+        orders.retain(|o| o.leaves() > 0.);
+        self.orders.append(&mut orders);
+    }
+
+    pub fn on_trade(&mut self, trade: &Trade, now: u64) {
+        self.advance(now);
+
+        // the taker side of the print consumes resting orders on the other side
+        let maker_side = match trade.side.as_str() {
+            "buy" => Side::Sell,
+            _ => Side::Buy,
+        };
+        let mut remaining = trade.sz;
+
+        let mut orders = std::mem::take(&mut self.orders);
+        orders.sort_by_key(|o| o.active_at);
+        for order in orders
+            .iter_mut()
+            .filter(|o| o.is_active && o.side == maker_side && o.inst_id == trade.inst_id)
+        {
+            let sign = order.side.sign();
+            if sign * (trade.px - order.px) < 0. && !same_price(trade.px, order.px) {
+                // printed through our price
+                let qty = order.leaves();
+                self.fill(order, qty, order.px, now, "limit", true);
+            } else if same_price(trade.px, order.px) && remaining > 0. {
+                let consumed = remaining.min(order.queue_ahead);
+                order.queue_ahead -= consumed;
+                remaining -= consumed;
+
+                let qty = remaining.min(order.leaves());
+                if qty > 0. {
+                    remaining -= qty;
+                    self.fill(order, qty, order.px, now, "limit", true);
+                }
+            }
+        }
+        orders.retain(|o| o.leaves() > 0.);
+        self.orders.append(&mut orders);
+    }
+
+    /// Order and position updates produced since the last call.
+    pub fn drain_events(&mut self) -> Vec<WebsocketEvent> {
+        std::mem::take(&mut self.outbox)
+    }
+
+    fn on_order_live(&mut self, order: &mut RestingOrder, now: u64) {
+        let levels = self.books.get(&order.inst_id).cloned().unwrap_or_default();
+        let (same, opposite) = levels.sides(order.side);
+        let sign = order.side.sign();
+
+        // marketable on arrival, take liquidity at the opposite touch
+        if let Some(&(px, _)) = opposite.first() {
+            if sign * (order.px - px) >= 0. {
+                let qty = order.leaves();
+                self.fill(order, qty, px, now, "limit", false);
+                return;
+            }
+        }
+
+        let qty = same
+            .iter()
+            .find(|l| same_price(l.0, order.px))
+            .map_or(0., |l| l.1);
+        order.queue_ahead = qty;
+        order.level_qty = qty;
+        debug!(
+            "simulated order {} live at {}, queue ahead {}",
+            order.cl_ord_id, order.px, order.queue_ahead
+        );
+        self.push_order(order, now, "live", "limit", None);
+    }
+
+    fn fill(
+        &mut self,
+        order: &mut RestingOrder,
+        qty: f64,
+        px: f64,
+        now: u64,
+        ord_type: &str,
+        is_maker: bool,
+    ) {
+        let qty = qty.min(order.leaves());
+        order.acc_fill_sz += qty;
+        order.fill_value += qty * px;
+
+        let fee_rate = if is_maker {
+            self.maker_fee
+        } else {
+            self.taker_fee
+        };
+        // OKX reports charged fees as negative numbers and rebates as positive ones
+        let fee = -(qty * px * fee_rate);
+
+        let state = if order.leaves() > 0. {
+            "partially_filled"
+        } else {
+            "filled"
+        };
+        let execution = Execution {
+            sz: qty,
+            px,
+            fee,
+            is_maker,
+        };
+        self.push_order(order, now, state, ord_type, Some(&execution));
+        self.update_position(&order.inst_id, order.side.sign() * qty, px, now);
+    }
+
+    fn update_position(&mut self, inst_id: &str, delta: f64, px: f64, now: u64) {
+        let idx = match self.positions.iter().position(|p| p.0 == inst_id) {
+            Some(idx) => idx,
+            None => {
+                self.positions.push((inst_id.to_string(), 0., 0.));
+                self.positions.len() - 1
+            }
+        };
+        let (_, pos, avg_px) = &mut self.positions[idx];
+
+        let new_pos = *pos + delta;
+        if new_pos == 0. {
+            *avg_px = 0.;
+        } else if *pos == 0. || (*pos > 0.) != (new_pos > 0.) {
+            *avg_px = px;
+        } else if pos.abs() < new_pos.abs() {
+            *avg_px = (*avg_px * pos.abs() + px * delta.abs()) / new_pos.abs();
+        }
+        *pos = new_pos;
+
+        let positions = Positions {
+            inst_id: inst_id.to_string(),
+            inst_type: "SWAP".to_string(),
+            mgn_mode: "cross".to_string(),
+            pos_side: "net".to_string(),
+            pos: new_pos.to_string(),
+            avg_px: avg_px.to_string(),
+            u_time: now.to_string(),
+            ..Default::default()
+        };
+        self.outbox
+            .push(WebsocketEvent::Position(Box::new(PositionsEvent {
+                arg: Arg {
+                    channel: "positions".to_string(),
+                    inst_type: Some("SWAP".to_string()),
+                    ..Default::default()
+                },
+                data: vec![positions],
+            })));
+    }
+
+    fn push_order(
+        &mut self,
+        order: &RestingOrder,
+        now: u64,
+        state: &str,
+        ord_type: &str,
+        execution: Option<&Execution>,
+    ) {
+        let trade_id = if execution.is_some() {
+            self.next_trade_id += 1;
+            (self.next_trade_id - 1).to_string()
+        } else {
+            String::new()
+        };
+        let (fill_sz, fill_px, fill_fee) = execution.map_or((0., 0., 0.), |e| (e.sz, e.px, e.fee));
+
+        let data = Order {
+            inst_type: "SWAP".to_string(),
+            inst_id: order.inst_id.clone(),
+            ord_id: order.ord_id.clone(),
+            cl_ord_id: order.cl_ord_id.clone(),
+            px: order.px.to_string(),
+            sz: order.sz.to_string(),
+            ord_type: ord_type.to_string(),
+            side: side_str(order.side).to_string(),
+            pos_side: "net".to_string(),
+            td_mode: "cross".to_string(),
+            fill_sz: fill_sz.to_string(),
+            fill_px: fill_px.to_string(),
+            trade_id,
+            acc_fill_sz: order.acc_fill_sz.to_string(),
+            fill_time: now.to_string(),
+            fill_fee: fill_fee.to_string(),
+            exec_type: match execution {
+                Some(e) if e.is_maker => "M".to_string(),
+                Some(_) => "T".to_string(),
+                None => String::new(),
+            },
+            state: state.to_string(),
+            avg_px: if order.acc_fill_sz > 0. {
+                order.avg_px().to_string()
+            } else {
+                String::new()
+            },
+            u_time: now.to_string(),
+            c_time: order.c_time.to_string(),
+            code: "0".to_string(),
+            ..Default::default()
+        };
+        self.outbox.push(WebsocketEvent::Order(Box::new(OrderEvent {
+            arg: Arg {
+                channel: "orders".to_string(),
+                inst_type: Some("SWAP".to_string()),
+                ..Default::default()
+            },
+            data: vec![data],
+        })));
+    }
+}
+
+#[cfg(test)]
+mod test {
+    use super::*;
+
+    fn exchange() -> SimulatedExchange {
+        SimulatedExchange::new(&BacktestConfig {
+            data_files: vec![],
+            orders_output: String::new(),
+            order_entry_latency: 10,
+            cancel_latency: 10,
+            maker_fee: -0.0001,
+            taker_fee: 0.0005,
+        })
+    }
+
+    fn book(inst_id: &str, bid: (&str, &str), ask: (&str, &str)) -> OrderBook {
+        let mut book = OrderBook::new(inst_id);
+        book.apply(
+            None,
+            &exrs::okex_v5::ws_model::OrderBook {
+                asks: vec![vec![ask.0.into(), ask.1.into(), "0".into(), "1".into()]],
+                bids: vec![vec![bid.0.into(), bid.1.into(), "0".into(), "1".into()]],
+                inst_id: inst_id.into(),
+                timestamp: 0,
+                checksum: None,
+                prev_seq_id: None,
+                seq_id: None,
+            },
+        )
+        .unwrap();
+        book
+    }
+
+    fn trade(px: f64, sz: f64, side: &str) -> Trade {
+        Trade {
+            inst_id: "BTC-USDT-SWAP".into(),
+            trade_id: "1".into(),
+            px,
+            sz,
+            side: side.into(),
+            timestamp: 0,
+        }
+    }
+
+    #[test]
+    fn test_queue_position_fill() {
+        let mut ex = exchange();
+        ex.on_book(&book("BTC-USDT-SWAP", ("100", "5"), ("101", "5")), 0);
+        ex.submit(0, "BTC-USDT-SWAP", "1", "a", Side::Buy, 100., 2.);
+
+        // not live yet, the print does not reach the order
+        ex.on_trade(&trade(100., 10., "sell"), 5);
+        ex.on_book(&book("BTC-USDT-SWAP", ("100", "5"), ("101", "5")), 10);
+        assert_eq!(ex.position("BTC-USDT-SWAP").0, 0.);
+
+        // 5 ahead in the queue, the first print only consumes the queue
+        ex.on_trade(&trade(100., 4., "sell"), 20);
+        assert_eq!(ex.position("BTC-USDT-SWAP").0, 0.);
+        ex.on_trade(&trade(100., 2., "sell"), 30);
+        assert_eq!(ex.position("BTC-USDT-SWAP").0, 1.);
+        ex.on_trade(&trade(100., 3., "sell"), 40);
+        assert_eq!(ex.position("BTC-USDT-SWAP"), (2., 100.));
+    }
+
+    #[test]
+    fn test_cancel_latency() {
+        let mut ex = exchange();
+        ex.on_book(&book("BTC-USDT-SWAP", ("100", "0"), ("101", "5")), 0);
+        ex.submit(0, "BTC-USDT-SWAP", "1", "a", Side::Buy, 100., 1.);
+        ex.advance(10);
+        assert!(ex.cancel(10, "a"));
+
+        // filled while the cancel is in flight
+        ex.on_trade(&trade(99., 1., "sell"), 15);
+        ex.advance(20);
+        assert_eq!(ex.position("BTC-USDT-SWAP").0, 1.);
+        assert!(!ex.cancel(20, "a"));
+    }
+
+    #[test]
+    fn test_books_per_instrument() {
+        let mut ex = exchange();
+        ex.on_book(&book("BTC-USDT-SWAP", ("100", "5"), ("101", "5")), 0);
+        ex.submit(0, "BTC-USDT-SWAP", "1", "a", Side::Buy, 100., 1.);
+        ex.on_book(&book("BTC-USDT-SWAP", ("100", "5"), ("101", "5")), 10);
+
+        // another instrument quoting through the price does not touch the order
+        ex.on_book(&book("ETH-USDT-SWAP", ("99", "5"), ("99.5", "5")), 20);
+        assert_eq!(ex.position("BTC-USDT-SWAP").0, 0.);
+        ex.on_book(&book("BTC-USDT-SWAP", ("99", "5"), ("99.5", "5")), 30);
+        assert_eq!(ex.position("BTC-USDT-SWAP"), (1., 100.));
+
+        // closed against its own book, not the last one seen
+        ex.on_book(&book("ETH-USDT-SWAP", ("10", "5"), ("11", "5")), 40);
+        assert!(ex.close_position(40, "BTC-USDT-SWAP", "2"));
+        assert_eq!(ex.position("BTC-USDT-SWAP").0, 0.);
+        assert!(ex.drain_events().iter().any(|e| match e {
+            WebsocketEvent::Order(order) => order.data[0].fill_px == "99",
+            _ => false,
+        }));
+    }
+
+    #[test]
+    fn test_taker_fills() {
+        let mut ex = exchange();
+        ex.on_book(&book("BTC-USDT-SWAP", ("100", "5"), ("101", "5")), 0);
+        ex.submit(0, "BTC-USDT-SWAP", "1", "a", Side::Sell, 100., 1.);
+        ex.submit(0, "BTC-USDT-SWAP", "2", "b", Side::Buy, 102., 1.);
+        ex.advance(10);
+
+        // both take liquidity, one at its own price and one better than its price
+        let fills: Vec<_> = ex
+            .drain_events()
+            .into_iter()
+            .filter_map(|e| match e {
+                WebsocketEvent::Order(order) => Some(order.data[0].clone()),
+                _ => None,
+            })
+            .collect();
+        assert_eq!(fills.len(), 2);
+        assert!(fills.iter().all(|o| o.exec_type == "T"));
+        assert_eq!(fills[0].avg_px, "100");
+        assert_eq!(fills[1].avg_px, "101");
+    }
+
+    #[test]
+    fn test_short_goes_flat() {
+        let mut ex = exchange();
+        ex.on_book(&book("BTC-USDT-SWAP", ("100", "5"), ("101", "5")), 0);
+        ex.submit(0, "BTC-USDT-SWAP", "1", "a", Side::Sell, 100., 1.);
+        ex.advance(10);
+        assert_eq!(ex.position("BTC-USDT-SWAP"), (-1., 100.));
+
+        assert!(ex.close_position(20, "BTC-USDT-SWAP", "2"));
+        assert_eq!(ex.position("BTC-USDT-SWAP"), (0., 0.));
+    }
+}
@@ -0,0 +1,7 @@
+pub mod account;
+pub mod candles;
+pub mod clock;
+pub mod engine;
+pub mod exchange;
+pub mod recorder;
+pub mod replay;
@@ -0,0 +1,162 @@
+use super::replay::RecordedEvent;
+
+use anyhow::Result;
+use chrono::{TimeZone, Utc};
+use flate2::{write::GzEncoder, Compression};
+use log::info;
+use serde_json::Value;
+use std::collections::HashMap;
+use std::fs::{self, File};
+use std::io::{BufWriter, Write};
+use std::path::PathBuf;
+
+struct OpenFile {
+    period_start: u64,
+    writer: GzEncoder<BufWriter<File>>,
+}
+
+/// Writes raw websocket messages as gzipped json-lines, one file per channel and instrument,
+/// rolled over every `rotate_interval` ms. Files are named
+/// `<channel>_<instId>_<YYYYmmdd-HHMMSS>.jsonl.gz` after the start of their period and can be fed
+/// straight to `EventReplay`.
+pub struct EventRecorder {
+    output_dir: PathBuf,
+    rotate_interval: u64,
+    files: HashMap<String, OpenFile>,
+}
+
+impl EventRecorder {
+    pub fn new(output_dir: impl Into<PathBuf>, rotate_interval: u64) -> Result<Self> {
+        let output_dir = output_dir.into();
+        fs::create_dir_all(&output_dir)?;
+        Ok(EventRecorder {
+            output_dir,
+            rotate_interval: rotate_interval.max(1),
+            files: HashMap::new(),
+        })
+    }
+
+    /// Records a push message with its local receive time, returns false for messages without
+    /// a channel (subscribe acks, errors) which are not recorded.
+    pub fn record(&mut self, local_ts: u64, message: Value) -> Result<bool> {
+        let key = match stream_key(&message) {
+            Some(key) => key,
+            None => return Ok(false),
+        };
+        let period_start = local_ts - local_ts % self.rotate_interval;
+
+        let rotate = match self.files.get(&key) {
+            Some(file) => file.period_start != period_start,
+            None => true,
+        };
+        if rotate {
+            if let Some(file) = self.files.remove(&key) {
+                file.writer.finish()?.flush()?;
+            }
+            let file = self.open(&key, period_start)?;
+            self.files.insert(key.clone(), file);
+        }
+
+        let file = self.files.get_mut(&key).unwrap();
+        let line = serde_json::to_string(&RecordedEvent {
+            local_ts,
+            event: message,
+        })?;
+        file.writer.write_all(line.as_bytes())?;
+        file.writer.write_all(b"\n")?;
+        Ok(true)
+    }
+
+    /// Finishes all open files, the recorder can keep being used afterwards.
+    pub fn close(&mut self) -> Result<()> {
+        for (_, file) in self.files.drain() {
+            file.writer.finish()?.flush()?;
+        }
+        Ok(())
+    }
+
+    fn open(&self, key: &str, period_start: u64) -> Result<OpenFile> {
+        let stamp = Utc
+            .timestamp_millis(period_start as i64)
+            .format("%Y%m%d-%H%M%S");
+        let mut path = self.output_dir.join(format!("{}_{}.jsonl.gz", key, stamp));
+        // a restart inside the same period must not truncate what was recorded before
+        let mut n = 1;
+        while path.exists() {
+            path = self
+                .output_dir
+                .join(format!("{}_{}.{}.jsonl.gz", key, stamp, n));
+            n += 1;
+        }
+        info!("recording {} into {:?}", key, path);
+
+        let writer = GzEncoder::new(BufWriter::new(File::create(path)?), Compression::default());
+        Ok(OpenFile {
+            period_start,
+            writer,
+        })
+    }
+}
+
+impl Drop for EventRecorder {
+    fn drop(&mut self) {
+        let _ = self.close();
+    }
+}
+
+fn stream_key(message: &Value) -> Option<String> {
+    message.get("data")?;
+    let arg = message.get("arg")?;
+    let channel = arg.get("channel")?.as_str()?;
+    match arg.get("instId").and_then(|v| v.as_str()) {
+        Some(inst_id) => Some(format!("{}_{}", channel, inst_id)),
+        None => Some(channel.to_string()),
+    }
+}
+
+#[cfg(test)]
+mod test {
+    use super::*;
+    use flate2::read::GzDecoder;
+    use serde_json::json;
+    use std::io::Read;
+
+    #[test]
+    fn test_rotation() {
+        let dir = std::env::temp_dir().join(format!("recorder_test_{}", std::process::id()));
+        let _ = fs::remove_dir_all(&dir);
+        let mut recorder = EventRecorder::new(&dir, 1000).unwrap();
+        let push = |px: &str| json!({"arg": {"channel": "trades", "instId": "BTC-USDT"}, "data": [{"px": px}]});
+
+        assert!(recorder.record(100, push("1")).unwrap());
+        assert!(recorder.record(900, push("2")).unwrap());
+        assert!(!recorder.record(950, json!({"event": "subscribe"})).unwrap());
+        assert!(recorder.record(1500, push("3")).unwrap());
+        recorder.close().unwrap();
+
+        let mut names: Vec<String> = fs::read_dir(&dir)
+            .unwrap()
+            .map(|entry| entry.unwrap().file_name().into_string().unwrap())
+            .collect();
+        names.sort();
+        assert_eq!(
+            names,
+            vec![
+                "trades_BTC-USDT_19700101-000000.jsonl.gz",
+                "trades_BTC-USDT_19700101-000001.jsonl.gz"
+            ]
+        );
+
+        // the gzip streams were finished, every line reads back
+        let lines = |name: &str| {
+            let mut content = String::new();
+            GzDecoder::new(File::open(dir.join(name)).unwrap())
+                .read_to_string(&mut content)
+                .unwrap();
+            content.lines().count()
+        };
+        assert_eq!(lines(&names[0]), 2);
+        assert_eq!(lines(&names[1]), 1);
+        fs::remove_dir_all(&dir).unwrap();
+    }
+}
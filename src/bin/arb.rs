@@ -5,6 +5,8 @@ use exrs::binance::websockets::WebSockets as BinanceWebSockets;
 use exrs::binance::ws_model::WebsocketEventUntag as BinanceWSEvent;
 use exrs::huobi::websockets::WebSockets as HuobiWebSockets;
 use exrs::huobi::ws_model::WebsocketEvent as HuobiWSEvent;
+use exrs::market_data::{Exchange, MarketEvent, MarketEventKind, ToMarketEvents};
+use exrs::supervisor::Supervisor;
 
 use log::{debug, info, warn};
 use rainmaker::strategies::cross_exchange_arbitrage;
@@ -21,6 +23,15 @@ enum Event {
     BinanceWS(BinanceWSEvent),
 }
 
+impl ToMarketEvents for Event {
+    fn to_market_events(&self, local_ts: u64) -> Vec<MarketEvent> {
+        match self {
+            Event::HuobiWS(event) => event.to_market_events(local_ts),
+            Event::BinanceWS(event) => event.to_market_events(local_ts),
+        }
+    }
+}
+
 #[derive(Debug, Clone)]
 struct StrategyData {
     capacity: usize,
@@ -55,7 +66,15 @@ impl StrategyData {
         }
     }
 
-    pub fn push(&mut self, event: Event) {
+    pub fn push(&mut self, event: MarketEvent) {
+        let bbo = match event.kind {
+            MarketEventKind::Bbo(bbo) => bbo,
+            _ => {
+                warn!("{:?} data error.", event.exchange);
+                return;
+            }
+        };
+
         if self.hb_ask_price.len() > self.capacity - 1 {
             self.hb_ask_price.pop_front();
             self.hb_bid_price.pop_front();
@@ -69,71 +88,60 @@ impl StrategyData {
             self.hb_ask_div_ba_bid_ma.pop_front();
         }
 
-        match event {
-            Event::HuobiWS(hb) => {
-                match hb {
-                    HuobiWSEvent::BBO(hb) => {
-                        self.hb_ask_price.push_back(hb.tick.ask);
-                        self.hb_bid_price.push_back(hb.tick.bid);
-                        self.hb_mid_price
-                            .push_back((hb.tick.ask - hb.tick.bid) / 2.);
-                        // front fill
-                        self.ba_ask_price
-                            .push_back(*self.ba_ask_price.back().unwrap_or_else(|| &0.));
-                        self.ba_bid_price
-                            .push_back(*self.ba_bid_price.back().unwrap_or_else(|| &0.));
-                        self.ba_mid_price
-                            .push_back(*self.ba_mid_price.back().unwrap_or_else(|| &0.));
-
-                        self.hb_bid_div_ba_ask
-                            .push_back(hb.tick.bid / self.ba_ask_price.back().unwrap());
-                        self.hb_bid_div_ba_ask_ma.push_back(
-                            self.hb_bid_div_ba_ask.iter().sum::<f64>()
-                                / self.hb_bid_div_ba_ask.len() as f64,
-                        );
-
-                        self.hb_ask_div_ba_bid
-                            .push_back(hb.tick.ask / self.ba_bid_price.back().unwrap());
-                        self.hb_ask_div_ba_bid_ma.push_back(
-                            self.hb_ask_div_ba_bid.iter().sum::<f64>()
-                                / self.hb_ask_div_ba_bid.len() as f64,
-                        );
-                    }
-                    _ => warn!("huobi data error."),
-                }
+        match event.exchange {
+            Exchange::Huobi => {
+                self.hb_ask_price.push_back(bbo.ask);
+                self.hb_bid_price.push_back(bbo.bid);
+                self.hb_mid_price.push_back((bbo.ask - bbo.bid) / 2.);
+                // front fill
+                self.ba_ask_price
+                    .push_back(*self.ba_ask_price.back().unwrap_or_else(|| &0.));
+                self.ba_bid_price
+                    .push_back(*self.ba_bid_price.back().unwrap_or_else(|| &0.));
+                self.ba_mid_price
+                    .push_back(*self.ba_mid_price.back().unwrap_or_else(|| &0.));
+
+                self.hb_bid_div_ba_ask
+                    .push_back(bbo.bid / self.ba_ask_price.back().unwrap());
+                self.hb_bid_div_ba_ask_ma.push_back(
+                    self.hb_bid_div_ba_ask.iter().sum::<f64>()
+                        / self.hb_bid_div_ba_ask.len() as f64,
+                );
+
+                self.hb_ask_div_ba_bid
+                    .push_back(bbo.ask / self.ba_bid_price.back().unwrap());
+                self.hb_ask_div_ba_bid_ma.push_back(
+                    self.hb_ask_div_ba_bid.iter().sum::<f64>()
+                        / self.hb_ask_div_ba_bid.len() as f64,
+                );
             }
-            Event::BinanceWS(ba) => {
-                match ba {
-                    BinanceWSEvent::BookTicker(ba) => {
-                        self.ba_ask_price.push_back(ba.best_ask);
-                        self.ba_bid_price.push_back(ba.best_bid);
-                        self.ba_mid_price
-                            .push_back((ba.best_ask - ba.best_bid) / 2.);
-                        // front fill
-                        self.hb_ask_price
-                            .push_back(*self.hb_ask_price.back().unwrap_or_else(|| &0.));
-                        self.hb_bid_price
-                            .push_back(*self.hb_bid_price.back().unwrap_or_else(|| &0.));
-                        self.hb_mid_price
-                            .push_back(*self.hb_mid_price.back().unwrap_or_else(|| &0.));
-
-                        self.hb_bid_div_ba_ask
-                            .push_back(self.hb_ask_price.back().unwrap() / ba.best_ask);
-                        self.hb_bid_div_ba_ask_ma.push_back(
-                            self.hb_bid_div_ba_ask.iter().sum::<f64>()
-                                / self.hb_bid_div_ba_ask.len() as f64,
-                        );
-
-                        self.hb_ask_div_ba_bid
-                            .push_back(self.hb_bid_price.back().unwrap() / ba.best_bid);
-                        self.hb_ask_div_ba_bid_ma.push_back(
-                            self.hb_ask_div_ba_bid.iter().sum::<f64>()
-                                / self.hb_ask_div_ba_bid.len() as f64,
-                        );
-                    }
-                    _ => warn!("binance data error."),
-                }
+            Exchange::Binance => {
+                self.ba_ask_price.push_back(bbo.ask);
+                self.ba_bid_price.push_back(bbo.bid);
+                self.ba_mid_price.push_back((bbo.ask - bbo.bid) / 2.);
+                // front fill
+                self.hb_ask_price
+                    .push_back(*self.hb_ask_price.back().unwrap_or_else(|| &0.));
+                self.hb_bid_price
+                    .push_back(*self.hb_bid_price.back().unwrap_or_else(|| &0.));
+                self.hb_mid_price
+                    .push_back(*self.hb_mid_price.back().unwrap_or_else(|| &0.));
+
+                self.hb_bid_div_ba_ask
+                    .push_back(self.hb_ask_price.back().unwrap() / bbo.ask);
+                self.hb_bid_div_ba_ask_ma.push_back(
+                    self.hb_bid_div_ba_ask.iter().sum::<f64>()
+                        / self.hb_bid_div_ba_ask.len() as f64,
+                );
+
+                self.hb_ask_div_ba_bid
+                    .push_back(self.hb_bid_price.back().unwrap() / bbo.bid);
+                self.hb_ask_div_ba_bid_ma.push_back(
+                    self.hb_ask_div_ba_bid.iter().sum::<f64>()
+                        / self.hb_ask_div_ba_bid.len() as f64,
+                );
             }
+            exchange => warn!("{:?} data not used.", exchange),
         }
     }
 }
@@ -151,9 +159,9 @@ async fn main() {
     actix_rt::spawn(async move {
         hb_web_socket.connect("ws").await.unwrap();
         hb_web_socket.subscribe_request(bbo_req).await.unwrap();
-        if let Err(e) = hb_web_socket.event_loop(&keep_running).await {
-            println!("hb Error: {}", e);
-        }
+        Supervisor::new("huobi", hb_web_socket)
+            .run(&keep_running)
+            .await;
     });
 
     let ba_tx = tx.clone();
@@ -162,9 +170,9 @@ async fn main() {
     let mut ba_web_socket: BinanceWebSockets<Event> = BinanceWebSockets::new(ba_tx);
     actix_rt::spawn(async move {
         ba_web_socket.connect(&book_ticker).await.unwrap(); // check error
-        if let Err(e) = ba_web_socket.event_loop(&keep_running).await {
-            println!("ba Error: {}", e);
-        }
+        Supervisor::new("binance", ba_web_socket)
+            .run(&keep_running)
+            .await;
     });
 
     let mut strategy_data = StrategyData::with_capacity(100 * 60 * 60);
@@ -176,7 +184,10 @@ async fn main() {
     let mut close_on_ask = 0.;
     loop {
         let event = rx.recv().await.unwrap();
-        strategy_data.push(event);
+        let local_ts = chrono::Utc::now().timestamp_millis() as u64;
+        for market_event in event.to_market_events(local_ts) {
+            strategy_data.push(market_event);
+        }
 
         if strategy_data.hb_ask_price.len() < 200 {
             println!("len: {}", strategy_data.hb_ask_price.len())
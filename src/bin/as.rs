@@ -1,6 +1,7 @@
 extern crate rainmaker;
 use env_logger::Builder;
 use exrs::binance_f::api::*;
+use exrs::binance_f::general::FuturesGeneral;
 use exrs::binance_f::userstream::*;
 use exrs::binance_f::websockets::*;
 use exrs::binance_f::ws_model::FuturesWebsocketEvent;
@@ -9,7 +10,9 @@ use std::sync::atomic::{AtomicBool, Ordering};
 use std::{env, fs};
 use tokio::sync::mpsc;
 
+use rainmaker::instruments::InstrumentRegistry;
 use rainmaker::strategies::avellaneda_stoikov::AvellanedaStoikov;
+use rainmaker::strategies::eie::intensity_estimator::FillSource;
 
 #[actix_rt::main]
 async fn main() {
@@ -19,12 +22,21 @@ async fn main() {
     let file = fs::File::open(&args[1]).expect("file should open read only");
     let config: rainmaker::config::Config =
         serde_json::from_reader(file).expect("file shoud be proper json");
-    let sub = String::from(format!(
-        "{}{}{}",
-        config.base_asset.clone().to_lowercase(),
-        config.quote_asset.clone().to_lowercase(),
-        "@bookTicker"
-    ));
+    let stream_symbol = format!(
+        "{}{}",
+        config.base_asset.to_lowercase(),
+        config.quote_asset.to_lowercase()
+    );
+    // the aggregated trades fill the hypothetical orders of the intensity estimation
+    let sub = if config.fill_source == FillSource::Trades {
+        format!(
+            "{}/{}",
+            book_ticker_stream(&stream_symbol),
+            agg_trade_stream(&stream_symbol)
+        )
+    } else {
+        book_ticker_stream(&stream_symbol)
+    };
     println!("trading to: {:?}", sub);
 
     let (tx, rx): (
@@ -83,6 +95,17 @@ async fn main() {
         }
     });
 
-    let mut strategy = AvellanedaStoikov::new(config);
+    let symbol = format!("{}{}", config.base_asset, config.quote_asset);
+    let mut instruments = InstrumentRegistry::new();
+    let general: FuturesGeneral = BinanceF::new(None, None);
+    if let Err(e) = instruments.load_binance_futures(&general).await {
+        warn!("loading instruments failed, using the config: {}", e);
+    }
+    let instrument = instruments
+        .resolve(&symbol, config.tick_size)
+        .expect("instrument should be listed or have a tick_size in the config");
+    info!("instrument: {:?}", instrument);
+
+    let mut strategy = AvellanedaStoikov::new(config, instrument);
     strategy.run_forever(rx).await;
 }
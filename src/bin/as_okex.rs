@@ -1,14 +1,22 @@
 extern crate rainmaker;
 use env_logger::Builder;
+use exrs::okex_v5::api::Okex;
 use exrs::okex_v5::config::*;
+use exrs::okex_v5::general::General;
+use exrs::okex_v5::orderbook::OrderBooks;
 use exrs::okex_v5::websockets::*;
 use exrs::okex_v5::ws_model::{Arg, SubscriptionRequest, WebsocketEvent};
+use exrs::supervisor::{ConnectionEvent, Supervisor};
 use log::{debug, info, warn};
 use std::sync::atomic::{AtomicBool, Ordering};
+use std::sync::Arc;
 use std::{env, fs};
 use tokio::sync::mpsc;
 
+use rainmaker::instruments::InstrumentRegistry;
+use rainmaker::shutdown::{self, ShutdownReason};
 use rainmaker::strategies::avellaneda_stoikov_okex::AvellanedaStoikov;
+use rainmaker::strategies::eie::intensity_estimator::FillSource;
 
 #[actix_rt::main]
 async fn main() {
@@ -24,76 +32,128 @@ async fn main() {
         config.quote_asset.clone()
     );
 
-    let sub = format!(
-        "{{\"op\": \"subscribe\",\"args\": [{{\"channel\": \"books5\",\"instId\": \"{}\"}}]}}",
-        pair
-    );
+    let mut instruments = InstrumentRegistry::new();
+    let general: General = Okex::new(None, None, None, &Config::new(config.is_testnet));
+    for inst_type in ["SPOT", "SWAP", "FUTURES"] {
+        if let Err(e) = instruments.load_okex(&general, inst_type).await {
+            warn!("loading {} instruments failed: {}", inst_type, e);
+        }
+    }
+    let instrument = instruments
+        .resolve(&pair, config.tick_size)
+        .expect("instrument should be listed or have a tick_size in the config");
+    info!("instrument: {:?}", instrument);
+    // the private channels take any type when the instrument could not be loaded
+    let inst_type = match instrument.inst_type.as_str() {
+        "" => "ANY".to_string(),
+        inst_type => inst_type.to_string(),
+    };
+
+    let mut strategy = AvellanedaStoikov::new(config.clone(), instrument);
+    let sub = OrderBooks::new(config.book_channel.clone()).subscribe_request(&pair);
     println!("trading to: {:?}", sub);
+    // the trade prints fill the hypothetical orders of the intensity estimation
+    let trades = (config.fill_source == FillSource::Trades).then(|| Arg::trades(&pair));
 
     let (tx, rx): (mpsc::Sender<WebsocketEvent>, mpsc::Receiver<WebsocketEvent>) =
         mpsc::channel(1024);
-
-    let private_keep_running = AtomicBool::new(true);
+    let (states_tx, states_rx): (
+        mpsc::UnboundedSender<ConnectionEvent>,
+        mpsc::UnboundedReceiver<ConnectionEvent>,
+    ) = mpsc::unbounded_channel();
+
+    let (shutdown_tx, shutdown_rx): (
+        mpsc::UnboundedSender<ShutdownReason>,
+        mpsc::UnboundedReceiver<ShutdownReason>,
+    ) = mpsc::unbounded_channel();
+    actix_rt::spawn(shutdown::forward_signals(shutdown_tx));
+
+    let private_keep_running = Arc::new(AtomicBool::new(true));
+    let private_running = private_keep_running.clone();
     let private_tx = tx.clone();
-    let c = config.clone();
     let is_testnet = config.is_testnet;
+    let private_states = states_tx.clone();
+    let credentials = (
+        config.api_key.clone().expect("api_key should be set"),
+        config.secret_key.clone().expect("secret_key should be set"),
+        config.passphrase.clone().expect("passphrase should be set"),
+    );
 
-    actix_rt::spawn(async move {
-        let mut private_ws: WebSockets<WebsocketEvent> = WebSockets::new(private_tx, is_testnet);
-        private_ws.connect("private").await.unwrap();
-        private_ws
-            .login(
-                c.api_key.unwrap(),
-                c.secret_key.unwrap(),
-                c.passphrase.unwrap(),
-            )
-            .await
-            .unwrap();
-
-        let arg = Arg {
-            channel: "positions".to_string(),
-            inst_type: Some("SWAP".to_string()),
-            inst_id: None,
-            ccy: None,
-            uly: None,
-        };
-
-        let req = SubscriptionRequest {
-            op: "subscribe".to_string(),
-            args: vec![arg],
-        };
-
-        let req_string = serde_json::to_string(&req).unwrap();
-
-        private_ws.subscribe_request(&req_string).await.unwrap();
-
-        while let Err(e) = private_ws.event_loop(&private_keep_running).await {
-            warn!("private_ws event_loop Error: {}, starting reconnect...", e);
+    // orders drive the order manager and the portfolio of the strategy, balance and
+    // position updates carry the funding payments
+    // spot has no positions
+    let channels: &[&str] = if inst_type == "SPOT" {
+        &["orders"]
+    } else {
+        &["positions", "orders"]
+    };
+    let mut args: Vec<Arg> = channels
+        .iter()
+        .map(|channel| Arg::inst_type(channel, &inst_type))
+        .collect();
+    args.push(Arg::new("balance_and_position"));
+    // stops living on the venue, conditional and oco on orders-algo, trailing on algo-advance
+    for channel in ["orders-algo", "algo-advance"] {
+        args.push(Arg::inst_type(channel, &inst_type));
+    }
+    let private_sub =
+        serde_json::to_string(&SubscriptionRequest::new(args)).expect("request should serialize");
+
+    let mut private_ws: WebSockets<WebsocketEvent> = WebSockets::new(private_tx, is_testnet);
+    private_ws
+        .prepare("private", Some(credentials), vec![private_sub])
+        .expect("private endpoint should be known");
+    let trader = private_ws.trader();
 
-            while let Err(e) = private_ws.connect("private").await {
-                warn!("private_ws connect Error: {}, try again...", e);
-            }
-        }
+    actix_rt::spawn(async move {
+        // the first connection, login and subscription retry like the reconnects and report
+        // their state, the strategy does not quote while the socket is down
+        Supervisor::new("okex private", private_ws)
+            .with_states(private_states)
+            .run(&private_running)
+            .await;
     });
 
-    let public_keep_running = AtomicBool::new(true);
+    let public_keep_running = Arc::new(AtomicBool::new(true));
+    let public_running = public_keep_running.clone();
+    let mut public_ws: WebSockets<WebsocketEvent> = WebSockets::new(tx.clone(), is_testnet);
+    strategy.set_book_requests(public_ws.request_sender());
+    let mut public_subs = vec![sub];
+    if let Some(trades) = trades {
+        public_subs.push(
+            serde_json::to_string(&SubscriptionRequest::new(vec![trades]))
+                .expect("request should serialize"),
+        );
+    }
+    public_ws
+        .prepare("public", None, public_subs)
+        .expect("public endpoint should be known");
     actix_rt::spawn(async move {
-        let public_tx = tx.clone();
-        let mut public_ws: WebSockets<WebsocketEvent> = WebSockets::new(public_tx, is_testnet);
-
-        public_ws.connect("public").await.unwrap();
-        public_ws.subscribe_request(&sub).await.unwrap();
-
-        while let Err(e) = public_ws.event_loop(&public_keep_running).await {
-            warn!("public_ws event_loop Error: {}, starting reconnect...", e);
-
-            while let Err(e) = public_ws.connect("public").await {
-                public_ws.subscribe_request(&sub).await.unwrap();
-                warn!("public_ws connect Error: {}, try again...", e);
-            }
-        }
+        Supervisor::new("okex public", public_ws)
+            .with_states(states_tx)
+            .run(&public_running)
+            .await;
     });
 
-    let mut strategy = AvellanedaStoikov::new(config);
-    strategy.run_forever(rx).await;
+    strategy
+        .configure_account()
+        .await
+        .expect("account configuration should succeed");
+    // orders and position left by a previous run, before the first quote
+    strategy
+        .recover()
+        .await
+        .expect("startup reconciliation should succeed");
+    if config.ws_trading {
+        strategy.set_ws_trader(trader);
+    }
+
+    let summary = strategy.run_forever(rx, states_rx, shutdown_rx).await;
+    private_keep_running.store(false, Ordering::Relaxed);
+    public_keep_running.store(false, Ordering::Relaxed);
+    println!(
+        "shutdown: {}",
+        serde_json::to_string(&summary).expect("summary should serialize")
+    );
+    std::process::exit(if summary.confirmed { 0 } else { 1 });
 }
@@ -0,0 +1,75 @@
+extern crate rainmaker;
+use env_logger::Builder;
+use std::{env, fs};
+
+use rainmaker::backtest::{engine::BacktestEngine, replay::EventReplay};
+use rainmaker::config::{BacktestConfig, OkexConfig};
+use rainmaker::instruments::Instrument;
+use rainmaker::strategies::avellaneda_stoikov_okex::AvellanedaStoikov;
+
+/// Usage: backtest <okex_config.json> <backtest_config.json>
+#[actix_rt::main]
+async fn main() {
+    println!("backtest started: {:?}", chrono::prelude::Local::now());
+    Builder::new().parse_default_env().init();
+    let args: Vec<String> = env::args().collect();
+    if args.len() < 3 {
+        panic!("usage: backtest <okex_config.json> <backtest_config.json>");
+    }
+
+    let file = fs::File::open(&args[1]).expect("file should open read only");
+    let config: OkexConfig = serde_json::from_reader(file).expect("file shoud be proper json");
+    let file = fs::File::open(&args[2]).expect("file should open read only");
+    let bt_config: BacktestConfig =
+        serde_json::from_reader(file).expect("file shoud be proper json");
+
+    let mut replay = EventReplay::open(&bt_config.data_files)
+        .expect("recorded files should open read only")
+        .peekable();
+    let start_ts = match replay.peek() {
+        Some(first) => first.local_ts,
+        None => panic!("no recorded events found in {:?}", bt_config.data_files),
+    };
+
+    let engine = BacktestEngine::new(start_ts, &bt_config);
+    let pair = format!("{}-{}", config.base_asset, config.quote_asset);
+    let tick_size = config
+        .tick_size
+        .expect("backtests need the tick_size in the config");
+    let instrument = Instrument::with_tick_size(&pair, tick_size);
+    let mut strategy =
+        AvellanedaStoikov::with_account(config, engine.account(), instrument, start_ts);
+    let report = engine.run(&mut strategy, replay).await;
+
+    let mut wrt =
+        csv::Writer::from_path(&bt_config.orders_output).expect("output csv should be writable");
+    for order in report.orders.iter() {
+        wrt.serialize(order).unwrap();
+    }
+    wrt.flush().unwrap();
+
+    println!(
+        "replayed {} events from {} to {}, captured {} orders into {}",
+        report.events,
+        report.start_ts,
+        report.end_ts,
+        report.orders.len(),
+        bt_config.orders_output
+    );
+    for (inst_id, pos, avg_px) in report.positions.iter() {
+        println!("final position {}: {} @ {}", inst_id, pos, avg_px);
+    }
+    for holding in report.holdings.iter() {
+        println!(
+            "{}: realized {:.4}, unrealized {:.4} ({:.2}%), fees {:.4}, rebates {:.4}, funding {:.4}, net {:.4}",
+            holding.symbol,
+            holding.realized_pnl,
+            holding.unrealized_pnl(),
+            holding.unrealized_pct() * 100.,
+            holding.fees,
+            holding.rebates,
+            holding.funding,
+            holding.net_pnl()
+        );
+    }
+}
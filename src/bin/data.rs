@@ -1,44 +1,48 @@
 use std::env;
 use std::fs;
 use chrono::NaiveDateTime;
+use env_logger::Builder;
+use log::warn;
 
 use exrs::okex_v5::api::*;
 use exrs::okex_v5::config::*;
 use exrs::okex_v5::market::*;
+use exrs::okex_v5::util::get_timestamp;
 
+use rainmaker::backtest::candles::{CandleDownloader, CandleFormat};
+use rainmaker::config::DownloaderConfig;
+
+/// Downloads historical candles for backtesting, safe to rerun as it resumes from the stored files.
+/// Usage: data <downloader_config.json>
 #[actix_rt::main]
 async fn main() {
-    // Intend for downloading historical data for backtesting strategy
+    Builder::new().parse_default_env().init();
     let args: Vec<String> = env::args().collect();
     let file = fs::File::open(&args[1]).expect("Error in opening config file");
-    let config: rainmaker::config::OkexConfig =
+    let config: DownloaderConfig =
         serde_json::from_reader(file).expect("Expect a proper json file");
     let api_config = Config::new(config.is_testnet);
-    let market_client: Market = Okex::new(
-        config.api_key.clone(),
-        config.secret_key.clone(),
-        config.passphrase.clone(),
-        &api_config
-    );
-    let symbol = String::from("BTC-USDT");
-    let start_time = NaiveDateTime::parse_from_str("2023-01-01 00:00:00", "%Y-%m-%d %H:%M:%S").unwrap();
-    // let end_time = NaiveDateTime::parse_from_str("2023-01-01 01:00:00", "%Y-%m-%d %H:%M:%S").unwrap();
-    println!("{:?}", start_time.timestamp_millis());
-    let response 
-        = market_client.get_candles(
-            symbol, 
-            None, 
-            Some(start_time.timestamp_millis()), 
-            None,
-            // Some(end_time.timestamp_millis()), 
-            None
-    ).await;
-    println!("{:?}", response);
-}
+    let market_client: Market = Okex::new(None, None, None, &api_config);
+
+    let start_time = NaiveDateTime::parse_from_str(&config.start_time, "%Y-%m-%d %H:%M:%S")
+        .expect("start_time should be %Y-%m-%d %H:%M:%S")
+        .timestamp_millis() as u64;
+    let end_time = match &config.end_time {
+        Some(end_time) => NaiveDateTime::parse_from_str(end_time, "%Y-%m-%d %H:%M:%S")
+            .expect("end_time should be %Y-%m-%d %H:%M:%S")
+            .timestamp_millis() as u64,
+        None => get_timestamp().unwrap(),
+    };
 
-// 1672534800000
-// 1676680920000
-// 1676674980000
-// 1676674920000 Friday, February 17, 2023 11:02:00 PM
-// 1676669040000 Friday, February 17, 2023 9:24:00 PM
-// 1676668980000 Friday, February 17, 2023 9:23:00 PM
+    let format = CandleFormat::from_name(&config.format).unwrap();
+    let downloader = CandleDownloader::new(market_client, &config.output_dir, format)
+        .expect("output dir should be writable");
+
+    for inst_id in config.inst_ids.iter() {
+        for bar in config.bars.iter() {
+            if let Err(e) = downloader.download(inst_id, bar, start_time, end_time).await {
+                warn!("download {} {} failed: {}, rerun to resume", inst_id, bar, e);
+            }
+        }
+    }
+}
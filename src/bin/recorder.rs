@@ -0,0 +1,96 @@
+extern crate rainmaker;
+use env_logger::Builder;
+use exrs::okex_v5::util::get_timestamp;
+use exrs::okex_v5::websockets::*;
+use exrs::okex_v5::ws_model::{Arg, SubscriptionRequest};
+use exrs::supervisor::Supervisor;
+use log::{info, warn};
+use serde_json::Value;
+use std::sync::atomic::{AtomicBool, Ordering};
+use std::sync::Arc;
+use std::{env, fs};
+use tokio::sync::mpsc;
+
+use rainmaker::backtest::recorder::EventRecorder;
+use rainmaker::config::RecorderConfig;
+use rainmaker::shutdown::{self, ShutdownReason};
+
+/// Records OKX public channels for the backtester, the files are finished on SIGINT/SIGTERM.
+/// Usage: recorder <recorder_config.json>
+#[actix_rt::main]
+async fn main() {
+    println!("recorder started: {:?}", chrono::prelude::Local::now());
+    Builder::new().parse_default_env().init();
+    let args: Vec<String> = env::args().collect();
+    let file = fs::File::open(&args[1]).expect("file should open read only");
+    let config: RecorderConfig = serde_json::from_reader(file).expect("file shoud be proper json");
+
+    let mut sub_args = Vec::new();
+    for inst_id in config.inst_ids.iter() {
+        for channel in config.channels.iter() {
+            sub_args.push(Arg::inst_id(channel, inst_id));
+        }
+    }
+    let sub = serde_json::to_string(&SubscriptionRequest::new(sub_args)).unwrap();
+    info!("recording: {}", sub);
+
+    // raw messages are kept so the recorded files stay lossless
+    let (tx, mut rx): (mpsc::Sender<Value>, mpsc::Receiver<Value>) = mpsc::channel(4096);
+    let is_testnet = config.is_testnet;
+
+    let (shutdown_tx, mut shutdown_rx): (
+        mpsc::UnboundedSender<ShutdownReason>,
+        mpsc::UnboundedReceiver<ShutdownReason>,
+    ) = mpsc::unbounded_channel();
+    actix_rt::spawn(shutdown::forward_signals(shutdown_tx));
+
+    let keep_running = Arc::new(AtomicBool::new(true));
+    let running = keep_running.clone();
+    actix_rt::spawn(async move {
+        let mut public_ws: WebSockets<Value> = WebSockets::new(tx, is_testnet);
+        public_ws
+            .prepare("public", None, vec![sub])
+            .expect("public endpoint should be known");
+
+        // the supervisor connects with backoff and sends the subscription after every connect
+        Supervisor::new("okex public", public_ws)
+            .run(&running)
+            .await;
+    });
+
+    let mut recorder = EventRecorder::new(&config.output_dir, config.rotate_interval * 1000)
+        .expect("output dir should be writable");
+    let mut count: u64 = 0;
+    loop {
+        let message = tokio::select! {
+            message = rx.recv() => match message {
+                Some(message) => message,
+                None => break,
+            },
+            Some(reason) = shutdown_rx.recv() => {
+                info!("recorder stopping, {}", reason);
+                break;
+            }
+        };
+        let local_ts = get_timestamp().unwrap();
+        if message.get("event").is_some() {
+            info!("websocket response: {}", message);
+            continue;
+        }
+        match recorder.record(local_ts, message) {
+            Ok(true) => {
+                count += 1;
+                if count.is_multiple_of(100_000) {
+                    info!("recorded {} messages", count);
+                }
+            }
+            Ok(false) => warn!("recorder skipped message without channel"),
+            Err(e) => warn!("recorder write Error: {}", e),
+        }
+    }
+    keep_running.store(false, Ordering::Relaxed);
+    match recorder.close() {
+        Ok(()) => info!("recorded {} messages, files finished", count),
+        Err(e) => warn!("recorder close Error: {}", e),
+    }
+}
@@ -1,5 +1,9 @@
+use exrs::okex_v5::rest_model::PositionMode;
 use serde::Deserialize;
 
+use crate::strategies::eie::calibration::aksolver_factory::SolverType;
+use crate::strategies::eie::intensity_estimator::FillSource;
+
 #[derive(Debug, Clone, Deserialize)]
 pub struct Config {
     pub api_key: Option<String>,
@@ -7,10 +11,17 @@ pub struct Config {
     pub base_asset: String,
     pub quote_asset: String,
     pub order_qty: f64,
-    pub tick_size: f64,
+    /// Tick size used when the instrument metadata cannot be loaded, e.g. in backtests
+    #[serde(default)]
+    pub tick_size: Option<f64>,
     pub n_spreads: usize,
     pub estimate_window: u64,
     pub period: u64,
+    #[serde(default)]
+    pub calibration: CalibrationConfig,
+    /// book or trades, what fills the hypothetical orders of the intensity estimation
+    #[serde(default)]
+    pub fill_source: FillSource,
     pub sigma_tick_period: usize,
     pub gamma: f64,
     pub sigma_multiplier: f64,
@@ -19,6 +30,8 @@ pub struct Config {
     pub stopprofit: f64,
     pub trailing_stop: f64,
     pub q_max: f64,
+    #[serde(default)]
+    pub risk: RiskConfig,
 }
 
 #[derive(Debug, Clone, Deserialize)]
@@ -29,10 +42,21 @@ pub struct OkexConfig {
     pub base_asset: String,
     pub quote_asset: String,
     pub order_qty: f64,
-    pub tick_size: f64,
+    /// Tick size used when the instrument metadata cannot be loaded, e.g. in backtests
+    #[serde(default)]
+    pub tick_size: Option<f64>,
     pub n_spreads: usize,
     pub estimate_window: u64,
     pub period: u64,
+    /// Fit of A and k to the spread intensity curve: multi_curve, log_regression,
+    /// poisson_mle or weighted_nls
+    #[serde(default)]
+    pub solver: SolverType,
+    #[serde(default)]
+    pub calibration: CalibrationConfig,
+    /// book or trades, what fills the hypothetical orders of the intensity estimation
+    #[serde(default)]
+    pub fill_source: FillSource,
     pub sigma_tick_period: usize,
     pub gamma: f64,
     pub sigma_multiplier: f64,
@@ -42,4 +66,140 @@ pub struct OkexConfig {
     pub trailing_stop: f64,
     pub q_max: f64,
     pub is_testnet: bool,
+    /// OKX order book channel, books5, books or books-l2-tbt
+    #[serde(default = "default_book_channel")]
+    pub book_channel: String,
+    #[serde(default)]
+    pub risk: RiskConfig,
+    #[serde(default)]
+    pub shutdown: ShutdownConfig,
+    /// Keep the orders a previous run left open instead of canceling them on startup
+    #[serde(default)]
+    pub keep_orphaned_orders: bool,
+    /// Cross margin leverage set on the pair at startup, left as it is when missing
+    #[serde(default)]
+    pub leverage: Option<f64>,
+    /// Switched to at startup when the account differs, only net_mode is supported since
+    /// orders are sent without a position side
+    #[serde(default)]
+    pub position_mode: Option<PositionMode>,
+    /// Place and cancel orders over the private websocket instead of REST
+    #[serde(default)]
+    pub ws_trading: bool,
+    /// Keep the stop loss, stop profit and trailing stop on the venue as algo orders placed
+    /// when the position changes, instead of closing the position from here. They trigger
+    /// while disconnected but are not simulated in backtests. Ignored on spot pairs, which
+    /// take no reduce only orders.
+    #[serde(default)]
+    pub venue_stops: bool,
+}
+
+fn default_book_channel() -> String {
+    "books5".to_string()
+}
+
+/// Pre-trade limits on top of `q_max`, the ones left out are not enforced
+#[derive(Debug, Clone, Default, Deserialize)]
+pub struct RiskConfig {
+    /// Quote value of the position including working orders
+    pub max_notional: Option<f64>,
+    pub max_open_orders: Option<usize>,
+    pub max_order_qty: Option<f64>,
+    /// Furthest a limit price may be from mid, 0.01 is 1%
+    pub price_band: Option<f64>,
+    pub max_orders_per_sec: Option<usize>,
+    pub max_cancels_per_sec: Option<usize>,
+}
+
+/// Checks A and k must pass before the strategy quotes with them, the ones left out are not
+/// enforced. Rejected estimates fall back to the last good ones.
+#[derive(Debug, Clone, Deserialize)]
+#[serde(default)]
+pub struct CalibrationConfig {
+    pub min_a: Option<f64>,
+    pub max_a: Option<f64>,
+    pub min_k: Option<f64>,
+    pub max_k: Option<f64>,
+    pub min_r_squared: Option<f64>,
+    /// Largest standard error of A or k relative to its estimate
+    pub max_relative_error: Option<f64>,
+    /// Weight of an accepted estimate against the last good one, 1 takes it as is
+    pub smoothing: f64,
+}
+
+impl Default for CalibrationConfig {
+    fn default() -> Self {
+        CalibrationConfig {
+            min_a: None,
+            max_a: None,
+            min_k: None,
+            max_k: None,
+            min_r_squared: None,
+            max_relative_error: None,
+            smoothing: 1.,
+        }
+    }
+}
+
+/// When the live loop stops by itself and how it winds down
+#[derive(Debug, Clone, Deserialize)]
+#[serde(default)]
+pub struct ShutdownConfig {
+    /// Largest drop of the net PnL from its peak, in the quote currency
+    pub max_drawdown: Option<f64>,
+    /// ms without market data before giving up
+    pub max_staleness: Option<u64>,
+    /// Close the position after canceling the orders
+    pub flatten: bool,
+    /// ms to wait for the venue to confirm the cancels and the close
+    pub confirm_timeout: u64,
+}
+
+impl Default for ShutdownConfig {
+    fn default() -> Self {
+        ShutdownConfig {
+            max_drawdown: None,
+            max_staleness: None,
+            flatten: false,
+            confirm_timeout: 10_000,
+        }
+    }
+}
+
+#[derive(Debug, Clone, Deserialize)]
+pub struct BacktestConfig {
+    pub data_files: Vec<String>,
+    pub orders_output: String,
+    /// ms between sending an order and it resting on the simulated book
+    pub order_entry_latency: u64,
+    /// ms between sending a cancel and it taking effect
+    pub cancel_latency: u64,
+    pub maker_fee: f64,
+    pub taker_fee: f64,
+}
+
+#[derive(Debug, Clone, Deserialize)]
+pub struct RecorderConfig {
+    pub is_testnet: bool,
+    pub inst_ids: Vec<String>,
+    /// OKX public channels, e.g. books5, books, trades, tickers
+    pub channels: Vec<String>,
+    pub output_dir: String,
+    /// seconds covered by each recorded file
+    pub rotate_interval: u64,
+}
+
+#[derive(Debug, Clone, Deserialize)]
+pub struct DownloaderConfig {
+    pub is_testnet: bool,
+    pub inst_ids: Vec<String>,
+    /// OKX bar sizes, e.g. 1m, 1H, 1D
+    pub bars: Vec<String>,
+    /// UTC, %Y-%m-%d %H:%M:%S
+    pub start_time: String,
+    /// UTC, %Y-%m-%d %H:%M:%S, up to now when missing
+    pub end_time: Option<String>,
+    pub output_dir: String,
+    /// csv or jsonl
+    pub format: String,
 }
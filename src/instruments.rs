@@ -0,0 +1,297 @@
+//! Instrument metadata.
+//!
+//! `InstrumentRegistry` loads tick size, lot size, minimum size and contract multiplier from
+//! the OKX instruments endpoint or the Binance spot and futures exchange info. The strategies
+//! round their prices to the tick and their quantities to the lot through `Instrument` and
+//! drop orders below the minimum size instead of having the venue reject them.
+
+use std::collections::HashMap;
+
+use anyhow::{bail, Result};
+use exrs::binance::{general::General as BinanceGeneral, rest_model as binance_model};
+use exrs::binance_f::{general::FuturesGeneral, rest_model as binance_f_model};
+use exrs::okex_v5::{
+    general::General as OkexGeneral,
+    rest_model::{self as okex_model, InstrumentsRequest},
+};
+use exrs::trading::Side;
+use log::{info, warn};
+
+#[derive(Debug, Clone, PartialEq)]
+pub struct Instrument {
+    pub symbol: String,
+    pub tick_size: f64,
+    /// Quantities are multiples of it, 0 leaves them as they are
+    pub lot_size: f64,
+    pub min_size: f64,
+    /// Quote value of one unit of quantity at a price of 1, ctVal for OKX derivatives
+    pub multiplier: f64,
+    /// OKX instType, SPOT, SWAP or FUTURES, empty for Binance and when only the tick is known
+    pub inst_type: String,
+}
+
+impl Instrument {
+    /// An instrument known only by its tick size, e.g. from a config in a backtest
+    pub fn with_tick_size(symbol: &str, tick_size: f64) -> Self {
+        Instrument {
+            symbol: symbol.to_string(),
+            tick_size,
+            lot_size: 0.,
+            min_size: 0.,
+            multiplier: 1.,
+            inst_type: String::new(),
+        }
+    }
+
+    pub fn from_okex(instrument: &okex_model::Instrument) -> Option<Self> {
+        let parse = |v: &str| v.parse::<f64>().ok();
+        // spot instruments have no contract value
+        let multiplier = match (parse(&instrument.ct_val), parse(&instrument.ct_mult)) {
+            (Some(ct_val), Some(ct_mult)) => ct_val * ct_mult,
+            (Some(ct_val), None) => ct_val,
+            _ => 1.,
+        };
+        Some(Instrument {
+            symbol: instrument.inst_id.clone(),
+            tick_size: parse(&instrument.tick_sz)?,
+            lot_size: parse(&instrument.lot_sz)?,
+            min_size: parse(&instrument.min_sz)?,
+            multiplier,
+            inst_type: instrument.inst_type.clone(),
+        })
+    }
+
+    pub fn from_binance(symbol: &binance_model::Symbol) -> Option<Self> {
+        let mut tick_size = None;
+        let mut lot = None;
+        for filter in &symbol.filters {
+            match filter {
+                binance_model::Filters::PriceFilter { tick_size: t, .. } => tick_size = Some(*t),
+                binance_model::Filters::LotSize {
+                    min_qty, step_size, ..
+                } => lot = Some((*step_size, *min_qty)),
+                _ => {}
+            }
+        }
+        let (lot_size, min_size) = lot?;
+        Some(Instrument {
+            symbol: symbol.symbol.clone(),
+            tick_size: tick_size?,
+            lot_size,
+            min_size,
+            multiplier: 1.,
+            inst_type: String::new(),
+        })
+    }
+
+    pub fn from_binance_futures(symbol: &binance_f_model::Symbol) -> Option<Self> {
+        let mut tick_size = None;
+        let mut lot = None;
+        for filter in &symbol.filters {
+            match filter {
+                binance_f_model::Filters::PriceFilter { tick_size: t, .. } => {
+                    tick_size = t.parse().ok()
+                }
+                binance_f_model::Filters::LotSize {
+                    min_qty, step_size, ..
+                } => lot = step_size.parse().ok().zip(min_qty.parse().ok()),
+                _ => {}
+            }
+        }
+        let (lot_size, min_size) = lot?;
+        Some(Instrument {
+            symbol: symbol.symbol.clone(),
+            tick_size: tick_size?,
+            lot_size,
+            min_size,
+            multiplier: 1.,
+            inst_type: String::new(),
+        })
+    }
+
+    /// Decimals of the tick size, 0 for ticks of 1 or more
+    pub fn price_decimals(&self) -> u32 {
+        decimals(self.tick_size)
+    }
+
+    /// Rounds to the tick away from the spread, down for bids and up for asks
+    pub fn round_price(&self, price: f64, side: Side) -> f64 {
+        if self.tick_size <= 0. {
+            return price;
+        }
+        let ticks = price / self.tick_size;
+        // float noise must not push an exact tick to the next one
+        let ticks = match side {
+            Side::Buy => (ticks + 1e-9).floor(),
+            Side::Sell => (ticks - 1e-9).ceil(),
+        };
+        round_decimals(ticks * self.tick_size, self.price_decimals())
+    }
+
+    /// Rounds down to the lot size
+    pub fn round_qty(&self, qty: f64) -> f64 {
+        if self.lot_size <= 0. {
+            return qty;
+        }
+        let lots = (qty / self.lot_size + 1e-9).floor();
+        round_decimals(lots * self.lot_size, decimals(self.lot_size))
+    }
+
+    /// Quantity rounded to the lot size, None when that leaves less than the minimum size
+    pub fn validate_qty(&self, qty: f64) -> Option<f64> {
+        let qty = self.round_qty(qty);
+        if qty <= 0. || qty < self.min_size {
+            None
+        } else {
+            Some(qty)
+        }
+    }
+}
+
+/// Decimals of a step like 0.01 or 1e-7, Display never uses the exponent form
+fn decimals(step: f64) -> u32 {
+    format!("{}", step)
+        .split('.')
+        .nth(1)
+        .map_or(0, |d| d.len() as u32)
+}
+
+fn round_decimals(v: f64, decimals: u32) -> f64 {
+    let scale = 10f64.powi(decimals as i32);
+    (v * scale).round() / scale
+}
+
+#[derive(Debug, Default)]
+pub struct InstrumentRegistry {
+    instruments: HashMap<String, Instrument>,
+}
+
+impl InstrumentRegistry {
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    pub fn insert(&mut self, instrument: Instrument) {
+        self.instruments
+            .insert(instrument.symbol.clone(), instrument);
+    }
+
+    pub fn get(&self, symbol: &str) -> Option<&Instrument> {
+        self.instruments.get(symbol)
+    }
+
+    /// The loaded `symbol`, or one known only by the fallback `tick_size` when `symbol` was
+    /// not loaded. The venue tick wins over the fallback.
+    pub fn resolve(&self, symbol: &str, tick_size: Option<f64>) -> Option<Instrument> {
+        match (self.get(symbol), tick_size) {
+            (Some(instrument), tick_size) => {
+                if tick_size.is_some_and(|t| t != instrument.tick_size) {
+                    warn!(
+                        "{} tick size is {} on the venue, ignoring {:?} of the config",
+                        symbol, instrument.tick_size, tick_size
+                    );
+                }
+                Some(instrument.clone())
+            }
+            (None, Some(tick_size)) => Some(Instrument::with_tick_size(symbol, tick_size)),
+            (None, None) => None,
+        }
+    }
+
+    pub fn len(&self) -> usize {
+        self.instruments.len()
+    }
+
+    pub fn is_empty(&self) -> bool {
+        self.instruments.is_empty()
+    }
+
+    /// Loads the OKX instruments of `inst_type`, e.g. SPOT or SWAP
+    pub async fn load_okex(&mut self, general: &OkexGeneral, inst_type: &str) -> Result<usize> {
+        let response = general
+            .get_instruments(InstrumentsRequest {
+                inst_type: inst_type.to_string(),
+                ..Default::default()
+            })
+            .await?;
+        if response.code != 0 {
+            bail!("instruments failed, {}: {}", response.code, response.msg);
+        }
+        let loaded = self.extend(response.data.iter().filter_map(Instrument::from_okex));
+        info!("loaded {} OKX {} instruments", loaded, inst_type);
+        Ok(loaded)
+    }
+
+    pub async fn load_binance(&mut self, general: &BinanceGeneral) -> Result<usize> {
+        let info = general.exchange_info().await?;
+        let loaded = self.extend(info.symbols.iter().filter_map(Instrument::from_binance));
+        info!("loaded {} Binance instruments", loaded);
+        Ok(loaded)
+    }
+
+    pub async fn load_binance_futures(&mut self, general: &FuturesGeneral) -> Result<usize> {
+        let info = general.exchange_info().await?;
+        let loaded = self.extend(
+            info.symbols
+                .iter()
+                .filter_map(Instrument::from_binance_futures),
+        );
+        info!("loaded {} Binance futures instruments", loaded);
+        Ok(loaded)
+    }
+
+    fn extend(&mut self, instruments: impl Iterator<Item = Instrument>) -> usize {
+        let mut loaded = 0;
+        for instrument in instruments {
+            self.insert(instrument);
+            loaded += 1;
+        }
+        loaded
+    }
+}
+
+#[cfg(test)]
+mod test {
+    use super::*;
+
+    #[test]
+    fn test_rounding() {
+        let instrument = Instrument {
+            symbol: "BTC-USDT-SWAP".to_string(),
+            tick_size: 0.1,
+            lot_size: 1.,
+            min_size: 1.,
+            multiplier: 0.01,
+            inst_type: "SWAP".to_string(),
+        };
+        assert_eq!(instrument.price_decimals(), 1);
+        assert_eq!(instrument.round_price(100.37, Side::Buy), 100.3);
+        assert_eq!(instrument.round_price(100.37, Side::Sell), 100.4);
+        assert_eq!(instrument.round_price(100.3, Side::Sell), 100.3);
+        assert_eq!(instrument.validate_qty(2.7), Some(2.));
+        assert_eq!(instrument.validate_qty(0.7), None);
+
+        // ticks that used to panic the string split
+        assert_eq!(Instrument::with_tick_size("X", 1.0).price_decimals(), 0);
+        let tiny = Instrument::with_tick_size("X", 1e-7);
+        assert_eq!(tiny.price_decimals(), 7);
+        assert_eq!(tiny.round_price(0.00001234567, Side::Buy), 0.0000123);
+    }
+
+    #[test]
+    fn test_resolve() {
+        let mut registry = InstrumentRegistry::new();
+        registry.insert(Instrument {
+            inst_type: "SWAP".to_string(),
+            ..Instrument::with_tick_size("BTC-USDT-SWAP", 0.1)
+        });
+        // the venue tick wins, the config one is only a fallback
+        let instrument = registry.resolve("BTC-USDT-SWAP", Some(0.5)).unwrap();
+        assert_eq!(instrument.tick_size, 0.1);
+        assert_eq!(instrument.inst_type, "SWAP");
+        let fallback = registry.resolve("ETH-USDT-SWAP", Some(0.01)).unwrap();
+        assert_eq!(fallback.tick_size, 0.01);
+        assert!(fallback.inst_type.is_empty());
+        assert!(registry.resolve("ETH-USDT-SWAP", None).is_none());
+    }
+}
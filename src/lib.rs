@@ -1,3 +1,9 @@
+pub mod backtest;
 pub mod config;
+pub mod instruments;
+pub mod oms;
+pub mod portfolio;
+pub mod risk;
+pub mod shutdown;
 pub mod strategies;
 pub mod util;
@@ -3,6 +3,7 @@
 
 use env_logger::Builder;
 use exrs::binance_f::api::*;
+use exrs::binance_f::general::FuturesGeneral;
 use exrs::binance_f::userstream::*;
 use exrs::binance_f::websockets::*;
 use exrs::binance_f::ws_model::FuturesWebsocketEvent;
@@ -12,9 +13,16 @@ use std::{env, fs};
 use tokio::sync::mpsc;
 
 pub mod config;
+pub mod instruments;
+pub mod oms;
+pub mod portfolio;
+pub mod risk;
+pub mod shutdown;
 pub mod strategies;
 pub mod util;
+use instruments::InstrumentRegistry;
 use strategies::avellaneda_stoikov::AvellanedaStoikov;
+use strategies::eie::intensity_estimator::FillSource;
 
 #[actix_rt::main]
 async fn main() {
@@ -23,12 +31,21 @@ async fn main() {
     let args: Vec<String> = env::args().collect();
     let file = fs::File::open(&args[1]).expect("file should open read only");
     let config: config::Config = serde_json::from_reader(file).expect("file shoud be proper json");
-    let sub = String::from(format!(
-        "{}{}{}",
-        config.base_asset.clone().to_lowercase(),
-        config.quote_asset.clone().to_lowercase(),
-        "@bookTicker"
-    ));
+    let stream_symbol = format!(
+        "{}{}",
+        config.base_asset.to_lowercase(),
+        config.quote_asset.to_lowercase()
+    );
+    // the aggregated trades fill the hypothetical orders of the intensity estimation
+    let sub = if config.fill_source == FillSource::Trades {
+        format!(
+            "{}/{}",
+            book_ticker_stream(&stream_symbol),
+            agg_trade_stream(&stream_symbol)
+        )
+    } else {
+        book_ticker_stream(&stream_symbol)
+    };
     println!("trading to: {:?}", sub);
 
     let (tx, rx): (
@@ -87,6 +104,17 @@ async fn main() {
         }
     });
 
-    let mut strategy = AvellanedaStoikov::new(config);
+    let symbol = format!("{}{}", config.base_asset, config.quote_asset);
+    let mut instruments = InstrumentRegistry::new();
+    let general: FuturesGeneral = BinanceF::new(None, None);
+    if let Err(e) = instruments.load_binance_futures(&general).await {
+        warn!("loading instruments failed, using the config: {}", e);
+    }
+    let instrument = instruments
+        .resolve(&symbol, config.tick_size)
+        .expect("instrument should be listed or have a tick_size in the config");
+    info!("instrument: {:?}", instrument);
+
+    let mut strategy = AvellanedaStoikov::new(config, instrument);
     strategy.run_forever(rx).await;
 }
@@ -0,0 +1,564 @@
+//! Order management.
+//!
+//! `OrderManager` follows every order the strategy sends through its life, from the REST
+//! request to the last update of the OKX `orders` channel, and answers open order, working
+//! quantity and fill queries. Each order moves through `OrderState` and transitions the venue
+//! reports out of order (e.g. a late `live` after `filled`) are ignored.
+
+use std::collections::{HashMap, HashSet, VecDeque};
+
+use exrs::okex_v5::rest_model::{OrderDetail, Transaction};
+use exrs::okex_v5::ws_model;
+use exrs::trading::{Fill, OrderAck, Side};
+use log::{debug, warn};
+
+/// Fills and trade ids kept for deduplication and queries, the oldest are dropped beyond it
+const MAX_FILLS: usize = 10_000;
+/// OKX sCode for canceling an order that does not exist
+const ORDER_NOT_EXIST: u16 = 51400;
+
+#[derive(Debug, Copy, Clone, PartialEq, Eq)]
+pub enum OrderState {
+    /// Sent, not acknowledged yet
+    PendingNew,
+    /// The request failed in transit, the order may still have reached the venue. It counts
+    /// as open until the orders channel or a cancel by client id settles it.
+    Unknown,
+    Live,
+    PartiallyFilled,
+    Filled,
+    /// Cancel sent, the order can still fill until the venue confirms
+    PendingCancel,
+    Canceled,
+    Rejected,
+}
+
+impl OrderState {
+    /// State of an OKX order `state` field
+    pub fn from_okex(state: &str) -> Option<OrderState> {
+        match state {
+            "live" => Some(OrderState::Live),
+            "partially_filled" => Some(OrderState::PartiallyFilled),
+            "filled" => Some(OrderState::Filled),
+            "canceled" | "mmp_canceled" => Some(OrderState::Canceled),
+            _ => None,
+        }
+    }
+
+    pub fn is_terminal(&self) -> bool {
+        matches!(
+            self,
+            OrderState::Filled | OrderState::Canceled | OrderState::Rejected
+        )
+    }
+
+    pub fn is_open(&self) -> bool {
+        !self.is_terminal()
+    }
+
+    /// Whether an order may move from `self` to `to`
+    pub fn can_become(&self, to: OrderState) -> bool {
+        use OrderState::*;
+        match (self, to) {
+            (PendingNew, _) => to != PendingNew,
+            (Unknown, _) => !matches!(to, PendingNew | Unknown),
+            (Live, PartiallyFilled | Filled | PendingCancel | Canceled) => true,
+            (PartiallyFilled, PartiallyFilled | Filled | PendingCancel | Canceled) => true,
+            // a rejected cancel puts the order back
+            (PendingCancel, Live | PartiallyFilled | Filled | Canceled) => true,
+            // or the cancel of an order whose placement failed found nothing on the venue
+            (PendingCancel, Unknown | Rejected) => true,
+            _ => false,
+        }
+    }
+}
+
+#[derive(Debug, Clone)]
+pub struct ManagedOrder {
+    pub symbol: String,
+    pub client_order_id: String,
+    /// Set once the venue acknowledged the order
+    pub order_id: Option<String>,
+    pub side: Side,
+    pub price: f64,
+    pub qty: f64,
+    pub filled_qty: f64,
+    pub avg_price: f64,
+    pub state: OrderState,
+    /// Venue message of a rejected order or cancel
+    pub reject_reason: Option<String>,
+    pub update_time: u64,
+}
+
+impl ManagedOrder {
+    /// Quantity still resting on the book, 0 once the order is done
+    pub fn working_qty(&self) -> f64 {
+        if self.state.is_open() {
+            (self.qty - self.filled_qty).max(0.)
+        } else {
+            0.
+        }
+    }
+
+    fn transition(&mut self, to: OrderState) -> bool {
+        if self.state == to {
+            return true;
+        }
+        if !self.state.can_become(to) {
+            debug!(
+                "{} ignoring {:?} -> {:?}",
+                self.client_order_id, self.state, to
+            );
+            return false;
+        }
+        self.state = to;
+        true
+    }
+
+    /// State the order is in when nothing is pending on it
+    fn resting_state(&self) -> OrderState {
+        if self.filled_qty > 0. {
+            OrderState::PartiallyFilled
+        } else if self.order_id.is_none() {
+            OrderState::Unknown
+        } else {
+            OrderState::Live
+        }
+    }
+}
+
+#[derive(Debug, Default)]
+pub struct OrderManager {
+    orders: HashMap<String, ManagedOrder>,
+    fills: VecDeque<Fill>,
+    trade_ids: HashSet<String>,
+    /// `trade_ids` in arrival order, to drop the oldest
+    trade_id_order: VecDeque<String>,
+}
+
+impl OrderManager {
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    /// Records an order about to be sent
+    pub fn on_new(
+        &mut self,
+        symbol: &str,
+        client_order_id: &str,
+        side: Side,
+        price: f64,
+        qty: f64,
+        ts: u64,
+    ) {
+        self.orders.insert(
+            client_order_id.to_string(),
+            ManagedOrder {
+                symbol: symbol.to_string(),
+                client_order_id: client_order_id.to_string(),
+                order_id: None,
+                side,
+                price,
+                qty,
+                filled_qty: 0.,
+                avg_price: 0.,
+                state: OrderState::PendingNew,
+                reject_reason: None,
+                update_time: ts,
+            },
+        );
+    }
+
+    /// Applies the per order result of a place order response, a non zero sCode is the venue
+    /// refusing the order
+    pub fn on_place_result(&mut self, tx: &Transaction) {
+        if tx.s_code != 0 {
+            let reason = format!("{}: {}", tx.s_code, tx.s_msg);
+            self.on_place_rejected(&tx.cl_ord_id, &reason);
+            return;
+        }
+        self.on_place_ack(&OrderAck {
+            order_id: tx.ord_id.clone(),
+            client_order_id: tx.cl_ord_id.clone(),
+        });
+    }
+
+    /// The venue accepted the order
+    pub fn on_place_ack(&mut self, ack: &OrderAck) {
+        if let Some(order) = self.orders.get_mut(&ack.client_order_id) {
+            order.order_id = Some(ack.order_id.clone());
+            // the orders channel may already have moved it further
+            if order.state == OrderState::PendingNew {
+                order.transition(OrderState::Live);
+            }
+        }
+    }
+
+    /// The venue refused the order
+    pub fn on_place_rejected(&mut self, client_order_id: &str, reason: &str) {
+        if let Some(order) = self.orders.get_mut(client_order_id) {
+            if order.transition(OrderState::Rejected) {
+                order.reject_reason = Some(reason.to_string());
+            }
+        }
+    }
+
+    /// The place request failed without an answer from the venue (REST error, dropped
+    /// connection, timeout), the order may or may not be live
+    pub fn on_place_failed(&mut self, client_order_id: &str, reason: &str) {
+        if let Some(order) = self.orders.get_mut(client_order_id) {
+            if order.state == OrderState::PendingNew && order.transition(OrderState::Unknown) {
+                warn!("{} placement unknown, {}", client_order_id, reason);
+            }
+        }
+    }
+
+    /// Marks the order as being canceled, false if it is not open
+    pub fn on_cancel_request(&mut self, client_order_id: &str) -> bool {
+        match self.orders.get_mut(client_order_id) {
+            Some(order) if order.state.is_open() => order.transition(OrderState::PendingCancel),
+            _ => false,
+        }
+    }
+
+    /// Applies the per order result of a cancel response
+    pub fn on_cancel_result(&mut self, tx: &Transaction) {
+        if tx.s_code == 0 {
+            return;
+        }
+        let reason = format!("{}: {}", tx.s_code, tx.s_msg);
+        // never acknowledged and not found on the venue, the placement did not go through
+        if let Some(order) = self.orders.get_mut(&tx.cl_ord_id) {
+            if tx.s_code == ORDER_NOT_EXIST
+                && order.order_id.is_none()
+                && order.state == OrderState::PendingCancel
+            {
+                order.transition(OrderState::Rejected);
+                order.reject_reason = Some(reason);
+                return;
+            }
+        }
+        self.on_cancel_failed(&tx.cl_ord_id, &reason);
+    }
+
+    /// Puts an order pending a cancel back, the venue refused or never got the cancel
+    pub fn on_cancel_failed(&mut self, client_order_id: &str, reason: &str) {
+        if let Some(order) = self.orders.get_mut(client_order_id) {
+            if order.state == OrderState::PendingCancel {
+                warn!("cancel of {} failed, {}", client_order_id, reason);
+                order.reject_reason = Some(reason.to_string());
+                let state = order.resting_state();
+                order.transition(state);
+            }
+        }
+    }
+
+    /// Applies an update of the orders channel, returns the fill it carries if it is new.
+    /// Orders placed outside this manager are picked up from their first update.
+    pub fn on_order_update(&mut self, update: &ws_model::Order) -> Option<Fill> {
+        let state = match OrderState::from_okex(&update.state) {
+            Some(state) => state,
+            None => {
+                warn!("unknown order state {}", update.state);
+                return None;
+            }
+        };
+        let ts = update.u_time.parse().unwrap_or(0);
+        // orders sent without a client id are keyed by the venue id
+        let key = if update.cl_ord_id.is_empty() {
+            update.ord_id.clone()
+        } else {
+            update.cl_ord_id.clone()
+        };
+        let order = self
+            .orders
+            .entry(key.clone())
+            .or_insert_with(|| ManagedOrder {
+                symbol: update.inst_id.clone(),
+                client_order_id: key,
+                order_id: None,
+                side: match update.side.as_str() {
+                    "sell" => Side::Sell,
+                    _ => Side::Buy,
+                },
+                price: parse(&update.px),
+                qty: parse(&update.sz),
+                filled_qty: 0.,
+                avg_price: 0.,
+                state: OrderState::PendingNew,
+                reject_reason: None,
+                update_time: ts,
+            });
+        if order.order_id.is_none() && !update.ord_id.is_empty() {
+            order.order_id = Some(update.ord_id.clone());
+        }
+
+        // fill updates only ever grow, a smaller one arrived late
+        let acc_fill_sz = parse(&update.acc_fill_sz);
+        if acc_fill_sz >= order.filled_qty {
+            order.filled_qty = acc_fill_sz;
+            let avg_px = parse(&update.avg_px);
+            if avg_px > 0. {
+                order.avg_price = avg_px;
+            }
+            // an amend changes price and size
+            if !order.state.is_terminal() && state.is_open() {
+                order.price = parse(&update.px);
+                order.qty = parse(&update.sz);
+            }
+        }
+        // keep waiting for the cancel confirmation when a fill comes in while it is pending
+        let pending_cancel = order.state == OrderState::PendingCancel
+            && matches!(state, OrderState::Live | OrderState::PartiallyFilled);
+        if !pending_cancel && order.transition(state) {
+            order.update_time = ts;
+        }
+
+        let fill = Fill::from_okex(update)?;
+        if !fill.trade_id.is_empty() {
+            if !self.trade_ids.insert(fill.trade_id.clone()) {
+                return None;
+            }
+            self.trade_id_order.push_back(fill.trade_id.clone());
+            if self.trade_id_order.len() > MAX_FILLS {
+                if let Some(trade_id) = self.trade_id_order.pop_front() {
+                    self.trade_ids.remove(&trade_id);
+                }
+            }
+        }
+        self.fills.push_back(fill.clone());
+        if self.fills.len() > MAX_FILLS {
+            self.fills.pop_front();
+        }
+        Some(fill)
+    }
+
+    pub fn order(&self, client_order_id: &str) -> Option<&ManagedOrder> {
+        self.orders.get(client_order_id)
+    }
+
+    /// Takes over an order found open on the venue, e.g. one left by a previous run. Returns
+    /// false for orders that are not open or already known.
+    pub fn restore(&mut self, detail: &OrderDetail) -> bool {
+        let state = match OrderState::from_okex(&detail.state) {
+            Some(state) if state.is_open() => state,
+            _ => return false,
+        };
+        let key = if detail.cl_ord_id.is_empty() {
+            detail.ord_id.clone()
+        } else {
+            detail.cl_ord_id.clone()
+        };
+        if self.orders.contains_key(&key) {
+            return false;
+        }
+        self.orders.insert(
+            key.clone(),
+            ManagedOrder {
+                symbol: detail.inst_id.clone(),
+                client_order_id: key,
+                order_id: Some(detail.ord_id.clone()),
+                side: match detail.side.as_str() {
+                    "sell" => Side::Sell,
+                    _ => Side::Buy,
+                },
+                price: parse(&detail.px),
+                qty: parse(&detail.sz),
+                filled_qty: parse(&detail.acc_fill_sz),
+                avg_price: parse(&detail.avg_px),
+                state,
+                reject_reason: None,
+                update_time: detail.u_time.parse().unwrap_or(0),
+            },
+        );
+        true
+    }
+
+    /// Orders not filled, canceled or rejected yet, including those pending a cancel
+    pub fn open_orders<'a>(&'a self, symbol: &'a str) -> impl Iterator<Item = &'a ManagedOrder> {
+        self.orders
+            .values()
+            .filter(move |o| o.symbol == symbol && o.state.is_open())
+    }
+
+    /// Client ids of the open orders not already being canceled
+    pub fn cancelable_ids(&self, symbol: &str) -> Vec<String> {
+        self.open_orders(symbol)
+            .filter(|o| o.state != OrderState::PendingCancel)
+            .map(|o| o.client_order_id.clone())
+            .collect()
+    }
+
+    /// Unfilled quantity of the open orders on one side
+    pub fn working_qty(&self, symbol: &str, side: Side) -> f64 {
+        self.open_orders(symbol)
+            .filter(|o| o.side == side)
+            .map(|o| o.working_qty())
+            .sum()
+    }
+
+    /// The last `MAX_FILLS` fills, oldest first
+    pub fn fills(&self) -> impl Iterator<Item = &Fill> {
+        self.fills.iter()
+    }
+
+    /// Signed filled quantity of `symbol` over the fills still kept, buys positive
+    pub fn net_filled_qty(&self, symbol: &str) -> f64 {
+        self.fills
+            .iter()
+            .filter(|f| f.symbol == symbol)
+            .map(|f| f.side.sign() * f.qty)
+            .sum()
+    }
+
+    /// Forgets the filled, canceled and rejected orders last updated before `before`, fills
+    /// are kept. Recently closed orders stay so late updates do not reopen them.
+    pub fn purge_closed(&mut self, before: u64) {
+        self.orders
+            .retain(|_, o| o.state.is_open() || o.update_time >= before);
+    }
+}
+
+fn parse(v: &str) -> f64 {
+    v.parse().unwrap_or(0.)
+}
+
+#[cfg(test)]
+mod test {
+    use super::*;
+
+    fn update(state: &str, fill_sz: &str, acc_fill_sz: &str, trade_id: &str) -> ws_model::Order {
+        ws_model::Order {
+            inst_id: "BTC-USDT-SWAP".to_string(),
+            ord_id: "1".to_string(),
+            cl_ord_id: "a".to_string(),
+            px: "100".to_string(),
+            sz: "3".to_string(),
+            side: "buy".to_string(),
+            state: state.to_string(),
+            fill_sz: fill_sz.to_string(),
+            fill_px: "100".to_string(),
+            acc_fill_sz: acc_fill_sz.to_string(),
+            avg_px: "100".to_string(),
+            trade_id: trade_id.to_string(),
+            ..Default::default()
+        }
+    }
+
+    #[test]
+    fn test_order_lifecycle() {
+        let mut oms = OrderManager::new();
+        oms.on_new("BTC-USDT-SWAP", "a", Side::Buy, 100., 3., 0);
+        assert_eq!(oms.working_qty("BTC-USDT-SWAP", Side::Buy), 3.);
+
+        assert!(oms.on_order_update(&update("live", "0", "0", "")).is_none());
+        let fill = oms.on_order_update(&update("partially_filled", "1", "1", "t1"));
+        assert_eq!(fill.unwrap().qty, 1.);
+        // the same trade twice is one fill
+        assert!(oms
+            .on_order_update(&update("partially_filled", "1", "1", "t1"))
+            .is_none());
+        assert_eq!(oms.working_qty("BTC-USDT-SWAP", Side::Buy), 2.);
+
+        assert!(oms.on_cancel_request("a"));
+        assert_eq!(oms.cancelable_ids("BTC-USDT-SWAP").len(), 0);
+        oms.on_order_update(&update("partially_filled", "1", "2", "t2"));
+        assert_eq!(oms.order("a").unwrap().state, OrderState::PendingCancel);
+        oms.on_order_update(&update("canceled", "0", "2", ""));
+        assert_eq!(oms.order("a").unwrap().state, OrderState::Canceled);
+        // late updates do not reopen it
+        oms.on_order_update(&update("live", "0", "0", ""));
+        assert_eq!(oms.order("a").unwrap().state, OrderState::Canceled);
+        assert_eq!(oms.working_qty("BTC-USDT-SWAP", Side::Buy), 0.);
+        assert_eq!(oms.net_filled_qty("BTC-USDT-SWAP"), 2.);
+    }
+
+    #[test]
+    fn test_rejections() {
+        let mut oms = OrderManager::new();
+        oms.on_new("BTC-USDT-SWAP", "a", Side::Sell, 100., 1., 0);
+        let tx = |s_code| Transaction {
+            cl_ord_id: "a".to_string(),
+            ord_id: "1".to_string(),
+            tag: None,
+            s_code,
+            s_msg: String::new(),
+        };
+        oms.on_place_result(&tx(0));
+        assert_eq!(oms.order("a").unwrap().state, OrderState::Live);
+        oms.on_cancel_request("a");
+        oms.on_cancel_result(&tx(51400));
+        assert_eq!(oms.order("a").unwrap().state, OrderState::Live);
+
+        oms.on_new("BTC-USDT-SWAP", "b", Side::Sell, 100., 1., 0);
+        oms.on_place_result(&Transaction {
+            cl_ord_id: "b".to_string(),
+            ..tx(51008)
+        });
+        assert_eq!(oms.order("b").unwrap().state, OrderState::Rejected);
+        oms.purge_closed(u64::MAX);
+        assert!(oms.order("b").is_none());
+    }
+
+    #[test]
+    fn test_unknown_placement() {
+        let mut oms = OrderManager::new();
+        oms.on_new("BTC-USDT-SWAP", "a", Side::Buy, 100., 3., 0);
+        oms.on_place_failed("a", "timeout");
+        assert_eq!(oms.order("a").unwrap().state, OrderState::Unknown);
+        // still counted and canceled by client id until it is settled
+        assert_eq!(oms.working_qty("BTC-USDT-SWAP", Side::Buy), 3.);
+        assert_eq!(oms.cancelable_ids("BTC-USDT-SWAP"), vec!["a".to_string()]);
+
+        // it did reach the venue
+        oms.on_order_update(&update("live", "0", "0", ""));
+        assert_eq!(oms.order("a").unwrap().state, OrderState::Live);
+        assert!(oms.on_cancel_request("a"));
+        oms.on_order_update(&update("canceled", "0", "0", ""));
+        assert_eq!(oms.order("a").unwrap().state, OrderState::Canceled);
+
+        // it did not, the cancel by client id finds nothing
+        oms.on_new("BTC-USDT-SWAP", "b", Side::Buy, 100., 1., 0);
+        oms.on_place_failed("b", "connection closed");
+        assert!(oms.on_cancel_request("b"));
+        oms.on_cancel_result(&Transaction {
+            cl_ord_id: "b".to_string(),
+            ord_id: String::new(),
+            tag: None,
+            s_code: ORDER_NOT_EXIST,
+            s_msg: "Order does not exist".to_string(),
+        });
+        assert_eq!(oms.order("b").unwrap().state, OrderState::Rejected);
+
+        // recently closed orders survive the purge
+        oms.purge_closed(0);
+        assert!(oms.order("a").is_some());
+        oms.purge_closed(u64::MAX);
+        assert!(oms.order("a").is_none() && oms.order("b").is_none());
+    }
+
+    #[test]
+    fn test_restore() {
+        let mut oms = OrderManager::new();
+        let detail = OrderDetail {
+            inst_id: "BTC-USDT-SWAP".to_string(),
+            ord_id: "7".to_string(),
+            px: "100".to_string(),
+            sz: "3".to_string(),
+            acc_fill_sz: "1".to_string(),
+            side: "sell".to_string(),
+            state: "partially_filled".to_string(),
+            ..Default::default()
+        };
+        assert!(oms.restore(&detail));
+        assert!(!oms.restore(&detail));
+        // no client id, the venue id is the key
+        assert_eq!(oms.cancelable_ids("BTC-USDT-SWAP"), vec!["7".to_string()]);
+        assert_eq!(oms.working_qty("BTC-USDT-SWAP", Side::Sell), 2.);
+        assert!(!oms.restore(&OrderDetail {
+            ord_id: "8".to_string(),
+            state: "filled".to_string(),
+            ..detail
+        }));
+    }
+}
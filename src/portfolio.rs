@@ -0,0 +1,350 @@
+//! Positions and PnL.
+//!
+//! `Portfolio` rebuilds every position from the fill stream: signed quantity, average entry,
+//! realized PnL on the closed part, unrealized PnL against the last mark, fees, rebates and
+//! funding. Amounts are in the quote currency, quantities in contracts scaled by the
+//! instrument's contract value. The venue's `positions` channel is the reference, a position
+//! that keeps disagreeing with it past the grace period is replaced by the venue's.
+
+use std::collections::HashMap;
+
+use exrs::okex_v5::rest_model::{BalanceDetail, PositionDetail};
+use exrs::okex_v5::ws_model::{BalancePosition, Positions};
+use exrs::trading::Fill;
+use log::{info, warn};
+use serde::Serialize;
+
+/// Quantities closer than this are equal
+const QTY_EPSILON: f64 = 1e-9;
+
+/// How long the fill stream may disagree with the venue before the venue wins, in ms
+const RECONCILE_GRACE: u64 = 5_000;
+
+#[derive(Debug, Clone, Default, Serialize)]
+pub struct Holding {
+    pub symbol: String,
+    /// Signed quantity, negative when short
+    pub qty: f64,
+    pub avg_entry: f64,
+    /// Quote value of one unit of `qty`, ctVal for OKX derivatives
+    pub multiplier: f64,
+    pub mark_price: f64,
+    pub realized_pnl: f64,
+    /// Fees charged
+    pub fees: f64,
+    /// Maker rebates received
+    pub rebates: f64,
+    /// Funding received, negative when paid
+    pub funding: f64,
+    pub volume: f64,
+    pub trades: u64,
+}
+
+impl Holding {
+    fn new(symbol: &str, multiplier: f64) -> Self {
+        Holding {
+            symbol: symbol.to_string(),
+            multiplier,
+            ..Default::default()
+        }
+    }
+
+    /// Applies a signed fill, returns the PnL it realized
+    fn apply(&mut self, qty: f64, price: f64) -> f64 {
+        let mut realized = 0.;
+        if self.qty.abs() < QTY_EPSILON || self.qty.signum() == qty.signum() {
+            let total = self.qty.abs() + qty.abs();
+            self.avg_entry = (self.qty.abs() * self.avg_entry + qty.abs() * price) / total;
+        } else {
+            let closed = qty.abs().min(self.qty.abs());
+            realized = closed * (price - self.avg_entry) * self.qty.signum() * self.multiplier;
+            // the part beyond the old position opens a new one at the fill price
+            if qty.abs() > self.qty.abs() + QTY_EPSILON {
+                self.avg_entry = price;
+            }
+        }
+        self.qty += qty;
+        if self.qty.abs() < QTY_EPSILON {
+            self.qty = 0.;
+            self.avg_entry = 0.;
+        }
+        self.realized_pnl += realized;
+        realized
+    }
+
+    pub fn notional(&self) -> f64 {
+        self.qty.abs() * self.avg_entry * self.multiplier
+    }
+
+    /// PnL of the open position against `mark_price`
+    pub fn unrealized_pnl(&self) -> f64 {
+        if self.qty == 0. || self.mark_price == 0. {
+            return 0.;
+        }
+        self.qty * (self.mark_price - self.avg_entry) * self.multiplier
+    }
+
+    /// Unrealized PnL relative to the entry notional, 0.01 is 1%
+    pub fn unrealized_pct(&self) -> f64 {
+        let notional = self.notional();
+        if notional == 0. {
+            0.
+        } else {
+            self.unrealized_pnl() / notional
+        }
+    }
+
+    /// Realized and unrealized PnL after fees, rebates and funding
+    pub fn net_pnl(&self) -> f64 {
+        self.realized_pnl + self.unrealized_pnl() - self.fees + self.rebates + self.funding
+    }
+}
+
+/// Outcome of comparing a position with the venue's
+#[derive(Debug, Copy, Clone, PartialEq)]
+pub enum Reconciliation {
+    InSync,
+    /// Quantities differ by this much, fills may still be on the way
+    Pending(f64),
+    /// The venue's position replaced the local one, which was off by this much
+    Adjusted(f64),
+}
+
+#[derive(Debug, Default)]
+pub struct Portfolio {
+    holdings: HashMap<String, Holding>,
+    multipliers: HashMap<String, f64>,
+    /// Cash balance per currency from the balance and position channel
+    cash: HashMap<String, f64>,
+    /// Since when each symbol disagrees with the venue
+    mismatch_since: HashMap<String, u64>,
+}
+
+impl Portfolio {
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    /// Contract value of `symbol`, 1 unless set
+    pub fn set_multiplier(&mut self, symbol: &str, multiplier: f64) {
+        self.multipliers.insert(symbol.to_string(), multiplier);
+        if let Some(holding) = self.holdings.get_mut(symbol) {
+            holding.multiplier = multiplier;
+        }
+    }
+
+    fn holding_mut(&mut self, symbol: &str) -> &mut Holding {
+        let multiplier = *self.multipliers.get(symbol).unwrap_or(&1.);
+        self.holdings
+            .entry(symbol.to_string())
+            .or_insert_with(|| Holding::new(symbol, multiplier))
+    }
+
+    pub fn holding(&self, symbol: &str) -> Option<&Holding> {
+        self.holdings.get(symbol)
+    }
+
+    pub fn holdings(&self) -> impl Iterator<Item = &Holding> {
+        self.holdings.values()
+    }
+
+    /// Applies a fill, returns the PnL it realized
+    pub fn on_fill(&mut self, fill: &Fill) -> f64 {
+        let holding = self.holding_mut(&fill.symbol);
+        let realized = holding.apply(fill.side.sign() * fill.qty, fill.price);
+        // spot buys are charged in the base currency
+        let base = fill.symbol.split('-').next().unwrap_or_default();
+        let fee = if fill.fee_asset == base {
+            fill.fee * fill.price
+        } else {
+            fill.fee
+        };
+        if fee >= 0. {
+            holding.fees += fee;
+        } else {
+            holding.rebates -= fee;
+        }
+        holding.volume += fill.qty * fill.price * holding.multiplier;
+        holding.trades += 1;
+        realized
+    }
+
+    pub fn on_funding(&mut self, symbol: &str, amount: f64) {
+        info!("{} funding {}", symbol, amount);
+        self.holding_mut(symbol).funding += amount;
+    }
+
+    /// Tracks cash balances and books the balance change of a `funding_fee` event as funding.
+    /// The change can only be attributed when the event carries a single position.
+    pub fn on_balance_position(&mut self, event: &BalancePosition) {
+        let mut change = 0.;
+        for balance in &event.bal_data {
+            let cash = balance.cash_bal.parse().unwrap_or(0.);
+            if let Some(previous) = self.cash.insert(balance.ccy.clone(), cash) {
+                change += cash - previous;
+            }
+        }
+        if event.event_type != "funding_fee" {
+            return;
+        }
+        match event.pos_data.as_slice() {
+            [position] => self.on_funding(&position.inst_id, change),
+            _ => warn!(
+                "funding of {} over {} positions not attributed",
+                change,
+                event.pos_data.len()
+            ),
+        }
+    }
+
+    /// Starts from a position read from the venue, e.g. after a restart. Quantity, entry and
+    /// mark are replaced, PnL already booked is kept.
+    pub fn restore_position(&mut self, position: &PositionDetail) {
+        let mut qty: f64 = position.pos.parse().unwrap_or(0.);
+        if position.pos_side == "short" {
+            qty = -qty.abs();
+        }
+        let holding = self.holding_mut(&position.inst_id);
+        holding.qty = qty;
+        holding.avg_entry = if qty == 0. {
+            0.
+        } else {
+            position.avg_px.parse().unwrap_or(0.)
+        };
+        if let Ok(mark_price) = position.mark_px.parse() {
+            holding.mark_price = mark_price;
+        }
+        self.mismatch_since.remove(&position.inst_id);
+        info!(
+            "{} restored at {} @ {}",
+            position.inst_id, qty, position.avg_px
+        );
+    }
+
+    /// Starts the cash balance of a currency from the venue, later funding payments are
+    /// measured against it
+    pub fn restore_balance(&mut self, balance: &BalanceDetail) {
+        self.cash
+            .insert(balance.ccy.clone(), balance.cash_bal.parse().unwrap_or(0.));
+    }
+
+    pub fn mark(&mut self, symbol: &str, price: f64) {
+        self.holding_mut(symbol).mark_price = price;
+    }
+
+    /// Compares a position update with the local position. A difference that outlives
+    /// `RECONCILE_GRACE` replaces quantity and entry with the venue's, realized PnL is kept.
+    pub fn reconcile(&mut self, position: &Positions, now: u64) -> Reconciliation {
+        let mut qty: f64 = position.pos.parse().unwrap_or(0.);
+        if position.pos_side == "short" {
+            qty = -qty.abs();
+        }
+        let avg_px = position.avg_px.parse().unwrap_or(0.);
+        let symbol = position.inst_id.clone();
+        let multiplier = *self.multipliers.get(&symbol).unwrap_or(&1.);
+        let holding = self
+            .holdings
+            .entry(symbol.clone())
+            .or_insert_with(|| Holding::new(&symbol, multiplier));
+        if let Ok(mark_price) = position.mark_px.parse() {
+            holding.mark_price = mark_price;
+        }
+        let diff = qty - holding.qty;
+        if diff.abs() < QTY_EPSILON {
+            self.mismatch_since.remove(&symbol);
+            return Reconciliation::InSync;
+        }
+        let since = *self.mismatch_since.entry(symbol.clone()).or_insert(now);
+        if now.saturating_sub(since) < RECONCILE_GRACE {
+            return Reconciliation::Pending(diff);
+        }
+        warn!(
+            "{} position {} disagrees with venue {} @ {}, adopting it",
+            symbol, holding.qty, qty, avg_px
+        );
+        holding.qty = qty;
+        holding.avg_entry = if qty == 0. { 0. } else { avg_px };
+        self.mismatch_since.remove(&symbol);
+        Reconciliation::Adjusted(diff)
+    }
+
+    pub fn realized_pnl(&self) -> f64 {
+        self.holdings.values().map(|h| h.realized_pnl).sum()
+    }
+
+    pub fn unrealized_pnl(&self) -> f64 {
+        self.holdings.values().map(|h| h.unrealized_pnl()).sum()
+    }
+
+    pub fn net_pnl(&self) -> f64 {
+        self.holdings.values().map(|h| h.net_pnl()).sum()
+    }
+}
+
+#[cfg(test)]
+mod test {
+    use super::*;
+    use exrs::trading::Side;
+
+    fn fill(side: Side, qty: f64, price: f64, fee: f64) -> Fill {
+        Fill {
+            symbol: "BTC-USDT-SWAP".to_string(),
+            order_id: String::new(),
+            client_order_id: String::new(),
+            trade_id: String::new(),
+            side,
+            price,
+            qty,
+            fee,
+            fee_asset: "USDT".to_string(),
+            is_maker: true,
+            ts: 0,
+        }
+    }
+
+    #[test]
+    fn test_pnl() {
+        let mut portfolio = Portfolio::new();
+        portfolio.set_multiplier("BTC-USDT-SWAP", 0.01);
+        portfolio.on_fill(&fill(Side::Buy, 2., 100., 0.1));
+        portfolio.on_fill(&fill(Side::Buy, 2., 110., -0.05));
+        let holding = portfolio.holding("BTC-USDT-SWAP").unwrap();
+        assert_eq!(holding.avg_entry, 105.);
+
+        // sell through the position, 4 closed at +15, 1 opened short at 120
+        let realized = portfolio.on_fill(&fill(Side::Sell, 5., 120., 0.));
+        assert!((realized - 4. * 15. * 0.01).abs() < 1e-9);
+        portfolio.mark("BTC-USDT-SWAP", 110.);
+        let holding = portfolio.holding("BTC-USDT-SWAP").unwrap();
+        assert_eq!(holding.qty, -1.);
+        assert_eq!(holding.avg_entry, 120.);
+        assert!((holding.unrealized_pnl() - 0.1).abs() < 1e-9);
+        assert!((holding.fees - 0.1).abs() < 1e-9);
+        assert!((holding.rebates - 0.05).abs() < 1e-9);
+        assert!((holding.net_pnl() - (0.6 + 0.1 - 0.1 + 0.05)).abs() < 1e-9);
+    }
+
+    #[test]
+    fn test_reconcile() {
+        let mut portfolio = Portfolio::new();
+        portfolio.on_fill(&fill(Side::Buy, 1., 100., 0.));
+        let position = Positions {
+            inst_id: "BTC-USDT-SWAP".to_string(),
+            pos: "3".to_string(),
+            avg_px: "101".to_string(),
+            pos_side: "net".to_string(),
+            ..Default::default()
+        };
+        assert_eq!(
+            portfolio.reconcile(&position, 0),
+            Reconciliation::Pending(2.)
+        );
+        assert_eq!(
+            portfolio.reconcile(&position, RECONCILE_GRACE),
+            Reconciliation::Adjusted(2.)
+        );
+        let holding = portfolio.holding("BTC-USDT-SWAP").unwrap();
+        assert_eq!((holding.qty, holding.avg_entry), (3., 101.));
+        assert_eq!(portfolio.reconcile(&position, 0), Reconciliation::InSync);
+    }
+}
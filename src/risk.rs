@@ -0,0 +1,305 @@
+//! Pre-trade risk checks.
+//!
+//! Every order the strategies send goes through `RiskManager::check_order` first. Orders that
+//! would breach the rate, open order or price band limits are rejected, orders that would take
+//! the position or its notional past the limits are clipped to what is left. Every rejection
+//! and clip is logged with its reason.
+
+use std::collections::VecDeque;
+
+use exrs::trading::Side;
+use log::warn;
+
+use crate::config::RiskConfig;
+
+/// Window of the rate limits in ms
+const RATE_WINDOW: u64 = 1_000;
+
+#[derive(Debug, Clone, PartialEq)]
+pub enum RiskDecision {
+    Accept(f64),
+    /// Accepted with a smaller quantity
+    Clip(f64, String),
+    Reject(String),
+}
+
+impl RiskDecision {
+    /// Quantity to send, None if rejected
+    pub fn qty(&self) -> Option<f64> {
+        match self {
+            RiskDecision::Accept(qty) | RiskDecision::Clip(qty, _) => Some(*qty),
+            RiskDecision::Reject(_) => None,
+        }
+    }
+}
+
+/// State the checks run against
+#[derive(Debug, Copy, Clone, Default)]
+pub struct Exposure {
+    /// Signed position
+    pub position: f64,
+    /// Unfilled quantity of the open orders on the side of the new order
+    pub working_qty: f64,
+    pub open_orders: usize,
+    pub mid_price: f64,
+}
+
+#[derive(Debug, Clone)]
+pub struct RiskManager {
+    max_position: f64,
+    config: RiskConfig,
+    /// Quote value of one unit of quantity
+    multiplier: f64,
+    /// Clipped quantities are rounded down to it, 0 leaves them as they are
+    lot_size: f64,
+    orders: VecDeque<u64>,
+    cancels: VecDeque<u64>,
+}
+
+impl RiskManager {
+    pub fn new(max_position: f64, config: RiskConfig) -> Self {
+        RiskManager {
+            max_position,
+            config,
+            multiplier: 1.,
+            lot_size: 0.,
+            orders: VecDeque::new(),
+            cancels: VecDeque::new(),
+        }
+    }
+
+    pub fn with_multiplier(mut self, multiplier: f64) -> Self {
+        self.multiplier = multiplier;
+        self
+    }
+
+    pub fn with_lot_size(mut self, lot_size: f64) -> Self {
+        self.lot_size = lot_size;
+        self
+    }
+
+    /// Checks a limit order at `now` (ms), an accepted order counts towards the order rate
+    pub fn check_order(
+        &mut self,
+        side: Side,
+        qty: f64,
+        price: f64,
+        exposure: &Exposure,
+        now: u64,
+    ) -> RiskDecision {
+        let decision = self.decide(side, qty, price, exposure, now);
+        match &decision {
+            RiskDecision::Accept(_) => {}
+            RiskDecision::Clip(clipped, reason) => {
+                warn!(
+                    "risk clipped {:?} {} @ {} to {}: {}",
+                    side, qty, price, clipped, reason
+                )
+            }
+            RiskDecision::Reject(reason) => {
+                warn!("risk rejected {:?} {} @ {}: {}", side, qty, price, reason)
+            }
+        }
+        if decision.qty().is_some() {
+            self.orders.push_back(now);
+        }
+        decision
+    }
+
+    fn decide(
+        &mut self,
+        side: Side,
+        qty: f64,
+        price: f64,
+        exposure: &Exposure,
+        now: u64,
+    ) -> RiskDecision {
+        if let Some(max) = self.config.max_orders_per_sec {
+            if within_window(&mut self.orders, now) >= max {
+                return RiskDecision::Reject(format!("more than {} orders per second", max));
+            }
+        }
+        if let Some(max) = self.config.max_open_orders {
+            if exposure.open_orders >= max {
+                return RiskDecision::Reject(format!("{} orders open already", max));
+            }
+        }
+        if let Some(band) = self.config.price_band {
+            if exposure.mid_price > 0. && (price / exposure.mid_price - 1.).abs() > band {
+                return RiskDecision::Reject(format!(
+                    "price {} more than {} away from mid {}",
+                    price, band, exposure.mid_price
+                ));
+            }
+        }
+
+        let mut allowed = qty;
+        let mut reasons = Vec::new();
+        if let Some(max) = self.config.max_order_qty {
+            if allowed > max {
+                allowed = max;
+                reasons.push(format!("order size limit {}", max));
+            }
+        }
+        // exposure if every order on this side fills
+        let committed = exposure.position + side.sign() * exposure.working_qty;
+        let room = self.room(side, committed, self.max_position);
+        if allowed > room {
+            allowed = room;
+            reasons.push(format!("position limit {}", self.max_position));
+        }
+        if let Some(max_notional) = self.config.max_notional {
+            if price > 0. {
+                let room = self.room(side, committed, max_notional / (price * self.multiplier));
+                if allowed > room {
+                    allowed = room;
+                    reasons.push(format!("notional limit {}", max_notional));
+                }
+            }
+        }
+
+        if reasons.is_empty() {
+            return RiskDecision::Accept(qty);
+        }
+        if self.lot_size > 0. {
+            allowed = (allowed / self.lot_size).floor() * self.lot_size;
+        }
+        if allowed <= 0. {
+            RiskDecision::Reject(reasons.join(", "))
+        } else {
+            RiskDecision::Clip(allowed, reasons.join(", "))
+        }
+    }
+
+    /// Quantity `side` may still add to `committed` without its absolute value passing `limit`.
+    /// Orders reducing the position may go down to the limit on the other side.
+    fn room(&self, side: Side, committed: f64, limit: f64) -> f64 {
+        (limit - side.sign() * committed).max(0.)
+    }
+
+    /// Checks a batch of `count` cancels at `now` (ms), accepted ones count towards the rate
+    pub fn check_cancels(&mut self, count: usize, now: u64) -> bool {
+        if let Some(max) = self.config.max_cancels_per_sec {
+            if within_window(&mut self.cancels, now) + count > max {
+                warn!(
+                    "risk rejected {} cancels: more than {} cancels per second",
+                    count, max
+                );
+                return false;
+            }
+        }
+        for _ in 0..count {
+            self.cancels.push_back(now);
+        }
+        true
+    }
+}
+
+/// Drops timestamps older than the rate window and counts the rest
+fn within_window(times: &mut VecDeque<u64>, now: u64) -> usize {
+    while let Some(&ts) = times.front() {
+        if ts + RATE_WINDOW <= now {
+            times.pop_front();
+        } else {
+            break;
+        }
+    }
+    times.len()
+}
+
+#[cfg(test)]
+mod test {
+    use super::*;
+
+    fn exposure(position: f64, working_qty: f64) -> Exposure {
+        Exposure {
+            position,
+            working_qty,
+            open_orders: 0,
+            mid_price: 100.,
+        }
+    }
+
+    #[test]
+    fn test_position_limits() {
+        let config = RiskConfig {
+            max_notional: Some(1_000.),
+            max_order_qty: Some(4.),
+            price_band: Some(0.05),
+            ..Default::default()
+        };
+        let mut risk = RiskManager::new(5., config);
+        let flat = exposure(0., 0.);
+        assert_eq!(
+            risk.check_order(Side::Buy, 2., 100., &flat, 0),
+            RiskDecision::Accept(2.)
+        );
+        assert_eq!(
+            risk.check_order(Side::Buy, 6., 100., &flat, 0).qty(),
+            Some(4.)
+        );
+        assert_eq!(risk.check_order(Side::Buy, 1., 110., &flat, 0).qty(), None);
+
+        // 3 long and 1 working, only 1 more may be bought, a sale may go 5 short
+        let long = exposure(3., 1.);
+        assert_eq!(
+            risk.check_order(Side::Buy, 2., 100., &long, 0).qty(),
+            Some(1.)
+        );
+        assert_eq!(
+            risk.check_order(Side::Sell, 4., 100., &exposure(3., 0.), 0)
+                .qty(),
+            Some(4.)
+        );
+        assert_eq!(
+            risk.check_order(Side::Buy, 1., 100., &exposure(5., 0.), 0)
+                .qty(),
+            None
+        );
+
+        // notional caps at 1000 / 200 = 5 units
+        let mut risk = RiskManager::new(
+            100.,
+            RiskConfig {
+                max_notional: Some(1_000.),
+                ..Default::default()
+            },
+        );
+        assert_eq!(
+            risk.check_order(Side::Sell, 8., 200., &Exposure::default(), 0)
+                .qty(),
+            Some(5.)
+        );
+    }
+
+    #[test]
+    fn test_rates() {
+        let config = RiskConfig {
+            max_orders_per_sec: Some(2),
+            max_cancels_per_sec: Some(3),
+            ..Default::default()
+        };
+        let mut risk = RiskManager::new(10., config);
+        let flat = exposure(0., 0.);
+        assert!(risk
+            .check_order(Side::Buy, 1., 100., &flat, 0)
+            .qty()
+            .is_some());
+        assert!(risk
+            .check_order(Side::Buy, 1., 100., &flat, 500)
+            .qty()
+            .is_some());
+        assert!(risk
+            .check_order(Side::Buy, 1., 100., &flat, 900)
+            .qty()
+            .is_none());
+        assert!(risk
+            .check_order(Side::Buy, 1., 100., &flat, 1_000)
+            .qty()
+            .is_some());
+
+        assert!(risk.check_cancels(2, 0));
+        assert!(!risk.check_cancels(2, 10));
+        assert!(risk.check_cancels(3, 1_000));
+    }
+}
@@ -0,0 +1,133 @@
+//! Kill switch and graceful shutdown.
+//!
+//! The live loop stops on SIGINT/SIGTERM, when the net PnL falls `max_drawdown` below its peak
+//! or when no market data arrived for `max_staleness` ms. The strategy then stops quoting,
+//! cancels every tracked order, optionally closes the position and waits for the venue to
+//! confirm before returning a `ShutdownSummary`.
+
+use std::fmt;
+
+use log::warn;
+use serde::Serialize;
+use tokio::sync::mpsc;
+
+use crate::config::ShutdownConfig;
+
+#[derive(Debug, Clone, PartialEq, Serialize)]
+pub enum ShutdownReason {
+    /// SIGINT or SIGTERM
+    Signal(String),
+    /// Net PnL this far below its peak
+    Drawdown(f64),
+    /// No market data for this many ms
+    StaleData(u64),
+}
+
+impl fmt::Display for ShutdownReason {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            ShutdownReason::Signal(signal) => write!(f, "received {}", signal),
+            ShutdownReason::Drawdown(drawdown) => write!(f, "drawdown of {}", drawdown),
+            ShutdownReason::StaleData(ms) => write!(f, "no market data for {}ms", ms),
+        }
+    }
+}
+
+/// State left behind by a shutdown
+#[derive(Debug, Clone, Serialize)]
+pub struct ShutdownSummary {
+    pub reason: ShutdownReason,
+    /// Orders the venue did not confirm as closed before the timeout
+    pub open_orders: usize,
+    pub position: f64,
+    pub realized_pnl: f64,
+    pub net_pnl: f64,
+    /// Whether every cancel, and the close when asked for, was confirmed
+    pub confirmed: bool,
+}
+
+/// Trips on drawdown or stale market data
+#[derive(Debug, Clone)]
+pub struct KillSwitch {
+    config: ShutdownConfig,
+    peak_pnl: f64,
+    last_market_data: Option<u64>,
+}
+
+impl KillSwitch {
+    pub fn new(config: ShutdownConfig) -> Self {
+        KillSwitch {
+            config,
+            peak_pnl: 0.,
+            last_market_data: None,
+        }
+    }
+
+    /// Market data arrived at `now` (ms)
+    pub fn on_market_data(&mut self, now: u64) {
+        self.last_market_data = Some(now);
+    }
+
+    /// Checks the limits against the net PnL at `now` (ms). Staleness only counts once the
+    /// first market data arrived.
+    pub fn check(&mut self, net_pnl: f64, now: u64) -> Option<ShutdownReason> {
+        self.peak_pnl = self.peak_pnl.max(net_pnl);
+        if let Some(max) = self.config.max_drawdown {
+            let drawdown = self.peak_pnl - net_pnl;
+            if drawdown > max {
+                warn!(
+                    "drawdown {} from peak {} over the limit {}",
+                    drawdown, self.peak_pnl, max
+                );
+                return Some(ShutdownReason::Drawdown(drawdown));
+            }
+        }
+        if let (Some(max), Some(last)) = (self.config.max_staleness, self.last_market_data) {
+            let age = now.saturating_sub(last);
+            if age > max {
+                warn!("market data {}ms old, over the limit {}ms", age, max);
+                return Some(ShutdownReason::StaleData(age));
+            }
+        }
+        None
+    }
+}
+
+/// Sends a `ShutdownReason` on the first SIGINT or SIGTERM
+pub async fn forward_signals(shutdown: mpsc::UnboundedSender<ShutdownReason>) {
+    let mut sigterm = tokio::signal::unix::signal(tokio::signal::unix::SignalKind::terminate())
+        .expect("SIGTERM handler should install");
+    let signal = tokio::select! {
+        _ = tokio::signal::ctrl_c() => "SIGINT",
+        _ = sigterm.recv() => "SIGTERM",
+    };
+    warn!("{} received, shutting down", signal);
+    let _ = shutdown.send(ShutdownReason::Signal(signal.to_string()));
+}
+
+#[cfg(test)]
+mod test {
+    use super::*;
+
+    #[test]
+    fn test_kill_switch() {
+        let mut kill_switch = KillSwitch::new(ShutdownConfig {
+            max_drawdown: Some(10.),
+            max_staleness: Some(5_000),
+            ..Default::default()
+        });
+        // stale data only counts after the first update
+        assert_eq!(kill_switch.check(0., 100_000), None);
+        kill_switch.on_market_data(100_000);
+        assert_eq!(kill_switch.check(25., 104_000), None);
+        assert_eq!(kill_switch.check(16., 105_000), None);
+        assert_eq!(
+            kill_switch.check(14., 105_000),
+            Some(ShutdownReason::Drawdown(11.))
+        );
+        assert_eq!(
+            kill_switch.check(25., 106_000),
+            Some(ShutdownReason::StaleData(6_000))
+        );
+    }
+}
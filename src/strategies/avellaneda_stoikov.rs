@@ -1,21 +1,27 @@
 use super::eie::{
-    calibration::aksolver_factory::{AkSolverFactory, SolverType},
+    calibration::{
+        aksolver_factory::{AkSolverFactory, SolverType},
+        diagnostics::AkFilter,
+    },
     intensity_estimator::IntensityEstimator,
     intensity_info::IntensityInfo,
 };
-use crate::{config::Config, util};
+use crate::config::Config;
+use crate::instruments::Instrument;
+use crate::risk::{Exposure, RiskManager};
 
 use anyhow::Result;
 use exrs::binance_f::{
-    account::{FuturesAccount, PositionSide},
-    api::BinanceF,
-    rest_model::TimeInForce,
     util::get_timestamp,
-    ws_model::{AccountUpdateEvent, BookTickerEvent, FuturesWebsocketEvent},
+    ws_model::{AccountUpdateEvent, AggrTradesEvent, BookTickerEvent, FuturesWebsocketEvent},
 };
+use exrs::trading::{self, Side, TradingClient, Venue, VenueConfig};
 use log::{debug, info, warn};
 use std::collections::VecDeque;
+use std::sync::Arc;
 use tokio::sync::mpsc;
+use tokio::sync::Mutex;
+use uuid::Uuid;
 
 #[derive(Debug, Copy, Clone)]
 pub struct Spread {
@@ -97,14 +103,13 @@ pub struct AvellanedaStoikov {
     config: Config,
     start_time: u64,
     timer: u64,
-    account_client: FuturesAccount,
+    account_client: Arc<dyn TradingClient>,
     strategy_data: StrategyData,
     base_asset: String,
     quote_asset: String,
     pair: String,
     order_qty: f64,
-    tick_size: f64,
-    tick_round: u32,
+    instrument: Instrument,
     n_spreads: usize,
     estimate_window: u64,
     period: u64,
@@ -116,6 +121,8 @@ pub struct AvellanedaStoikov {
     buy_k: f64,
     sell_a: f64,
     sell_k: f64,
+    buy_filter: AkFilter,
+    sell_filter: AkFilter,
     position: Position,
     cash: f64,
     total_profit: f64,
@@ -127,30 +134,33 @@ pub struct AvellanedaStoikov {
     trailing_stop: f64,
     active_trailing_stop: bool,
     q_max: f64,
+    risk: Arc<Mutex<RiskManager>>,
 }
 
 impl AvellanedaStoikov {
-    pub fn new(config: Config) -> Box<Self> {
+    /// Prices and quantities are rounded to the tick and lot size of `instrument`
+    pub fn new(config: Config, instrument: Instrument) -> Box<Self> {
         let solver_type = SolverType::LogRegression;
 
         let sf = AkSolverFactory::new(&solver_type);
         let ie = IntensityEstimator::new(
-            config.tick_size.clone(),
+            instrument.tick_size,
             config.n_spreads.clone(),
             config.estimate_window.clone(),
             config.period.clone(),
             sf,
-        );
-
-        let account_client: FuturesAccount =
-            BinanceF::new(config.api_key.clone(), config.secret_key.clone());
+        )
+        .with_fill_source(config.fill_source);
+
+        let account_client: Arc<dyn TradingClient> = trading::connect(&VenueConfig {
+            venue: Venue::BinanceFutures,
+            api_key: config.api_key.clone(),
+            secret_key: config.secret_key.clone(),
+            passphrase: None,
+            is_testnet: false,
+        })
+        .into();
 
-        let tick_round = config
-            .tick_size
-            .to_string()
-            .split(".")
-            .collect::<Vec<&str>>()[1]
-            .len() as u32;
         let pair = format!(
             "{}{}",
             config.base_asset.clone(),
@@ -167,8 +177,7 @@ impl AvellanedaStoikov {
             quote_asset: config.quote_asset,
             pair: pair.clone(),
             order_qty: config.order_qty,
-            tick_size: config.tick_size,
-            tick_round: tick_round,
+            instrument: instrument.clone(),
             n_spreads: config.n_spreads,
             estimate_window: config.estimate_window,
             period: config.period,
@@ -180,6 +189,8 @@ impl AvellanedaStoikov {
             buy_k: 0.2,
             sell_a: 0.4,
             sell_k: 0.2,
+            buy_filter: AkFilter::new(config.calibration.clone(), "buy"),
+            sell_filter: AkFilter::new(config.calibration.clone(), "sell"),
             position: Position {
                 symbol: pair.clone(),
                 position_amount: 0f64,
@@ -195,6 +206,10 @@ impl AvellanedaStoikov {
             trailing_stop: config.trailing_stop,
             active_trailing_stop: false,
             q_max: config.q_max,
+            risk: Arc::new(Mutex::new(
+                RiskManager::new(config.q_max, config.risk.clone())
+                    .with_lot_size(instrument.lot_size),
+            )),
         })
     }
 
@@ -203,17 +218,13 @@ impl AvellanedaStoikov {
     }
 
     pub async fn run_forever(&mut self, mut rx: mpsc::Receiver<FuturesWebsocketEvent>) {
-        let account_balance = self.account_client.account_balance().await.unwrap();
+        let account_balance = self.account_client.balances().await.unwrap();
 
         info!("account_balance: {:?}", account_balance);
 
         let pair = "DOGEUSDT";
 
-        let positions = self
-            .account_client
-            .position_information(pair)
-            .await
-            .unwrap();
+        let positions = self.account_client.positions(pair).await.unwrap();
 
         info!("position_information: {:?}", positions);
 
@@ -238,6 +249,9 @@ impl AvellanedaStoikov {
                         // debug!("book_event: {:?}", book_event);
                         self.on_tick(book_event).await.unwrap();
                     }
+                    FuturesWebsocketEvent::AggrTrades(trade_event) => {
+                        self.on_trade(trade_event);
+                    }
                     FuturesWebsocketEvent::AccountUpdate(account_event) => {
                         // debug!("account_event: {:?}", account_event);
                         self.on_account(account_event).await.unwrap();
@@ -257,6 +271,16 @@ impl AvellanedaStoikov {
         }
     }
 
+    fn on_trade(&mut self, data: Box<AggrTradesEvent>) {
+        if data.symbol != self.pair {
+            return;
+        }
+        match data.price.parse::<f64>() {
+            Ok(price) => self.ie.on_trade(price, data.trade_order_time),
+            Err(e) => warn!("aggTrade price {} dropped: {}", data.price, e),
+        }
+    }
+
     async fn on_tick(&mut self, data: Box<BookTickerEvent>) -> Result<()> {
         debug!("on_ticker: {:?}", data);
         self.strategy_data.push(data.clone());
@@ -264,12 +288,15 @@ impl AvellanedaStoikov {
         if let Some(intensity_info) =
             self.calculate_intensity_info(data.best_ask, data.best_bid, data.transaction_time)
         {
-            let (buy_a, buy_k, sell_a, sell_k) = intensity_info.get_ak();
-
-            self.buy_a = buy_a + std::f64::EPSILON;
-            self.buy_k = buy_k + std::f64::EPSILON;
-            self.sell_a = sell_a + std::f64::EPSILON;
-            self.sell_k = sell_k + std::f64::EPSILON;
+            // the starting values stand in until a fit passes the checks
+            if let Some((a, k)) = self.buy_filter.update(&intensity_info.buy_fit) {
+                self.buy_a = a;
+                self.buy_k = k;
+            }
+            if let Some((a, k)) = self.sell_filter.update(&intensity_info.sell_fit) {
+                self.sell_a = a;
+                self.sell_k = k;
+            }
 
             let spread = self.calculate_spread();
             info!("speard: {:?}", spread);
@@ -305,7 +332,7 @@ impl AvellanedaStoikov {
                     if self.position.position_amount > 0f64 {
                         match self
                             .account_client
-                            .market_sell(&self.pair, self.position.position_amount)
+                            .market_order(&self.pair, Side::Sell, self.position.position_amount)
                             .await
                         {
                             Ok(answer) => info!("Trailing stop market sell {:?}", answer),
@@ -314,7 +341,11 @@ impl AvellanedaStoikov {
                     } else if self.position.position_amount < 0f64 {
                         match self
                             .account_client
-                            .market_buy(&self.pair, self.position.position_amount.abs())
+                            .market_order(
+                                &self.pair,
+                                Side::Buy,
+                                self.position.position_amount.abs(),
+                            )
                             .await
                         {
                             Ok(answer) => info!("Trailing stop market buy {:?}", answer),
@@ -334,15 +365,15 @@ impl AvellanedaStoikov {
                 if self.unrealized_pnl < -self.stoploss {
                     warn!("unrealized_pnl: {:?}, small than stoploss: {:?} stoploss then sleep: {:?}ms", self.unrealized_pnl, self.stoploss, self.stoploss_sleep);
 
-                    match self.account_client.cancel_all_open_orders(&self.pair).await {
-                        Ok(answer) => info!("Cancel all open orders: {:?}", answer),
+                    match self.account_client.cancel_all(&self.pair).await {
+                        Ok(()) => info!("Cancel all open orders"),
                         Err(err) => warn!("Cancel all open orders Error: {:?}", err),
                     }
 
                     if self.position.position_amount > 0f64 {
                         match self
                             .account_client
-                            .market_sell(&self.pair, self.position.position_amount)
+                            .market_order(&self.pair, Side::Sell, self.position.position_amount)
                             .await
                         {
                             Ok(answer) => info!("Stop loss market sell {:?}", answer),
@@ -351,7 +382,11 @@ impl AvellanedaStoikov {
                     } else {
                         match self
                             .account_client
-                            .market_buy(&self.pair, self.position.position_amount.abs())
+                            .market_order(
+                                &self.pair,
+                                Side::Buy,
+                                self.position.position_amount.abs(),
+                            )
                             .await
                         {
                             Ok(answer) => info!("Stop loss market buy {:?}", answer),
@@ -374,15 +409,15 @@ impl AvellanedaStoikov {
                         self.unrealized_pnl, self.stopprofit
                     );
 
-                    match self.account_client.cancel_all_open_orders(&self.pair).await {
-                        Ok(answer) => info!("Cancel all open orders: {:?}", answer),
+                    match self.account_client.cancel_all(&self.pair).await {
+                        Ok(()) => info!("Cancel all open orders"),
                         Err(err) => warn!("Cancel all open orders Error: {:?}", err),
                     }
 
                     if self.position.position_amount > 0f64 {
                         match self
                             .account_client
-                            .market_sell(&self.pair, self.position.position_amount)
+                            .market_order(&self.pair, Side::Sell, self.position.position_amount)
                             .await
                         {
                             Ok(answer) => info!("Stop stopprofit market sell {:?}", answer),
@@ -391,7 +426,11 @@ impl AvellanedaStoikov {
                     } else {
                         match self
                             .account_client
-                            .market_buy(&self.pair, self.position.position_amount.abs())
+                            .market_order(
+                                &self.pair,
+                                Side::Buy,
+                                self.position.position_amount.abs(),
+                            )
                             .await
                         {
                             Ok(answer) => info!("Stop stopprofit market buy {:?}", answer),
@@ -414,51 +453,77 @@ impl AvellanedaStoikov {
                     let last_wap = self.strategy_data.wap.back().unwrap().clone();
                     let pair = self.pair.clone();
                     let order_qty = self.order_qty.clone();
-                    let tick_round = self.tick_round.clone();
+                    let instrument = self.instrument.clone();
+                    let risk = self.risk.clone();
+                    // every open order is canceled before quoting, only the position counts
+                    let mut exposure = Exposure {
+                        position: self.position.position_amount,
+                        working_qty: 0.,
+                        open_orders: 0,
+                        mid_price: (self.strategy_data.ask_price.back().unwrap()
+                            + self.strategy_data.bid_price.back().unwrap())
+                            / 2.,
+                    };
+                    let now = data.transaction_time;
 
                     actix_rt::spawn(async move {
                         debug!("on_ticker thread");
 
-                        match account_client.cancel_all_open_orders(&pair).await {
-                            Ok(answer) => info!("Cancel all open orders: {:?}", answer),
-                            Err(err) => warn!("Cancel all open orders Error: {:?}", err),
+                        // one request cancels every order of the pair
+                        if !risk.lock().await.check_cancels(1, now) {
+                            return;
+                        }
+                        match account_client.cancel_all(&pair).await {
+                            Ok(()) => info!("Cancel all open orders"),
+                            Err(err) => {
+                                // the last quotes may still be open, do not add to them
+                                warn!("Cancel all open orders Error: {:?}", err);
+                                return;
+                            }
                         }
 
-                        let sell_price = util::round_to(last_wap + spread.ask, tick_round);
+                        let sell_price = instrument.round_price(last_wap + spread.ask, Side::Sell);
 
-                        let buy_price = util::round_to(last_wap - spread.bid, tick_round);
+                        let buy_price = instrument.round_price(last_wap - spread.bid, Side::Buy);
 
                         debug!(
                             "wap: {}, ask_spread: {}, bid_spread: {}, sell_price {}, buy_price {}",
                             last_wap, spread.ask, spread.bid, sell_price, buy_price
                         );
 
-                        match account_client
-                            .limit_buy(
-                                &pair,
-                                order_qty,
-                                buy_price,
-                                PositionSide::Both,
-                                TimeInForce::GTC,
-                            )
+                        let qty = risk
+                            .lock()
                             .await
-                        {
-                            Ok(answer) => info!("Limit buy {:?}", answer),
-                            Err(err) => warn!("Limit buy Error: {}", err),
+                            .check_order(Side::Buy, order_qty, buy_price, &exposure, now)
+                            .qty();
+                        if let Some(qty) = qty.and_then(|qty| instrument.validate_qty(qty)) {
+                            let order_id = Uuid::new_v4().to_simple().to_string();
+                            match account_client
+                                .limit_order(&pair, Side::Buy, qty, buy_price, &order_id)
+                                .await
+                            {
+                                Ok(answer) => {
+                                    info!("Limit buy {:?}", answer);
+                                    exposure.open_orders += 1;
+                                }
+                                Err(err) => warn!("Limit buy Error: {}", err),
+                            }
                         }
 
-                        match account_client
-                            .limit_sell(
-                                &pair,
-                                order_qty,
-                                sell_price,
-                                PositionSide::Both,
-                                TimeInForce::GTC,
-                            )
+                        let qty = risk
+                            .lock()
                             .await
-                        {
-                            Ok(answer) => info!("Limit sell {:?}", answer),
-                            Err(err) => warn!("Limit sell Error: {}", err),
+                            .check_order(Side::Sell, order_qty, sell_price, &exposure, now)
+                            .qty();
+                        if let Some(qty) = qty.and_then(|qty| instrument.validate_qty(qty)) {
+                            let order_id = Uuid::new_v4().to_simple().to_string();
+                            match account_client
+                                .limit_order(&pair, Side::Sell, qty, sell_price, &order_id)
+                                .await
+                            {
+                                Ok(answer) => info!("Limit sell {:?}", answer),
+                                Err(err) => warn!("Limit sell Error: {}", err),
+                            }
                         }
                     });
 
@@ -1,32 +1,52 @@
 use crate::config::OkexConfig;
+use crate::instruments::Instrument;
+use crate::oms::OrderManager;
+use crate::portfolio::Portfolio;
+use crate::risk::{Exposure, RiskManager};
+use crate::shutdown::{KillSwitch, ShutdownReason, ShutdownSummary};
 use crate::strategies::eie::{
-    calibration::aksolver_factory::{AkSolverFactory, SolverType},
+    calibration::{aksolver_factory::AkSolverFactory, diagnostics::AkFilter},
     intensity_estimator::IntensityEstimator,
     intensity_info::IntensityInfo,
 };
-use crate::util;
+use crate::strategies::okex_account::{LiveAccount, OkexAccount, OkexRecovery};
 
+use exrs::okex_v5::orderbook::{OrderBook, OrderBooks};
 use exrs::okex_v5::ws_model::OrderBookEvent;
 use exrs::okex_v5::{
     account::Account,
     api::Okex,
     config::Config,
-    rest_model::{OrderCancellation, PositionSide},
+    errors::Result as OkexResult,
+    rest_model::{
+        AlgoCancellation, AlgoOrderDetail, AlgoOrderRequest, AlgoOrderType, AlgoOrdersRequest,
+        MarginMode, MaxSizeRequest, OrderCancellation, OrderSide, PendingOrdersRequest,
+        PositionMode, PositionsRequest, RestResponse, SetLeverageRequest, TradeFeeRequest,
+        TradeMode,
+    },
     util::get_timestamp,
+    websockets::WsTrader,
     ws_model::{
         AccountEvent, BalancePositionEvent, OrderEvent, PositionsEvent, TickerEvent, WebsocketEvent,
     },
 };
+use exrs::supervisor::{ConnectionEvent, ConnectionState};
+use exrs::trading::{self, Side};
 
-use anyhow::Result;
+use anyhow::{bail, Result};
 use log::{debug, info, warn};
-use std::collections::VecDeque;
+use rust_decimal::prelude::ToPrimitive;
+use std::collections::{HashMap, HashSet, VecDeque};
 use std::sync::Arc;
+use std::time::Duration;
 use tokio::sync::mpsc;
 use tokio::sync::Mutex;
-use uuid::adapter::Simple;
+use tokio::task::JoinHandle;
 use uuid::Uuid;
 
+/// How long closed orders are kept after their last update, for the late pushes about them
+const CLOSED_ORDER_RETENTION_MS: u64 = 60_000;
+
 #[derive(Debug, Copy, Clone)]
 pub struct Spread {
     ask: f64,
@@ -63,7 +83,8 @@ impl StrategyData {
         }
     }
 
-    pub fn push(&mut self, event: Box<OrderBookEvent>) {
+    /// Records the top of `book`, which must have both sides
+    pub fn push(&mut self, book: &OrderBook) {
         if self.timestamp.len() > self.capacity - 1 {
             self.timestamp.pop_front();
             self.ask_price.pop_front();
@@ -76,13 +97,14 @@ impl StrategyData {
             self.tv.pop_front();
         }
 
-        let event = &event.data[0];
+        let (best_ask, best_ask_qty) = book.best_ask().unwrap();
+        let (best_bid, best_bid_qty) = book.best_bid().unwrap();
 
-        let transaction_time = event.timestamp;
-        let best_ask = event.asks[0][0].parse().unwrap();
-        let best_ask_qty = event.asks[0][1].parse().unwrap();
-        let best_bid = event.bids[0][0].parse().unwrap();
-        let best_bid_qty = event.bids[0][1].parse().unwrap();
+        let transaction_time = book.timestamp();
+        let best_ask = best_ask.to_f64().unwrap();
+        let best_ask_qty = best_ask_qty.to_f64().unwrap();
+        let best_bid = best_bid.to_f64().unwrap();
+        let best_bid_qty = best_bid_qty.to_f64().unwrap();
 
         self.timestamp.push_back(transaction_time);
         self.ask_price.push_back(best_ask);
@@ -112,33 +134,55 @@ struct Position {
     pub entry_price: f64,
 }
 
-pub struct AvellanedaStoikov {
+/// Algo orders protecting the position on the venue when `venue_stops` is set
+#[derive(Debug, Clone, Default)]
+struct VenueStops {
+    /// Position and entry price the stops were placed for
+    position_amount: f64,
+    entry_price: f64,
+    /// Stop loss and stop profit
+    oco: Vec<String>,
+    /// move_order_stop orders
+    trailing: Vec<String>,
+}
+
+pub struct AvellanedaStoikov<A: OkexAccount = Account> {
     config: OkexConfig,
     start_time: u64,
     timer: u64,
-    account_client: Account,
+    account_client: A,
     strategy_data: StrategyData,
-    opened_order_ids: Arc<Mutex<Vec<Simple>>>,
+    books: OrderBooks,
+    /// Connections currently down, no quotes go out while any is
+    disconnected: HashSet<String>,
+    orders: Arc<Mutex<OrderManager>>,
+    /// Algo orders waiting on the venue by algo id, e.g. stops placed by hand or by another run
+    algo_orders: HashMap<String, AlgoOrderDetail>,
+    venue_stops: VenueStops,
+    risk: Arc<Mutex<RiskManager>>,
+    kill_switch: KillSwitch,
     base_asset: String,
     quote_asset: String,
     pair: String,
     order_qty: f64,
-    tick_size: f64,
-    tick_round: u32,
+    instrument: Instrument,
     n_spreads: usize,
     estimate_window: u64,
     period: u64,
     gamma: f64,
     sigma_multiplier: f64,
     ie: IntensityEstimator,
+    buy_filter: AkFilter,
+    sell_filter: AkFilter,
     sigma: f64,
     buy_a: f64,
     buy_k: f64,
     sell_a: f64,
     sell_k: f64,
     position: Position,
+    /// Cash balance of the base asset reported by the account channel
     cash: f64,
-    total_profit: f64,
+    portfolio: Portfolio,
     stoploss: f64,
     stoploss_sleep: u64,
     stopprofit: f64,
@@ -147,61 +191,303 @@ pub struct AvellanedaStoikov {
     trailing_stop: f64,
     active_trailing_stop: bool,
     q_max: f64,
+    /// Task sending the quotes of the last quote period
+    quoting: Option<JoinHandle<()>>,
 }
 
-impl AvellanedaStoikov {
-    pub fn new(config: OkexConfig) -> Box<Self> {
-        let solver_type = SolverType::LogRegression;
-
-        let sf = AkSolverFactory::new(&solver_type);
-        let ie = IntensityEstimator::new(
-            config.tick_size,
-            config.n_spreads,
-            config.estimate_window,
-            config.period,
-            sf,
-        );
+impl AvellanedaStoikov<LiveAccount> {
+    pub fn new(config: OkexConfig, instrument: Instrument) -> Box<Self> {
         let api_config = Config::new(config.is_testnet);
-        let account_client: Account = Okex::new(
+        let rest: Account = Okex::new(
             config.api_key.clone(),
             config.secret_key.clone(),
             config.passphrase.clone(),
             &api_config,
         );
+        let account_client = LiveAccount { rest, ws: None };
+
+        Self::with_account(config, account_client, instrument, get_timestamp().unwrap())
+    }
+
+    /// Sends the orders and cancels through the private websocket `trader` from now on
+    pub fn set_ws_trader(&mut self, trader: WsTrader) {
+        self.account_client.ws = Some(trader);
+    }
+
+    /// Applies the position mode and leverage of the config, failing when the venue refuses
+    /// them. The fee tier and the max size of the order are only logged, a failed query does
+    /// not stop the startup.
+    pub async fn configure_account(&mut self) -> Result<()> {
+        // quotes, closes and stops carry no posSide, the venue rejects them in long/short mode
+        if self.config.position_mode == Some(PositionMode::LongShortMode) {
+            bail!("long_short_mode is not supported, orders are sent in net mode");
+        }
+        if let Some(pos_mode) = self.config.position_mode {
+            let config = rest_data(
+                "account config",
+                self.account_client.rest.get_account_config().await?,
+            )?;
+            let current = config
+                .first()
+                .map(|c| c.pos_mode.clone())
+                .unwrap_or_default();
+            if serde_json::to_value(pos_mode)? != current.as_str() {
+                info!("switching position mode from {} to {:?}", current, pos_mode);
+                rest_data(
+                    "set position mode",
+                    self.account_client.rest.set_position_mode(pos_mode).await?,
+                )?;
+            }
+        }
+
+        if let Some(lever) = self.config.leverage {
+            let leverage = rest_data(
+                "set leverage",
+                self.account_client
+                    .rest
+                    .set_leverage(SetLeverageRequest {
+                        inst_id: Some(self.pair.clone()),
+                        ccy: None,
+                        lever,
+                        mgn_mode: MarginMode::Cross,
+                        pos_side: None,
+                    })
+                    .await?,
+            )?;
+            info!("leverage: {:?}", leverage);
+        }
+
+        // the fee query needs the instType of the loaded instrument
+        if self.instrument.inst_type.is_empty() {
+            info!("fee tier of {} unknown, instrument not loaded", self.pair);
+        } else {
+            let fees = rest_result(
+                "trade fee",
+                self.account_client
+                    .rest
+                    .get_trade_fee(TradeFeeRequest {
+                        inst_type: self.instrument.inst_type.clone(),
+                        inst_id: Some(self.pair.clone()),
+                        uly: None,
+                    })
+                    .await,
+            );
+            match fees {
+                Ok(fees) => {
+                    for fee in &fees {
+                        info!(
+                            "fee tier {}, maker {}, taker {}",
+                            fee.level, fee.maker, fee.taker
+                        );
+                    }
+                }
+                Err(err) => warn!("trade fee Error: {}", err),
+            }
+        }
+
+        let max_size = rest_result(
+            "max size",
+            self.account_client
+                .rest
+                .get_max_size(MaxSizeRequest {
+                    inst_id: self.pair.clone(),
+                    td_mode: TradeMode::for_inst_id(&self.pair),
+                    ..Default::default()
+                })
+                .await,
+        );
+        match max_size {
+            Ok(max_size) => {
+                for size in &max_size {
+                    let max_buy: f64 = size.max_buy.parse().unwrap_or(0f64);
+                    let max_sell: f64 = size.max_sell.parse().unwrap_or(0f64);
+                    if self.order_qty > max_buy.min(max_sell) {
+                        warn!(
+                            "order qty {} over the max size, buy {}, sell {}",
+                            self.order_qty, max_buy, max_sell
+                        );
+                    }
+                }
+            }
+            Err(err) => warn!("max size Error: {}", err),
+        }
+        Ok(())
+    }
+}
+
+/// Data of a REST response, an error when the venue returned one
+fn rest_data<T>(what: &str, response: RestResponse<T>) -> Result<Vec<T>> {
+    if response.code != 0 {
+        bail!("{} failed, {}: {}", what, response.code, response.msg);
+    }
+    Ok(response.data)
+}
+
+/// Data of a REST call, an error when the request or the venue failed
+fn rest_result<T>(what: &str, response: OkexResult<RestResponse<T>>) -> Result<Vec<T>> {
+    rest_data(what, response?)
+}
+
+impl<A: OkexAccount + OkexRecovery> AvellanedaStoikov<A> {
+    /// Picks up the state a previous run left on the venue, to be called before the first
+    /// quote. Open orders of the pair are taken over by the order manager and canceled unless
+    /// `keep_orphaned_orders` is set, the position and the cash balances are seeded from REST
+    /// instead of waiting for the first push.
+    pub async fn recover(&mut self) -> Result<()> {
+        let pending = rest_data(
+            "orders pending",
+            self.account_client
+                .get_orders_pending(PendingOrdersRequest {
+                    inst_id: Some(self.pair.clone()),
+                    ..Default::default()
+                })
+                .await?,
+        )?;
+        let mut orders = self.orders.lock().await;
+        let restored = pending.iter().filter(|o| orders.restore(o)).count();
+        drop(orders);
+        if restored > 0 {
+            if self.config.keep_orphaned_orders {
+                info!("adopted {} open orders of {}", restored, self.pair);
+            } else {
+                warn!("canceling {} orders left open on {}", restored, self.pair);
+                cancel_open_orders(&self.account_client, &self.orders, &self.pair).await;
+            }
+        }
+
+        // protective exits placed on the venue outlive a restart
+        for ord_type in [
+            AlgoOrderType::Conditional,
+            AlgoOrderType::Oco,
+            AlgoOrderType::MoveOrderStop,
+        ] {
+            let pending = rest_data(
+                "algo orders pending",
+                self.account_client
+                    .get_algo_orders_pending(AlgoOrdersRequest {
+                        inst_id: Some(self.pair.clone()),
+                        ..AlgoOrdersRequest::new(ord_type)
+                    })
+                    .await?,
+            )?;
+            for detail in pending {
+                self.on_algo_order(detail);
+            }
+        }
+        if self.config.venue_stops {
+            // stops of a previous run are replaced by the first sync
+            for order in self.algo_orders.values() {
+                match order.ord_type.as_str() {
+                    "oco" => self.venue_stops.oco.push(order.algo_id.clone()),
+                    "move_order_stop" => self.venue_stops.trailing.push(order.algo_id.clone()),
+                    _ => {}
+                }
+            }
+            self.venue_stops.position_amount = f64::NAN;
+        }
+
+        let positions = rest_data(
+            "positions",
+            self.account_client
+                .get_positions(PositionsRequest {
+                    inst_id: Some(self.pair.clone()),
+                    ..Default::default()
+                })
+                .await?,
+        )?;
+        for position in &positions {
+            self.portfolio.restore_position(position);
+        }
+        self.sync_position();
+
+        let balances = rest_data("balance", self.account_client.get_balance(None).await?)?;
+        for detail in balances.iter().flat_map(|b| &b.details) {
+            self.portfolio.restore_balance(detail);
+            if detail.ccy.eq(&self.base_asset) {
+                self.cash = detail.cash_bal.parse().unwrap_or(0f64);
+            }
+        }
+        self.sync_venue_stops().await;
+
+        info!(
+            "recovered {} orders, cash {:?}, q {:?}",
+            restored, self.cash, self.position.position_amount
+        );
+        Ok(())
+    }
+}
+
+impl<A: OkexAccount> AvellanedaStoikov<A> {
+    /// Builds the strategy on top of any `OkexAccount`, e.g. a simulated one for backtests.
+    /// `start_time` is the millisecond timestamp the intensity estimation window starts from.
+    /// Prices and quantities are rounded to the tick and lot size of `instrument`.
+    pub fn with_account(
+        mut config: OkexConfig,
+        account_client: A,
+        instrument: Instrument,
+        start_time: u64,
+    ) -> Box<Self> {
+        let sf = AkSolverFactory::new(&config.solver);
+        let ie = IntensityEstimator::new(
+            instrument.tick_size,
+            config.n_spreads,
+            config.estimate_window,
+            config.period,
+            sf,
+        )
+        .with_fill_source(config.fill_source);
 
-        let tick_round = config
-            .tick_size
-            .to_string()
-            .split(".")
-            .collect::<Vec<&str>>()[1]
-            .len() as u32;
         let pair = format!(
             "{}-{}",
             config.base_asset.clone(),
             config.quote_asset.clone()
         );
 
-        debug!("pair: {}", pair);
+        debug!("pair: {}, instrument: {:?}", pair, instrument);
+        // spot takes no reduceOnly nor closeFraction, and long/short needs a posSide
+        if config.venue_stops
+            && (matches!(TradeMode::for_inst_id(&pair), TradeMode::Cash)
+                || config.position_mode == Some(PositionMode::LongShortMode))
+        {
+            warn!(
+                "venue stops are not supported on {}, using client stops",
+                pair
+            );
+            config.venue_stops = false;
+        }
+        let mut portfolio = Portfolio::new();
+        portfolio.set_multiplier(&pair, instrument.multiplier);
 
         Box::new(AvellanedaStoikov {
             config: config.clone(),
-            start_time: get_timestamp().unwrap(),
+            start_time,
             timer: 0,
-            account_client: account_client,
+            account_client,
             strategy_data: StrategyData::with_capacity(config.sigma_tick_period),
-            opened_order_ids: Arc::new(Mutex::new(Vec::new())),
+            books: OrderBooks::new(config.book_channel.clone()),
+            disconnected: HashSet::new(),
+            orders: Arc::new(Mutex::new(OrderManager::new())),
+            algo_orders: HashMap::new(),
+            venue_stops: VenueStops::default(),
+            risk: Arc::new(Mutex::new(
+                RiskManager::new(config.q_max, config.risk.clone())
+                    .with_multiplier(instrument.multiplier)
+                    .with_lot_size(instrument.lot_size),
+            )),
+            kill_switch: KillSwitch::new(config.shutdown.clone()),
             base_asset: config.base_asset,
             quote_asset: config.quote_asset,
             pair: pair.clone(),
             order_qty: config.order_qty,
-            tick_size: config.tick_size,
-            tick_round: tick_round,
+            instrument,
             n_spreads: config.n_spreads,
             estimate_window: config.estimate_window,
             period: config.period,
             gamma: config.gamma, // inventory risk aversion parameter, a higher gamma value will result in a more aggressive trading strategy, with larger positions taken in response to market movements.
             sigma_multiplier: config.sigma_multiplier,
             ie: ie,
+            buy_filter: AkFilter::new(config.calibration.clone(), "buy"),
+            sell_filter: AkFilter::new(config.calibration.clone(), "sell"),
             sigma: 1.0,
             // order book liquidity parameters
             buy_a: 0.4,
@@ -214,7 +500,7 @@ impl AvellanedaStoikov {
                 entry_price: 0f64,
             },
             cash: 0f64,
-            total_profit: 0f64,
+            portfolio,
             stoploss: config.stoploss,
             stoploss_sleep: config.stoploss_sleep,
             in_stoploss: false,
@@ -223,6 +509,7 @@ impl AvellanedaStoikov {
             trailing_stop: config.trailing_stop,
             active_trailing_stop: false,
             q_max: config.q_max,
+            quoting: None,
         })
     }
 
@@ -230,78 +517,212 @@ impl AvellanedaStoikov {
         "Avellaneda_Stoikov".into()
     }
 
-    pub async fn run_forever(&mut self, mut rx: mpsc::Receiver<WebsocketEvent>) {
+    /// Lets the order books resubscribe through the public websocket when they get out of sync
+    pub fn set_book_requests(&mut self, requests: mpsc::UnboundedSender<String>) {
+        self.books.set_requests(requests);
+    }
+
+    /// Trades until a signal arrives on `shutdown` or the kill switch trips, then winds down
+    pub async fn run_forever(
+        &mut self,
+        mut rx: mpsc::Receiver<WebsocketEvent>,
+        mut states: mpsc::UnboundedReceiver<ConnectionEvent>,
+        mut shutdown: mpsc::UnboundedReceiver<ShutdownReason>,
+    ) -> ShutdownSummary {
+        // stale data has to be noticed while nothing arrives
+        let mut checks = tokio::time::interval(Duration::from_secs(1));
         loop {
-            if let Some(event) = rx.recv().await {
-                // debug!("Receiving events: {:?}", event);
-                match event {
-                    WebsocketEvent::OrderBook(book_event) => {
-                        debug!("OrderBook: {:?}", book_event);
-                        self.on_orderbook(book_event).await.unwrap();
-                    }
-                    // WebsocketEvent::Ticker(ticker_event) => {
-                    //     debug!("Ticker: {:?}", ticker_event);
-                    //     self.on_tick(ticker_event).await.unwrap();
-                    // }
-                    WebsocketEvent::Account(account_event) => {
-                        debug!("Account: {:?}", account_event);
-                        self.on_account(account_event).await.unwrap();
-                    }
-                    WebsocketEvent::Position(position_event) => {
-                        debug!("Position: {:?}", position_event);
-                        self.on_position(position_event).await.unwrap();
-                    }
-                    WebsocketEvent::BalancePosition(balance_position_event) => {
-                        debug!("BalancePosition: {:?}", balance_position_event);
-                        self.on_balance_position(balance_position_event);
-                    }
-                    WebsocketEvent::Order(order_event) => {
-                        debug!("Order: {:?}", order_event);
-                        self.on_order(order_event);
-                    }
-                    _ => {
-                        warn!("Websockets parse error! {:?}", event);
+            let reason = tokio::select! {
+                Some(event) = rx.recv() => {
+                    if let WebsocketEvent::OrderBook(_) = event {
+                        self.kill_switch.on_market_data(get_timestamp().unwrap());
                     }
+                    self.on_event(event).await;
+                    self.kill_switch
+                        .check(self.portfolio.net_pnl(), get_timestamp().unwrap())
+                }
+                Some(state) = states.recv() => {
+                    self.on_connection(state).await;
+                    None
                 }
+                Some(reason) = shutdown.recv() => Some(reason),
+                _ = checks.tick() => self
+                    .kill_switch
+                    .check(self.portfolio.net_pnl(), get_timestamp().unwrap()),
+            };
+            if let Some(reason) = reason {
+                return self.shutdown(&mut rx, reason).await;
             }
             actix_rt::task::yield_now().await;
         }
     }
 
-    async fn on_position(&mut self, event: Box<PositionsEvent>) -> Result<()> {
-        info!("on_position: {:?}", event);
-        let data = &event.data;
+    /// Stops quoting, cancels every tracked order, closes the position when configured to and
+    /// waits for the orders channel to confirm. Order book events are dropped from here on.
+    pub async fn shutdown(
+        &mut self,
+        rx: &mut mpsc::Receiver<WebsocketEvent>,
+        reason: ShutdownReason,
+    ) -> ShutdownSummary {
+        warn!("shutting down, {}", reason);
+        self.cancel_open_orders().await;
+        let flatten = self.config.shutdown.flatten;
+        if flatten && self.position.position_amount != 0f64 {
+            match self.account_client.close_position(&self.pair).await {
+                Ok(()) => info!("Shutdown close position"),
+                Err(err) => warn!("Shutdown close position Error: {}", err),
+            }
+        }
 
-        // for detail in &data.details {
-        //     if detail.ccy.eq(&self.base_asset) {
-        //         self.cash = detail.cash_bal;
-        //     }
-        // }
+        let deadline = tokio::time::Instant::now()
+            + Duration::from_millis(self.config.shutdown.confirm_timeout);
+        while !self.wound_down(flatten).await {
+            match tokio::time::timeout_at(deadline, rx.recv()).await {
+                Ok(Some(WebsocketEvent::OrderBook(_))) => {}
+                Ok(Some(event)) => {
+                    self.on_event(event).await;
+                    // orders still pending new when the cancels went out can be canceled now
+                    self.cancel_open_orders().await;
+                }
+                Ok(None) | Err(_) => break,
+            }
+        }
 
-        let tmp_q = data
-            .iter()
-            .find(|&x| x.inst_id.eq(&self.pair))
-            .and_then(|x| {
-                Some(
-                    x.pos
-                        .parse::<f64>()
-                        .unwrap_or_else(|_| self.position.position_amount),
-                )
-            });
-
-        let entry_price = data
-            .iter()
-            .find(|&x| x.inst_id.eq(&self.pair))
-            .and_then(|x| {
-                Some(
-                    x.avg_px
-                        .parse::<f64>()
-                        .unwrap_or_else(|_| self.position.entry_price),
-                )
-            });
-
-        self.position.entry_price = entry_price.unwrap_or_else(|| self.position.entry_price);
-        self.position.position_amount = tmp_q.unwrap_or_else(|| self.position.position_amount);
+        let summary = ShutdownSummary {
+            reason,
+            open_orders: self.orders.lock().await.open_orders(&self.pair).count(),
+            position: self.position.position_amount,
+            realized_pnl: self.portfolio.realized_pnl(),
+            net_pnl: self.portfolio.net_pnl(),
+            confirmed: self.wound_down(flatten).await,
+        };
+        info!("shutdown summary: {:?}", summary);
+        summary
+    }
+
+    async fn wound_down(&self, flatten: bool) -> bool {
+        let no_orders = self.orders.lock().await.open_orders(&self.pair).count() == 0;
+        no_orders && (!flatten || self.position.position_amount == 0f64)
+    }
+
+    /// Pulls the quotes when a websocket drops and resumes quoting once every connection
+    /// is back
+    pub async fn on_connection(&mut self, event: ConnectionEvent) {
+        match event.state {
+            ConnectionState::Connected => {
+                if self.disconnected.remove(&event.name) {
+                    info!(
+                        "{} back, {} still down",
+                        event.name,
+                        self.disconnected.len()
+                    );
+                }
+            }
+            ConnectionState::Disconnected => {
+                if self.disconnected.insert(event.name.clone()) {
+                    warn!("{} down, pulling quotes", event.name);
+                    self.cancel_open_orders().await;
+                }
+            }
+            ConnectionState::Connecting => {}
+        }
+    }
+
+    async fn cancel_open_orders(&self) {
+        cancel_open_orders(&self.account_client, &self.orders, &self.pair).await;
+    }
+
+    /// Waits for the quoting task of the last quote period. The backtest engine calls it after
+    /// every event so that the replay does not depend on how the tasks get scheduled.
+    pub async fn settle(&mut self) {
+        if let Some(task) = self.quoting.take() {
+            if let Err(e) = task.await {
+                warn!("quoting task failed: {}", e);
+            }
+        }
+    }
+
+    /// Order state shared with the quoting tasks
+    pub fn orders(&self) -> Arc<Mutex<OrderManager>> {
+        self.orders.clone()
+    }
+
+    /// Positions and PnL built from the fills
+    pub fn portfolio(&self) -> &Portfolio {
+        &self.portfolio
+    }
+
+    fn sync_position(&mut self) {
+        if let Some(holding) = self.portfolio.holding(&self.pair) {
+            self.position.position_amount = holding.qty;
+            self.position.entry_price = holding.avg_entry;
+        }
+    }
+
+    /// Dispatches a single websocket event, used by both `run_forever` and the backtest engine.
+    pub async fn on_event(&mut self, event: WebsocketEvent) {
+        // debug!("Receiving events: {:?}", event);
+        match event {
+            WebsocketEvent::OrderBook(book_event) => {
+                debug!("OrderBook: {:?}", book_event);
+                self.on_orderbook(book_event).await.unwrap();
+            }
+            WebsocketEvent::Trades(trades_event) => {
+                debug!("Trades: {:?}", trades_event);
+                for trade in trades_event.data.iter().filter(|t| t.inst_id == self.pair) {
+                    self.ie.on_trade(trade.px, trade.timestamp);
+                }
+            }
+            // WebsocketEvent::Ticker(ticker_event) => {
+            //     debug!("Ticker: {:?}", ticker_event);
+            //     self.on_tick(ticker_event).await.unwrap();
+            // }
+            WebsocketEvent::Account(account_event) => {
+                debug!("Account: {:?}", account_event);
+                self.on_account(account_event).await.unwrap();
+            }
+            WebsocketEvent::Position(position_event) => {
+                debug!("Position: {:?}", position_event);
+                self.on_position(position_event).await.unwrap();
+            }
+            WebsocketEvent::BalancePosition(balance_position_event) => {
+                debug!("BalancePosition: {:?}", balance_position_event);
+                self.on_balance_position(balance_position_event).await;
+            }
+            WebsocketEvent::Order(order_event) => {
+                debug!("Order: {:?}", order_event);
+                self.on_order(order_event).await;
+            }
+            WebsocketEvent::AlgoOrders(algo_event) => {
+                debug!("AlgoOrders: {:?}", algo_event);
+                for order in &algo_event.data {
+                    self.on_algo_order(order.into());
+                }
+            }
+            WebsocketEvent::AdvanceAlgoOrdersEvent(algo_event) => {
+                debug!("AdvanceAlgoOrders: {:?}", algo_event);
+                for order in &algo_event.data {
+                    self.on_algo_order(order.into());
+                }
+            }
+            _ => {
+                warn!("Websockets parse error! {:?}", event);
+            }
+        }
+    }
+
+    async fn on_position(&mut self, event: Box<PositionsEvent>) -> Result<()> {
+        info!("on_position: {:?}", event);
+        for position in event.data.iter().filter(|x| x.inst_id.eq(&self.pair)) {
+            // the grace period runs on the venue's clock, which is the replayed one in backtests
+            let now = match position.u_time.parse() {
+                Ok(u_time) => u_time,
+                Err(_) => get_timestamp()?,
+            };
+            let reconciliation = self.portfolio.reconcile(position, now);
+            debug!("{} reconciliation: {:?}", self.pair, reconciliation);
+        }
+        self.sync_position();
 
         info!(
             "cash {:?}, q {:?}",
@@ -310,43 +731,239 @@ impl AvellanedaStoikov {
         Ok(())
     }
 
-    async fn on_order(&mut self, event: Box<OrderEvent>) {}
+    async fn on_order(&mut self, event: Box<OrderEvent>) {
+        let mut orders = self.orders.lock().await;
+        let mut now: u64 = 0;
+        for update in &event.data {
+            now = now.max(update.u_time.parse().unwrap_or(0));
+            if let Some(fill) = orders.on_order_update(update) {
+                let realized = self.portfolio.on_fill(&fill);
+                info!("fill: {:?}, realized {}", fill, realized);
+            }
+        }
+        orders.purge_closed(now.saturating_sub(CLOSED_ORDER_RETENTION_MS));
+        drop(orders);
+        self.sync_position();
+        self.sync_venue_stops().await;
+    }
 
-    async fn on_balance_position(&mut self, event: Box<BalancePositionEvent>) {}
+    /// Keeps the algo orders of the pair that are still waiting, a triggered one shows up as a
+    /// regular order on the orders channel
+    fn on_algo_order(&mut self, order: AlgoOrderDetail) {
+        if order.inst_id != self.pair {
+            return;
+        }
+        match order.state.as_str() {
+            "live" | "partially_effective" | "pause" => {
+                self.algo_orders.insert(order.algo_id.clone(), order);
+            }
+            state => {
+                info!(
+                    "{} algo order {} {} at {}",
+                    order.ord_type, order.algo_id, state, order.actual_px
+                );
+                self.algo_orders.remove(&order.algo_id);
+                // the venue hit the stop loss, sleep as if it was hit here
+                if state == "effective"
+                    && order.actual_side == "sl"
+                    && self.venue_stops.oco.contains(&order.algo_id)
+                {
+                    warn!("venue stop loss {} triggered", order.algo_id);
+                    self.in_stoploss = true;
+                    self.active_trailing_stop = false;
+                    self.timer = order.trigger_time.parse().unwrap_or(0) / 1000;
+                }
+            }
+        }
+    }
+
+    /// Places the stop loss and stop profit as an oco and the trailing stop as a
+    /// move_order_stop closing the position on the venue. They are replaced whenever the
+    /// position or its entry price changes and canceled once it is flat.
+    async fn sync_venue_stops(&mut self) {
+        if !self.config.venue_stops {
+            return;
+        }
+        let position_amount = self.position.position_amount;
+        let entry_price = self.position.entry_price;
+        if self.venue_stops.position_amount == position_amount
+            && self.venue_stops.entry_price == entry_price
+        {
+            return;
+        }
+        if !self.cancel_venue_stops().await || position_amount == 0f64 {
+            return;
+        }
+
+        // (side closing the position, 1 for a long and -1 for a short)
+        let (side, sign) = if position_amount > 0f64 {
+            (Side::Sell, 1f64)
+        } else {
+            (Side::Buy, -1f64)
+        };
+        let order_side = match side {
+            Side::Buy => OrderSide::Buy,
+            Side::Sell => OrderSide::Sell,
+        };
+        // same trade mode and net position as the quotes
+        let stop = AlgoOrderRequest {
+            symbol: self.pair.clone(),
+            trade_mode: TradeMode::for_inst_id(&self.pair),
+            side: order_side,
+            position_side: None,
+            reduce_only: Some(true),
+            ..Default::default()
+        };
+        // an order price of -1 closes at market
+        let oco = AlgoOrderRequest {
+            order_type: AlgoOrderType::Oco,
+            close_fraction: Some(1f64),
+            sl_trigger_px: Some(
+                self.instrument
+                    .round_price(entry_price * (1f64 - sign * self.stoploss), side),
+            ),
+            sl_ord_px: Some(-1f64),
+            tp_trigger_px: Some(
+                self.instrument
+                    .round_price(entry_price * (1f64 + sign * self.stopprofit), side),
+            ),
+            tp_ord_px: Some(-1f64),
+            ..stop.clone()
+        };
+        // trails by `trailing_stop` once the position made as much
+        let trailing = AlgoOrderRequest {
+            order_type: AlgoOrderType::MoveOrderStop,
+            qty: Some(position_amount.abs()),
+            callback_ratio: Some(self.trailing_stop),
+            active_px: Some(
+                self.instrument
+                    .round_price(entry_price * (1f64 + sign * self.trailing_stop), side),
+            ),
+            ..stop
+        };
+
+        let mut placed = true;
+        for (order, ids) in [
+            (oco, &mut self.venue_stops.oco),
+            (trailing, &mut self.venue_stops.trailing),
+        ] {
+            let answer = self.account_client.place_algo_order(order).await;
+            match rest_result("venue stop", answer) {
+                Ok(data) => {
+                    info!("venue stop {:?}", data);
+                    ids.extend(data.into_iter().map(|tx| tx.algo_id));
+                }
+                Err(err) => {
+                    warn!("venue stop Error: {}", err);
+                    placed = false;
+                }
+            }
+        }
+        // the next sync cancels what went through and tries again
+        if placed {
+            self.venue_stops.position_amount = position_amount;
+            self.venue_stops.entry_price = entry_price;
+        }
+    }
+
+    /// Cancels the venue stops, false when they may still be live
+    async fn cancel_venue_stops(&mut self) -> bool {
+        let cancellations = |ids: &[String]| {
+            ids.iter()
+                .map(|id| AlgoCancellation {
+                    algo_id: id.clone(),
+                    inst_id: self.pair.clone(),
+                })
+                .collect::<Vec<_>>()
+        };
+        if !self.venue_stops.oco.is_empty() {
+            let orders = cancellations(&self.venue_stops.oco);
+            let answer = self.account_client.cancel_algo_orders(orders).await;
+            match rest_result("cancel stops", answer) {
+                Ok(data) => info!("cancel stops {:?}", data),
+                Err(err) => {
+                    warn!("cancel stops Error: {}", err);
+                    return false;
+                }
+            }
+            self.venue_stops.oco.clear();
+        }
+        if !self.venue_stops.trailing.is_empty() {
+            let orders = cancellations(&self.venue_stops.trailing);
+            let answer = self.account_client.cancel_advance_algo_orders(orders).await;
+            match rest_result("cancel stops", answer) {
+                Ok(data) => info!("cancel trailing stops {:?}", data),
+                Err(err) => {
+                    warn!("cancel trailing stops Error: {}", err);
+                    return false;
+                }
+            }
+            self.venue_stops.trailing.clear();
+        }
+        self.venue_stops.position_amount = 0f64;
+        self.venue_stops.entry_price = 0f64;
+        true
+    }
+
+    /// Algo orders of the pair waiting on the venue
+    pub fn algo_orders(&self) -> impl Iterator<Item = &AlgoOrderDetail> {
+        self.algo_orders.values()
+    }
+
+    async fn on_balance_position(&mut self, event: Box<BalancePositionEvent>) {
+        for balance_position in &event.data {
+            self.portfolio.on_balance_position(balance_position);
+        }
+    }
 
     async fn on_tick(&mut self, event: Box<TickerEvent>) {}
 
     async fn on_orderbook(&mut self, event: Box<OrderBookEvent>) -> Result<()> {
         // debug!("on_orderbook: {:?}", event);
-        self.strategy_data.push(event.clone());
-        let data = &event.data[0];
+        match self.books.on_event(&event) {
+            Ok(inst_ids) if inst_ids.contains(&self.pair) => {}
+            Ok(_) => return Ok(()),
+            Err(e) => {
+                warn!("order book dropped: {}", e);
+                return Ok(());
+            }
+        }
+        let book = self.books.book(&self.pair).unwrap();
+        if book.best_bid().is_none() || book.best_ask().is_none() {
+            return Ok(());
+        }
+        let timestamp = book.timestamp();
+        self.strategy_data.push(book);
 
         if let Some(intensity_info) = self.calculate_intensity_info(
-            data.asks[0][0].parse().unwrap(),
-            data.bids[0][0].parse().unwrap(),
-            data.timestamp,
+            *self.strategy_data.ask_price.back().unwrap(),
+            *self.strategy_data.bid_price.back().unwrap(),
+            timestamp,
         ) {
-            let (buy_a, buy_k, sell_a, sell_k) = intensity_info.get_ak();
-
-            self.buy_a = buy_a + std::f64::EPSILON;
-            self.buy_k = buy_k + std::f64::EPSILON;
-            self.sell_a = sell_a + std::f64::EPSILON;
-            self.sell_k = sell_k + std::f64::EPSILON;
+            // the starting values stand in until a fit passes the checks
+            if let Some((a, k)) = self.buy_filter.update(&intensity_info.buy_fit) {
+                self.buy_a = a;
+                self.buy_k = k;
+            }
+            if let Some((a, k)) = self.sell_filter.update(&intensity_info.sell_fit) {
+                self.sell_a = a;
+                self.sell_k = k;
+            }
 
             let spread = self.calculate_spread();
             info!("speard: {:?}", spread);
 
             if !self.in_stoploss {
-                if self.position.position_amount > 0f64 {
-                    self.unrealized_pnl = (self.strategy_data.bid_price.back().unwrap()
-                        * self.position.position_amount)
-                        / (self.position.entry_price * self.position.position_amount)
-                        - 1f64;
-                } else if self.position.position_amount < 0f64 {
-                    self.unrealized_pnl = -((self.strategy_data.ask_price.back().unwrap()
-                        * self.position.position_amount)
-                        / (self.position.entry_price * self.position.position_amount)
-                        - 1f64);
+                // longs close at the bid, shorts at the ask
+                let mark_price = if self.position.position_amount > 0f64 {
+                    *self.strategy_data.bid_price.back().unwrap()
+                } else {
+                    *self.strategy_data.ask_price.back().unwrap()
+                };
+                self.portfolio.mark(&self.pair, mark_price);
+                if self.position.position_amount != 0f64 {
+                    self.unrealized_pnl =
+                        self.portfolio.holding(&self.pair).unwrap().unrealized_pct();
                 }
 
                 info!(
@@ -357,29 +974,25 @@ impl AvellanedaStoikov {
                     self.stopprofit
                 );
 
-                if self.unrealized_pnl > self.trailing_stop
-                    && (self.timer <= data.timestamp / 1e3 as u64 - (10000 / 1000))
+                // with venue stops the exits are algo orders on the venue instead
+                let client_stops = !self.config.venue_stops;
+
+                if client_stops
+                    && self.unrealized_pnl > self.trailing_stop
+                    && (self.timer <= timestamp / 1e3 as u64 - (10000 / 1000))
                 {
                     self.active_trailing_stop = true;
                 }
 
                 if self.active_trailing_stop && (self.unrealized_pnl < self.trailing_stop) {
                     if self.position.position_amount > 0f64 {
-                        match self
-                            .account_client
-                            .close_position(&self.pair, Some(PositionSide::Net))
-                            .await
-                        {
-                            Ok(answer) => info!("Trailing stop market sell {:?}", answer),
+                        match self.account_client.close_position(&self.pair).await {
+                            Ok(()) => info!("Trailing stop market sell"),
                             Err(err) => warn!("Trailing stop market sell Error: {}", err),
                         }
                     } else if self.position.position_amount < 0f64 {
-                        match self
-                            .account_client
-                            .close_position(&self.pair, Some(PositionSide::Net))
-                            .await
-                        {
-                            Ok(answer) => info!("Trailing stop market buy {:?}", answer),
+                        match self.account_client.close_position(&self.pair).await {
+                            Ok(()) => info!("Trailing stop market buy"),
                             Err(err) => warn!("Trailing stop market buy Error: {}", err),
                         }
                     } else {
@@ -390,41 +1003,22 @@ impl AvellanedaStoikov {
 
                     self.active_trailing_stop = false;
 
-                    self.timer = data.timestamp / 1e3 as u64;
+                    self.timer = timestamp / 1e3 as u64;
                 }
 
-                if self.unrealized_pnl < -self.stoploss {
+                if client_stops && self.unrealized_pnl < -self.stoploss {
                     warn!("unrealized_pnl: {:?}, small than stoploss: {:?} stoploss then sleep: {:?}ms", self.unrealized_pnl, self.stoploss, self.stoploss_sleep);
 
-                    let mut ids = self.opened_order_ids.lock().await;
-                    if !ids.is_empty() {
-                        let orders = create_order_cancellation(&self.pair, ids.clone())?;
-
-                        match self.account_client.cancel_all_open_orders(orders).await {
-                            Ok(answer) => {
-                                info!("Cancel all open orders: {:?}", answer);
-                                ids.clear();
-                            }
-                            Err(err) => warn!("Cancel all open orders Error: {:?}", err),
-                        }
-                    }
+                    self.cancel_open_orders().await;
 
                     if self.position.position_amount > 0f64 {
-                        match self
-                            .account_client
-                            .close_position(&self.pair, Some(PositionSide::Net))
-                            .await
-                        {
-                            Ok(answer) => info!("Stop loss market sell {:?}", answer),
+                        match self.account_client.close_position(&self.pair).await {
+                            Ok(()) => info!("Stop loss market sell"),
                             Err(err) => warn!("Stop loss market sell Error: {}", err),
                         }
                     } else {
-                        match self
-                            .account_client
-                            .close_position(&self.pair, Some(PositionSide::Net))
-                            .await
-                        {
-                            Ok(answer) => info!("Stop loss market buy {:?}", answer),
+                        match self.account_client.close_position(&self.pair).await {
+                            Ok(()) => info!("Stop loss market buy"),
                             Err(err) => warn!("Stop loss market buy Error: {}", err),
                         }
                     }
@@ -435,58 +1029,44 @@ impl AvellanedaStoikov {
 
                     self.active_trailing_stop = false;
 
-                    self.timer = data.timestamp / 1e3 as u64;
-                } else if (self.unrealized_pnl > self.stopprofit)
-                    && (self.timer <= data.timestamp / 1e3 as u64 - (self.period / 1000))
+                    self.timer = timestamp / 1e3 as u64;
+                } else if client_stops
+                    && (self.unrealized_pnl > self.stopprofit)
+                    && (self.timer <= timestamp / 1e3 as u64 - (self.period / 1000))
                 {
                     warn!(
                         "unrealized_pnl: {:?}, bigger than stopprofit: {:?}",
                         self.unrealized_pnl, self.stopprofit
                     );
 
-                    let mut ids = self.opened_order_ids.lock().await;
-                    if !ids.is_empty() {
-                        let orders = create_order_cancellation(&self.pair, ids.clone())?;
-
-                        match self.account_client.cancel_all_open_orders(orders).await {
-                            Ok(answer) => {
-                                info!("Cancel all open orders: {:?}", answer);
-                                ids.clear();
-                            }
-                            Err(err) => warn!("Cancel all open orders Error: {:?}", err),
-                        }
-                    }
+                    self.cancel_open_orders().await;
 
                     if self.position.position_amount > 0f64 {
-                        match self
-                            .account_client
-                            .close_position(&self.pair, Some(PositionSide::Net))
-                            .await
-                        {
-                            Ok(answer) => info!("Stop stopprofit market sell {:?}", answer),
+                        match self.account_client.close_position(&self.pair).await {
+                            Ok(()) => info!("Stop stopprofit market sell"),
                             Err(err) => warn!("Stop stopprofit market sell Error: {}", err),
                         }
                     } else {
-                        match self
-                            .account_client
-                            .close_position(&self.pair, Some(PositionSide::Net))
-                            .await
-                        {
-                            Ok(answer) => info!("Stop stopprofit market buy {:?}", answer),
+                        match self.account_client.close_position(&self.pair).await {
+                            Ok(()) => info!("Stop stopprofit market buy"),
                             Err(err) => warn!("Stop stopprofit market buy Error: {}", err),
                         }
                     }
 
                     self.unrealized_pnl = 0f64;
 
-                    self.timer = data.timestamp / 1e3 as u64;
-                } else if self.timer <= data.timestamp / 1e3 as u64 - (self.period / 1000) {
+                    self.timer = timestamp / 1e3 as u64;
+                } else if self.disconnected.is_empty()
+                    && self.timer <= timestamp / 1e3 as u64 - (self.period / 1000)
+                {
                     debug!(
                         "timer: {}, now - {} = {}",
                         self.timer,
                         (self.period / 1000),
-                        data.timestamp / 1e3 as u64 - 2
+                        timestamp / 1e3 as u64 - 2
                     );
+                    // retries stops that failed to go through
+                    self.sync_venue_stops().await;
 
                     let account_client = self.account_client.clone();
                     let last_wap = self.strategy_data.wap.back().unwrap().clone();
@@ -494,32 +1074,33 @@ impl AvellanedaStoikov {
                     let bid_price = self.strategy_data.bid_price.back().unwrap().clone();
                     let pair = self.pair.clone();
                     let order_qty = self.order_qty.clone();
-                    let tick_round = self.tick_round.clone();
-                    let opened_order_ids = self.opened_order_ids.clone();
+                    let instrument = self.instrument.clone();
+                    let orders = self.orders.clone();
+                    let risk = self.risk.clone();
+                    let position = self.position.position_amount;
 
-                    actix_rt::spawn(async move {
+                    self.quoting = Some(actix_rt::spawn(async move {
                         debug!("on_ticker thread");
 
-                        let mut ids = opened_order_ids.lock().await;
-                        if !ids.is_empty() {
-                            let orders = create_order_cancellation(&pair, ids.clone()).unwrap();
-
-                            match &account_client.cancel_all_open_orders(orders).await {
-                                Ok(answer) => {
-                                    info!("Cancel all open orders: {:?}", answer);
-                                    ids.clear();
-                                }
-                                Err(err) => warn!("Cancel all open orders Error: {:?}", err),
-                            }
+                        // the locks are never held across a request, the orders channel
+                        // and the other tasks keep going while it is in flight
+                        let cancels = orders.lock().await.cancelable_ids(&pair).len();
+                        if !risk.lock().await.check_cancels(cancels, timestamp) {
+                            return;
+                        }
+                        if !cancel_open_orders(&account_client, &orders, &pair).await {
+                            return;
                         }
 
-                        let mut sell_price = util::round_to(last_wap + spread.ask, tick_round);
+                        let mut sell_price =
+                            instrument.round_price(last_wap + spread.ask, Side::Sell);
                         if spread.ask < 0. {
                             sell_price = ask_price;
                         };
                         // let mut buy_price = util::round_to(last_wap, tick_round);
                         // In original strategy, Bid offer price = reservation price - optimal spread / 2
-                        let mut buy_price = util::round_to(last_wap - spread.bid, tick_round);
+                        let mut buy_price =
+                            instrument.round_price(last_wap - spread.bid, Side::Buy);
                         debug!(
                             "buy_price:{}, last_wap:{}, spread.bid:{}",
                             buy_price, last_wap, spread.bid
@@ -534,53 +1115,59 @@ impl AvellanedaStoikov {
                             last_wap, spread.ask, spread.bid, sell_price, buy_price
                         );
 
-                        let order_id = Uuid::new_v4().to_simple();
-
-                        match account_client
-                            .limit_buy(
-                                &pair,
-                                order_qty,
-                                buy_price,
-                                PositionSide::Net,
-                                &order_id.to_string(),
-                            )
-                            .await
-                        {
-                            Ok(answer) => {
-                                info!("Limit buy {:?}", answer);
-                                if answer.code == 0 {
-                                    ids.push(order_id);
+                        let mid_price = (ask_price + bid_price) / 2.;
+                        for (side, price) in [(Side::Buy, buy_price), (Side::Sell, sell_price)] {
+                            let order_id = Uuid::new_v4().to_simple().to_string();
+                            let qty = {
+                                let mut orders = orders.lock().await;
+                                let exposure = Exposure {
+                                    position,
+                                    working_qty: orders.working_qty(&pair, side),
+                                    open_orders: orders.open_orders(&pair).count(),
+                                    mid_price,
+                                };
+                                let qty = match risk
+                                    .lock()
+                                    .await
+                                    .check_order(side, order_qty, price, &exposure, timestamp)
+                                    .qty()
+                                    .and_then(|qty| instrument.validate_qty(qty))
+                                {
+                                    Some(qty) => qty,
+                                    None => continue,
+                                };
+                                orders.on_new(&pair, &order_id, side, price, qty, timestamp);
+                                qty
+                            };
+
+                            let answer = account_client
+                                .limit_order(&pair, side, qty, price, &order_id)
+                                .await;
+                            let mut orders = orders.lock().await;
+                            match answer {
+                                Ok(ack) => {
+                                    info!("Limit {:?} {:?}", side, ack);
+                                    orders.on_place_ack(&ack);
                                 }
-                            }
-                            Err(err) => warn!("Limit buy Error: {}", err),
-                        }
-
-                        let order_id = Uuid::new_v4().to_simple();
-
-                        match account_client
-                            .limit_sell(
-                                &pair,
-                                order_qty,
-                                sell_price,
-                                PositionSide::Net,
-                                &order_id.to_string(),
-                            )
-                            .await
-                        {
-                            Ok(answer) => {
-                                info!("Limit sell {:?}", answer);
-                                if answer.code == 0 {
-                                    ids.push(order_id);
+                                Err(trading::Error::Rejected { code, msg }) => {
+                                    warn!("Limit {:?} rejected, {}: {}", side, code, msg);
+                                    orders.on_place_rejected(
+                                        &order_id,
+                                        &format!("{}: {}", code, msg),
+                                    );
+                                }
+                                Err(err) => {
+                                    warn!("Limit {:?} Error: {}", side, err);
+                                    orders.on_place_failed(&order_id, &err.to_string());
                                 }
                             }
-                            Err(err) => warn!("Limit sell Error: {}", err),
                         }
-                    });
+                    }));
 
-                    self.timer = data.timestamp / 1e3 as u64;
+                    self.timer = timestamp / 1e3 as u64;
                     debug!("new timer {}", self.timer);
                 }
-            } else if self.timer <= data.timestamp / 1e3 as u64 - (self.stoploss_sleep / 1000) {
+            } else if self.timer <= timestamp / 1e3 as u64 - (self.stoploss_sleep / 1000) {
                 self.in_stoploss = false;
                 info!("stoploss sleep finished!");
             } else {
@@ -596,27 +1183,13 @@ impl AvellanedaStoikov {
         info!("on_account: {:?}", event);
         let data = &event.data[0];
 
+        // the position comes from the fills and the positions channel, not the equity
         for detail in &data.details {
             if detail.ccy.eq(&self.base_asset) {
                 self.cash = detail.cash_bal;
             }
         }
 
-        let tmp_q = data
-            .details
-            .iter()
-            .find(|&x| x.ccy.eq(&self.base_asset))
-            .and_then(|x| Some(x.eq));
-
-        let entry_price = data
-            .details
-            .iter()
-            .find(|&x| x.ccy.eq(&self.base_asset))
-            .and_then(|x| Some(x.eq_usd / x.eq));
-
-        self.position.entry_price = entry_price.unwrap_or_else(|| self.position.entry_price);
-        self.position.position_amount = tmp_q.unwrap_or_else(|| self.position.position_amount);
-
         info!(
             "cash {:?}, q {:?}",
             self.cash, self.position.position_amount
@@ -742,20 +1315,433 @@ impl AvellanedaStoikov {
     }
 }
 
+/// Orders the manager keys by their venue id, the ones placed without a client id, are
+/// canceled by that id
 fn create_order_cancellation(
+    orders: &OrderManager,
     symbol: &str,
-    opened_order_ids: Vec<Simple>,
-) -> Result<Vec<OrderCancellation>> {
-    let mut batch = Vec::new();
-    for client_order_id in opened_order_ids {
-        let oc = OrderCancellation {
-            symbol: symbol.to_string(),
-            order_id: None,
-            orig_client_order_id: Some(client_order_id.to_string()),
-        };
+    ids: &[String],
+) -> Vec<OrderCancellation> {
+    ids.iter()
+        .map(|id| {
+            let by_order_id = orders
+                .order(id)
+                .is_some_and(|o| o.order_id.as_deref() == Some(id.as_str()));
+            OrderCancellation {
+                symbol: symbol.to_string(),
+                order_id: by_order_id.then(|| id.clone()),
+                orig_client_order_id: (!by_order_id).then(|| id.clone()),
+            }
+        })
+        .collect()
+}
+
+/// Cancels the open orders of `pair`, they stay pending cancel until the orders channel
+/// confirms. `orders` is unlocked while the request is in flight. False when the request
+/// failed and the orders may still be live.
+async fn cancel_open_orders<A: OkexAccount>(
+    account_client: &A,
+    orders: &Mutex<OrderManager>,
+    pair: &str,
+) -> bool {
+    let (ids, cancellations) = {
+        let mut orders = orders.lock().await;
+        let ids = orders.cancelable_ids(pair);
+        let cancellations = create_order_cancellation(&orders, pair, &ids);
+        for id in &ids {
+            orders.on_cancel_request(id);
+        }
+        (ids, cancellations)
+    };
+    if ids.is_empty() {
+        return true;
+    }
+    let answer = account_client.cancel_all_open_orders(cancellations).await;
+    let mut orders = orders.lock().await;
+    match answer {
+        Ok(answer) => {
+            info!("Cancel all open orders: {:?}", answer);
+            for tx in &answer.data {
+                orders.on_cancel_result(tx);
+            }
+            true
+        }
+        Err(err) => {
+            warn!("Cancel all open orders Error: {:?}", err);
+            for id in &ids {
+                orders.on_cancel_failed(id, &err.to_string());
+            }
+            false
+        }
+    }
+}
+
+#[cfg(test)]
+mod test {
+    use super::*;
+    use crate::oms::OrderState;
+    use async_trait::async_trait;
+    use exrs::okex_v5::rest_model::{
+        AlgoTransaction, Balance, BalanceDetail, OrderCancellation, OrderDetail, PositionDetail,
+        Transaction, TransactionResponse,
+    };
+    use exrs::trading::{NewOrder, OpenOrder, OrderAck, Position, TradingClient, Venue};
+
+    /// Answers the recovery queries from canned data and records the cancels and algo orders
+    #[derive(Clone, Default)]
+    struct StubAccount {
+        pending: Vec<OrderDetail>,
+        positions: Vec<PositionDetail>,
+        balances: Vec<Balance>,
+        canceled: Arc<std::sync::Mutex<Vec<OrderCancellation>>>,
+        algo_orders: Arc<std::sync::Mutex<Vec<AlgoOrderRequest>>>,
+        canceled_algos: Arc<std::sync::Mutex<Vec<AlgoCancellation>>>,
+    }
+
+    fn algo_answer(algo_ids: Vec<String>) -> OkexResult<RestResponse<AlgoTransaction>> {
+        ok(algo_ids
+            .into_iter()
+            .map(|algo_id| AlgoTransaction {
+                algo_id,
+                s_code: 0,
+                s_msg: String::new(),
+            })
+            .collect())
+    }
+
+    fn ok<T>(data: Vec<T>) -> OkexResult<RestResponse<T>> {
+        Ok(RestResponse {
+            code: 0,
+            msg: String::new(),
+            data,
+        })
+    }
+
+    fn answer(data: Vec<Transaction>) -> OkexResult<TransactionResponse> {
+        Ok(TransactionResponse {
+            code: 0,
+            msg: String::new(),
+            data,
+        })
+    }
+
+    fn accepted(cl_ord_id: &str) -> Transaction {
+        Transaction {
+            cl_ord_id: cl_ord_id.to_string(),
+            ord_id: String::new(),
+            tag: None,
+            s_code: 0,
+            s_msg: String::new(),
+        }
+    }
+
+    #[async_trait]
+    impl TradingClient for StubAccount {
+        fn venue(&self) -> Venue {
+            Venue::Okex
+        }
+
+        async fn place_order(&self, order: NewOrder) -> trading::Result<OrderAck> {
+            Ok(OrderAck {
+                order_id: String::new(),
+                client_order_id: order.client_order_id.unwrap_or_default(),
+            })
+        }
+
+        async fn cancel_order(&self, _symbol: &str, _client_order_id: &str) -> trading::Result<()> {
+            Ok(())
+        }
+
+        async fn cancel_all(&self, _symbol: &str) -> trading::Result<()> {
+            Ok(())
+        }
+
+        async fn amend_order(
+            &self,
+            _symbol: &str,
+            client_order_id: &str,
+            _new_qty: Option<f64>,
+            _new_price: Option<f64>,
+        ) -> trading::Result<OrderAck> {
+            Ok(OrderAck {
+                order_id: String::new(),
+                client_order_id: client_order_id.to_string(),
+            })
+        }
+
+        async fn close_position(&self, _symbol: &str) -> trading::Result<()> {
+            Ok(())
+        }
+
+        async fn positions(&self, _symbol: &str) -> trading::Result<Vec<Position>> {
+            Ok(vec![])
+        }
+
+        async fn balances(&self) -> trading::Result<Vec<trading::Balance>> {
+            Ok(vec![])
+        }
+
+        async fn open_orders(&self, _symbol: &str) -> trading::Result<Vec<OpenOrder>> {
+            Ok(vec![])
+        }
+    }
+
+    #[async_trait]
+    impl OkexAccount for StubAccount {
+        async fn cancel_all_open_orders(
+            &self,
+            orders: Vec<OrderCancellation>,
+        ) -> OkexResult<TransactionResponse> {
+            let data = orders
+                .iter()
+                .map(|o| accepted(o.orig_client_order_id.as_deref().unwrap_or_default()))
+                .collect();
+            self.canceled.lock().unwrap().extend(orders);
+            answer(data)
+        }
+
+        async fn place_algo_order(
+            &self,
+            order: AlgoOrderRequest,
+        ) -> OkexResult<RestResponse<AlgoTransaction>> {
+            let mut algo_orders = self.algo_orders.lock().unwrap();
+            algo_orders.push(order);
+            algo_answer(vec![algo_orders.len().to_string()])
+        }
+
+        async fn cancel_algo_orders(
+            &self,
+            orders: Vec<AlgoCancellation>,
+        ) -> OkexResult<RestResponse<AlgoTransaction>> {
+            let algo_ids = orders.iter().map(|o| o.algo_id.clone()).collect();
+            self.canceled_algos.lock().unwrap().extend(orders);
+            algo_answer(algo_ids)
+        }
+
+        async fn cancel_advance_algo_orders(
+            &self,
+            orders: Vec<AlgoCancellation>,
+        ) -> OkexResult<RestResponse<AlgoTransaction>> {
+            self.cancel_algo_orders(orders).await
+        }
+    }
+
+    #[async_trait]
+    impl OkexRecovery for StubAccount {
+        async fn get_orders_pending(
+            &self,
+            _request: PendingOrdersRequest,
+        ) -> OkexResult<RestResponse<OrderDetail>> {
+            ok(self.pending.clone())
+        }
+
+        async fn get_algo_orders_pending(
+            &self,
+            _request: AlgoOrdersRequest,
+        ) -> OkexResult<RestResponse<AlgoOrderDetail>> {
+            ok(vec![])
+        }
+
+        async fn get_positions(
+            &self,
+            _request: PositionsRequest,
+        ) -> OkexResult<RestResponse<PositionDetail>> {
+            ok(self.positions.clone())
+        }
+
+        async fn get_balance(&self, _ccy: Option<String>) -> OkexResult<RestResponse<Balance>> {
+            ok(self.balances.clone())
+        }
+    }
+
+    fn strategy_with(
+        account: StubAccount,
+        keep_orphaned_orders: bool,
+        venue_stops: bool,
+    ) -> Box<AvellanedaStoikov<StubAccount>> {
+        strategy_on("USDT", account, keep_orphaned_orders, venue_stops)
+    }
+
+    fn strategy_on(
+        quote_asset: &str,
+        account: StubAccount,
+        keep_orphaned_orders: bool,
+        venue_stops: bool,
+    ) -> Box<AvellanedaStoikov<StubAccount>> {
+        let config: OkexConfig = serde_json::from_value(serde_json::json!({
+            "base_asset": "BTC",
+            "quote_asset": quote_asset,
+            "order_qty": 1,
+            "n_spreads": 10,
+            "estimate_window": 600000,
+            "period": 2000,
+            "sigma_tick_period": 550,
+            "gamma": 0.2,
+            "sigma_multiplier": 1,
+            "stoploss": 0.01,
+            "stoploss_sleep": 300000,
+            "stopprofit": 0.01,
+            "trailing_stop": 0.005,
+            "q_max": 10,
+            "is_testnet": true,
+            "keep_orphaned_orders": keep_orphaned_orders,
+            "venue_stops": venue_stops
+        }))
+        .unwrap();
+        let pair = format!("BTC-{}", quote_asset);
+        AvellanedaStoikov::with_account(config, account, Instrument::with_tick_size(&pair, 0.1), 0)
+    }
+
+    fn stub_account() -> StubAccount {
+        StubAccount {
+            pending: vec![
+                OrderDetail {
+                    inst_id: "BTC-USDT".to_string(),
+                    ord_id: "1".to_string(),
+                    cl_ord_id: "a".to_string(),
+                    px: "100".to_string(),
+                    sz: "2".to_string(),
+                    side: "buy".to_string(),
+                    state: "live".to_string(),
+                    ..Default::default()
+                },
+                // nothing left to take over
+                OrderDetail {
+                    inst_id: "BTC-USDT".to_string(),
+                    ord_id: "2".to_string(),
+                    cl_ord_id: "b".to_string(),
+                    state: "filled".to_string(),
+                    ..Default::default()
+                },
+            ],
+            positions: vec![PositionDetail {
+                inst_id: "BTC-USDT".to_string(),
+                pos: "3".to_string(),
+                avg_px: "99".to_string(),
+                ..Default::default()
+            }],
+            balances: vec![Balance {
+                details: vec![BalanceDetail {
+                    ccy: "BTC".to_string(),
+                    cash_bal: "1.5".to_string(),
+                    ..Default::default()
+                }],
+                ..Default::default()
+            }],
+            ..Default::default()
+        }
+    }
+
+    #[actix_rt::test]
+    async fn test_recover() {
+        let account = stub_account();
+        let mut strategy = strategy_with(account.clone(), false, false);
+        strategy.recover().await.unwrap();
+        let canceled = account.canceled.lock().unwrap().clone();
+        assert_eq!(canceled.len(), 1);
+        assert_eq!(canceled[0].orig_client_order_id.as_deref(), Some("a"));
+        let orders = strategy.orders();
+        let orders = orders.lock().await;
+        assert_eq!(orders.order("a").unwrap().state, OrderState::PendingCancel);
+        assert!(orders.order("b").is_none());
+        drop(orders);
+        assert_eq!(strategy.position.position_amount, 3.);
+        assert_eq!(strategy.position.entry_price, 99.);
+        assert_eq!(strategy.cash, 1.5);
+
+        let account = stub_account();
+        let mut strategy = strategy_with(account.clone(), true, false);
+        strategy.recover().await.unwrap();
+        assert!(account.canceled.lock().unwrap().is_empty());
+        let orders = strategy.orders();
+        let orders = orders.lock().await;
+        assert_eq!(orders.order("a").unwrap().state, OrderState::Live);
+        assert_eq!(orders.working_qty("BTC-USDT", Side::Buy), 2.);
+        drop(orders);
+        assert_eq!(strategy.position.position_amount, 3.);
+    }
+
+    #[actix_rt::test]
+    async fn test_venue_stops() {
+        let mut account = stub_account();
+        account.positions[0].inst_id = "BTC-USDT-SWAP".to_string();
+        let mut strategy = strategy_on("USDT-SWAP", account.clone(), true, true);
+        strategy.recover().await.unwrap();
+        {
+            let algo_orders = account.algo_orders.lock().unwrap();
+            assert_eq!(algo_orders.len(), 2);
+            // the long of 3 from 99 is closed by selling, in net mode like the quotes
+            let oco = &algo_orders[0];
+            assert!(matches!(oco.order_type, AlgoOrderType::Oco));
+            assert!(matches!(oco.trade_mode, TradeMode::Cross));
+            assert!(oco.position_side.is_none());
+            assert!(matches!(oco.side, OrderSide::Sell));
+            assert_eq!(oco.close_fraction, Some(1.));
+            assert_eq!(oco.sl_trigger_px, Some(98.1));
+            assert_eq!(oco.tp_trigger_px, Some(100.));
+            let trailing = &algo_orders[1];
+            assert!(matches!(trailing.order_type, AlgoOrderType::MoveOrderStop));
+            assert_eq!(trailing.qty, Some(3.));
+            assert_eq!(trailing.callback_ratio, Some(0.005));
+            assert_eq!(trailing.active_px, Some(99.5));
+        }
 
-        batch.push(oc)
+        // nothing changed, nothing is sent
+        strategy.sync_venue_stops().await;
+        assert_eq!(account.algo_orders.lock().unwrap().len(), 2);
+
+        // the position grew, the stops are replaced
+        strategy.position.position_amount = 4.;
+        strategy.sync_venue_stops().await;
+        assert_eq!(account.canceled_algos.lock().unwrap().len(), 2);
+        assert_eq!(account.algo_orders.lock().unwrap()[3].qty, Some(4.));
+
+        // flat, the stops are canceled
+        strategy.position.position_amount = 0.;
+        strategy.sync_venue_stops().await;
+        let canceled: Vec<String> = account
+            .canceled_algos
+            .lock()
+            .unwrap()
+            .iter()
+            .map(|c| c.algo_id.clone())
+            .collect();
+        assert_eq!(canceled, vec!["1", "2", "3", "4"]);
+        assert_eq!(account.algo_orders.lock().unwrap().len(), 4);
+        assert!(strategy.venue_stops.oco.is_empty() && strategy.venue_stops.trailing.is_empty());
     }
 
-    Ok(batch)
+    #[actix_rt::test]
+    async fn test_venue_stops_refused_on_spot() {
+        let account = stub_account();
+        let mut strategy = strategy_with(account.clone(), true, true);
+        assert!(!strategy.config.venue_stops);
+        strategy.recover().await.unwrap();
+        assert!(account.algo_orders.lock().unwrap().is_empty());
+    }
+
+    #[actix_rt::test]
+    async fn test_reconcile_on_venue_time() {
+        let mut strategy = strategy_with(StubAccount::default(), false, false);
+        let event = |u_time: &str| {
+            Box::new(PositionsEvent {
+                arg: Default::default(),
+                data: vec![exrs::okex_v5::ws_model::Positions {
+                    inst_id: "BTC-USDT".to_string(),
+                    pos: "2".to_string(),
+                    avg_px: "100".to_string(),
+                    u_time: u_time.to_string(),
+                    ..Default::default()
+                }],
+            })
+        };
+
+        // the grace period counts from the first update, not from the wall clock
+        strategy.on_position(event("1000")).await.unwrap();
+        strategy.on_position(event("5999")).await.unwrap();
+        assert_eq!(strategy.position.position_amount, 0.);
+        strategy.on_position(event("6000")).await.unwrap();
+        assert_eq!(strategy.position.position_amount, 2.);
+        assert_eq!(strategy.position.entry_price, 100.);
+    }
 }
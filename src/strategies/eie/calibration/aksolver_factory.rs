@@ -1,11 +1,21 @@
+use serde::Deserialize;
+
 use super::multicurve_aksolver::MultiCurveAkSolver;
+use super::nls_aksolver::WeightedNlsAkSolver;
+use super::poisson_aksolver::PoissonAkSolver;
 use super::regression_aksolver::RegressionAkSolver;
 use super::traits::AbstractAkSolver;
 
-#[derive(Debug, Copy, Clone)]
+#[derive(Debug, Copy, Clone, Default, PartialEq, Deserialize)]
+#[serde(rename_all = "snake_case")]
 pub enum SolverType {
     MultiCurve,
+    #[default]
     LogRegression,
+    /// Maximum likelihood of the fill counts over the exposure of each level
+    PoissonMle,
+    /// Least squares on the intensities weighted by the exposure of each level
+    WeightedNls,
 }
 
 #[derive(Debug, Copy, Clone)]
@@ -26,6 +36,8 @@ impl AkSolverFactory {
         match self.solver_type {
             SolverType::MultiCurve => MultiCurveAkSolver::new(&spread_specification),
             SolverType::LogRegression => RegressionAkSolver::new(&spread_specification),
+            SolverType::PoissonMle => PoissonAkSolver::new(spread_specification),
+            SolverType::WeightedNls => WeightedNlsAkSolver::new(spread_specification),
         }
     }
 }
@@ -0,0 +1,204 @@
+use log::warn;
+
+use crate::config::CalibrationConfig;
+
+/// z of a two sided 95% interval
+const Z_95: f64 = 1.96;
+
+/// A and k of one side with how well they fit the spread intensity curve
+#[derive(Debug, Clone, Default)]
+pub struct AkFit {
+    pub a: f64,
+    pub k: f64,
+    /// Of the intensities, over the levels with a usable estimate
+    pub r_squared: f64,
+    /// Intensity minus the fitted one per level, NaN for levels without an estimate
+    pub residuals: Vec<f64>,
+    /// Filled trackers per level
+    pub fills: Vec<usize>,
+    /// Standard errors of the fill count model at the estimates, NaN when it is singular
+    pub a_std_err: f64,
+    pub k_std_err: f64,
+}
+
+impl AkFit {
+    /// Diagnoses `(a, k)` against the levels at `spreads`, their intensities and their
+    /// exposures in units of dt
+    pub fn new(
+        (a, k): (f64, f64),
+        spreads: &[f64],
+        intensities: &[f64],
+        exposures: &[f64],
+        fills: Vec<usize>,
+    ) -> Self {
+        let fitted = |spread: f64| a * (-k * spread.abs()).exp();
+        let residuals: Vec<f64> = spreads
+            .iter()
+            .zip(intensities)
+            .map(|(&spread, &intensity)| intensity - fitted(spread))
+            .collect();
+
+        let usable: Vec<usize> = (0..spreads.len())
+            .filter(|&i| intensities[i].is_finite() && exposures[i] > 0.)
+            .collect();
+        let mean = usable.iter().map(|&i| intensities[i]).sum::<f64>() / usable.len() as f64;
+        let total: f64 = usable
+            .iter()
+            .map(|&i| (intensities[i] - mean).powi(2))
+            .sum();
+        let residual: f64 = usable.iter().map(|&i| residuals[i].powi(2)).sum();
+
+        // Fisher information of n_i ~ Poisson(E_i * A * exp(-k * spread_i)) in (A, k)
+        let (mut i_aa, mut i_ak, mut i_kk) = (0., 0., 0.);
+        for &i in &usable {
+            let spread = spreads[i].abs();
+            let expected = exposures[i] * fitted(spread);
+            i_aa += expected / (a * a);
+            i_ak -= expected * spread / a;
+            i_kk += expected * spread * spread;
+        }
+        let det = i_aa * i_kk - i_ak * i_ak;
+        let std_err = |v: f64| if det > 0. { (v / det).sqrt() } else { f64::NAN };
+
+        AkFit {
+            a,
+            k,
+            r_squared: 1. - residual / total,
+            residuals,
+            fills,
+            a_std_err: std_err(i_kk),
+            k_std_err: std_err(i_aa),
+        }
+    }
+
+    /// 95% confidence interval of A
+    pub fn a_interval(&self) -> (f64, f64) {
+        (
+            self.a - Z_95 * self.a_std_err,
+            self.a + Z_95 * self.a_std_err,
+        )
+    }
+
+    /// 95% confidence interval of k
+    pub fn k_interval(&self) -> (f64, f64) {
+        (
+            self.k - Z_95 * self.k_std_err,
+            self.k + Z_95 * self.k_std_err,
+        )
+    }
+
+    /// Why the fit is not usable under `config`, None when it is
+    pub fn rejection(&self, config: &CalibrationConfig) -> Option<String> {
+        if !(self.a.is_finite() && self.a > 0. && self.k.is_finite() && self.k > 0.) {
+            return Some(format!("A {} and k {} must be positive", self.a, self.k));
+        }
+        let outside = |v: f64, min: Option<f64>, max: Option<f64>| {
+            min.is_some_and(|min| v < min) || max.is_some_and(|max| v > max)
+        };
+        if outside(self.a, config.min_a, config.max_a) {
+            return Some(format!("A {} out of bounds", self.a));
+        }
+        if outside(self.k, config.min_k, config.max_k) {
+            return Some(format!("k {} out of bounds", self.k));
+        }
+        if let Some(min) = config.min_r_squared {
+            if self.r_squared.is_nan() || self.r_squared < min {
+                return Some(format!("R2 {} below {}", self.r_squared, min));
+            }
+        }
+        if let Some(max) = config.max_relative_error {
+            let error = (self.a_std_err / self.a).max(self.k_std_err / self.k);
+            if error.is_nan() || error > max {
+                return Some(format!("relative standard error {} over {}", error, max));
+            }
+        }
+        None
+    }
+}
+
+/// Keeps the last A and k that passed the checks of `CalibrationConfig`, smoothing the new
+/// ones into it
+#[derive(Debug, Clone)]
+pub struct AkFilter {
+    config: CalibrationConfig,
+    side: &'static str,
+    last_good: Option<(f64, f64)>,
+}
+
+impl AkFilter {
+    pub fn new(config: CalibrationConfig, side: &'static str) -> Self {
+        AkFilter {
+            config,
+            side,
+            last_good: None,
+        }
+    }
+
+    /// The smoothed A and k after `fit`, the last good ones when it is rejected and None
+    /// before any fit was accepted
+    pub fn update(&mut self, fit: &AkFit) -> Option<(f64, f64)> {
+        if let Some(reason) = fit.rejection(&self.config) {
+            warn!(
+                "{} calibration rejected, {}, keeping {:?}",
+                self.side, reason, self.last_good
+            );
+            return self.last_good;
+        }
+        let weight = self.config.smoothing;
+        let (a, k) = match self.last_good {
+            Some((a, k)) => (
+                weight * fit.a + (1. - weight) * a,
+                weight * fit.k + (1. - weight) * k,
+            ),
+            None => (fit.a, fit.k),
+        };
+        self.last_good = Some((a, k));
+        self.last_good
+    }
+
+    pub fn last_good(&self) -> Option<(f64, f64)> {
+        self.last_good
+    }
+}
+
+#[cfg(test)]
+mod test {
+    use super::*;
+
+    #[test]
+    fn test_fit_and_filter() {
+        let spreads = [0., -0.5, -1., -1.5, -2.];
+        let exposures = [1000., 1200., 1500., 1800., 2000.];
+        let intensities: Vec<f64> = spreads
+            .iter()
+            .map(|spread: &f64| 0.8 * (-1.5 * spread.abs()).exp())
+            .collect();
+        let fit = AkFit::new((0.8, 1.5), &spreads, &intensities, &exposures, vec![1; 5]);
+        assert!((fit.r_squared - 1.).abs() < 1e-12);
+        assert!(fit.residuals.iter().all(|r| r.abs() < 1e-12));
+        let (lo, hi) = fit.k_interval();
+        assert!(lo < 1.5 && 1.5 < hi && fit.a_std_err > 0.);
+
+        let mut filter = AkFilter::new(
+            CalibrationConfig {
+                max_k: Some(10.),
+                min_r_squared: Some(0.5),
+                smoothing: 0.5,
+                ..Default::default()
+            },
+            "buy",
+        );
+        assert_eq!(filter.update(&fit), Some((0.8, 1.5)));
+
+        let steep = AkFit::new((0.8, 20.), &spreads, &intensities, &exposures, vec![1; 5]);
+        assert!(steep.rejection(&filter.config).is_some());
+        assert_eq!(filter.update(&steep), Some((0.8, 1.5)));
+
+        let garbage = AkFit::new((f64::NAN, 1.), &spreads, &intensities, &exposures, vec![]);
+        assert_eq!(filter.update(&garbage), Some((0.8, 1.5)));
+
+        let next = AkFit::new((1.0, 1.5), &spreads, &intensities, &exposures, vec![1; 5]);
+        let (a, k) = filter.update(&next).unwrap();
+        assert!((a - 0.9).abs() < 1e-12 && (k - 1.5).abs() < 1e-12);
+    }
+}
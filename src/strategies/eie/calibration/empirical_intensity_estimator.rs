@@ -1,64 +1,75 @@
-use std::cell::RefCell;
+use std::cmp::Ordering;
+use std::collections::{BTreeMap, VecDeque};
 
+/// Order price with a total order, the key of the price index
 #[derive(Debug, Copy, Clone)]
-struct Fill;
+struct Price(f64);
 
-impl Fill {
-    fn is_order_filled(&self, spread_direction: f64, filled_price: f64, order_price: f64) -> bool {
-        match spread_direction > 0.0 {
-            true => {
-                return filled_price > order_price;
-            }
-            false => {
-                return filled_price < order_price;
-            }
-        }
+impl PartialEq for Price {
+    fn eq(&self, other: &Self) -> bool {
+        self.cmp(other) == Ordering::Equal
     }
 }
 
-#[derive(Debug, Copy, Clone)]
-struct LimitOrderTracker {
-    order_price: f64,
-    start_ts: u64,
+impl Eq for Price {}
+
+impl PartialOrd for Price {
+    fn partial_cmp(&self, other: &Self) -> Option<Ordering> {
+        Some(self.cmp(other))
+    }
 }
 
-impl LimitOrderTracker {
-    fn new(order_price: f64, start_ts: u64) -> Self {
-        LimitOrderTracker {
-            order_price: order_price,
-            start_ts: start_ts,
-        }
+impl Ord for Price {
+    fn cmp(&self, other: &Self) -> Ordering {
+        self.0.total_cmp(&other.0)
     }
 }
 
+/// Live trackers by price, the sequence number keeps equal prices apart
+type TrackerKey = (Price, u64);
+
+/// Waiting times of hypothetical limit orders placed every `dt` at `spread` from the
+/// reference price, see readme.md.
+///
+/// Live trackers sit in a queue ordered by start time, so the expired ones are popped from
+/// its front, and in a price index, so the filled ones are popped from one of its ends. A
+/// tracker leaves the queue lazily once it is gone from the index. Every tracker is inserted
+/// and removed once, which keeps a tick at amortized O(log n).
 #[derive(Debug, Clone)]
 pub struct EmpiricalIntensityEstimator {
     spread: f64,
     spread_direction: f64,
     dt: u64,
-    fill_comp: Fill,
     initializing: bool,
     last_price: f64,
     last_limit_order_inserted: u64,
-    live_trackers: RefCell<Vec<LimitOrderTracker>>,
+    next_seq: u64,
+    /// (start_ts, key) in start order, None for NaN prices which never fill
+    live_queue: VecDeque<(u64, Option<TrackerKey>)>,
+    /// key -> start_ts
+    live_prices: BTreeMap<TrackerKey, u64>,
+    live_count: usize,
     live_trackers_start_time_sum: u64,
-    finished_trackers: RefCell<Vec<(u64, u64)>>,
+    /// (start_ts, seq) -> waiting time
+    finished_trackers: BTreeMap<(u64, u64), u64>,
     finished_trackers_wait_time_sum: u64,
 }
 
 impl EmpiricalIntensityEstimator {
     pub fn new(spread: f64, spread_direction: f64, dt: u64) -> Self {
         EmpiricalIntensityEstimator {
-            spread: spread,
-            spread_direction: spread_direction,
-            dt: dt,
-            fill_comp: Fill,
+            spread,
+            spread_direction,
+            dt,
             initializing: true,
             last_price: f64::NAN,
             last_limit_order_inserted: 0,
-            live_trackers: RefCell::new(Vec::new()),
+            next_seq: 0,
+            live_queue: VecDeque::new(),
+            live_prices: BTreeMap::new(),
+            live_count: 0,
             live_trackers_start_time_sum: 0,
-            finished_trackers: RefCell::new(Vec::new()),
+            finished_trackers: BTreeMap::new(),
             finished_trackers_wait_time_sum: 0,
         }
     }
@@ -69,80 +80,256 @@ impl EmpiricalIntensityEstimator {
             self.last_limit_order_inserted = ts - self.dt;
         }
 
-        let lt = &mut self.live_trackers.borrow_mut();
-
         while self.last_limit_order_inserted + self.dt < ts {
-            self.last_limit_order_inserted = self.last_limit_order_inserted + self.dt;
-            lt.push(LimitOrderTracker::new(
+            self.last_limit_order_inserted += self.dt;
+            self.insert(
                 self.last_price + self.spread,
                 self.last_limit_order_inserted,
-            ));
-            self.live_trackers_start_time_sum += self.last_limit_order_inserted;
+            );
         }
 
         if self.last_limit_order_inserted + self.dt == ts {
             self.last_limit_order_inserted = ts;
-            lt.push(LimitOrderTracker::new(ref_price + self.spread, ts));
-            self.live_trackers_start_time_sum += ts;
+            self.insert(ref_price + self.spread, ts);
         }
 
         self.last_price = ref_price;
 
-        for i in 0..lt.len() {
-            if let Some(tr) = lt.get(i) {
-                if window_start > tr.start_ts {
-                    self.live_trackers_start_time_sum -= tr.start_ts;
-                    lt.remove(i);
-                    continue;
-                }
+        self.expire_live(window_start);
 
-                if self
-                    .fill_comp
-                    .is_order_filled(self.spread_direction, fill_price, tr.order_price)
-                {
-                    self.live_trackers_start_time_sum -= tr.start_ts;
-
-                    let duration = ts - tr.start_ts;
-                    // add to finished trackers, add duration to sum
-                    self.finished_trackers
-                        .borrow_mut()
-                        .push((tr.start_ts, duration));
-                    self.finished_trackers_wait_time_sum += duration;
-                    lt.remove(i);
+        // a sell order above the reference price fills when the price trades above it,
+        // a buy order below it when the price trades below it
+        if fill_price.is_nan() {
+            return;
+        }
+        loop {
+            let entry = if self.spread_direction > 0.0 {
+                match self.live_prices.first_entry() {
+                    Some(entry) if fill_price > entry.key().0 .0 => entry,
+                    _ => break,
                 }
-            }
+            } else {
+                match self.live_prices.last_entry() {
+                    Some(entry) if fill_price < entry.key().0 .0 => entry,
+                    _ => break,
+                }
+            };
+            let seq = entry.key().1;
+            let start_ts = entry.remove();
+            self.live_count -= 1;
+            self.live_trackers_start_time_sum -= start_ts;
+
+            let duration = ts - start_ts;
+            self.finished_trackers.insert((start_ts, seq), duration);
+            self.finished_trackers_wait_time_sum += duration;
         }
     }
 
     pub fn estimate_intensity(&mut self, ts: u64, window_start: u64) -> f64 {
-        let ft = &mut self.finished_trackers.borrow_mut();
+        while let Some(entry) = self.finished_trackers.first_entry() {
+            if entry.key().0 >= window_start {
+                break;
+            }
+            self.finished_trackers_wait_time_sum -= entry.remove();
+        }
 
-        for i in 0..ft.len() {
-            if let Some(&tr) = ft.get(i) {
-                if tr.0 < window_start {
-                    ft.remove(i);
-                    self.finished_trackers_wait_time_sum -= tr.1;
-                }
+        if self.last_live_start() != Some(ts) && self.live_count > 0 {
+            self.expire_live(window_start);
+        }
+
+        self.dt as f64 * self.finished_trackers.len() as f64 / self.exposure(ts)
+    }
+
+    /// Trackers of the last estimated window that were filled
+    pub fn fills(&self) -> usize {
+        self.finished_trackers.len()
+    }
+
+    /// Total time in ms the trackers of the last estimated window were exposed to fills
+    pub fn exposure(&self, ts: u64) -> f64 {
+        self.live_count as f64 * ts as f64 - self.live_trackers_start_time_sum as f64
+            + self.finished_trackers_wait_time_sum as f64
+    }
+
+    fn insert(&mut self, order_price: f64, start_ts: u64) {
+        // -0.0 and 0.0 compare equal like in the fill check
+        let order_price = order_price + 0.0;
+        let key = if order_price.is_nan() {
+            None
+        } else {
+            let key = (Price(order_price), self.next_seq);
+            self.next_seq += 1;
+            self.live_prices.insert(key, start_ts);
+            Some(key)
+        };
+        self.live_queue.push_back((start_ts, key));
+        self.live_count += 1;
+        self.live_trackers_start_time_sum += start_ts;
+    }
+
+    fn is_live(&self, key: &Option<TrackerKey>) -> bool {
+        match key {
+            Some(key) => self.live_prices.contains_key(key),
+            None => true,
+        }
+    }
+
+    /// Drops the trackers started before `window_start` without counting them as filled
+    fn expire_live(&mut self, window_start: u64) {
+        while let Some(&(start_ts, key)) = self.live_queue.front() {
+            if !self.is_live(&key) {
+                self.live_queue.pop_front();
+                continue;
+            }
+            if start_ts >= window_start {
+                break;
+            }
+            self.live_queue.pop_front();
+            if let Some(key) = key {
+                self.live_prices.remove(&key);
+            }
+            self.live_count -= 1;
+            self.live_trackers_start_time_sum -= start_ts;
+        }
+    }
+
+    fn last_live_start(&mut self) -> Option<u64> {
+        while let Some(&(start_ts, key)) = self.live_queue.back() {
+            if self.is_live(&key) {
+                return Some(start_ts);
+            }
+            self.live_queue.pop_back();
+        }
+        None
+    }
+}
+
+#[cfg(test)]
+mod test {
+    use super::*;
+
+    /// The waiting time estimator without the bookkeeping, every tracker is checked on
+    /// every tick
+    struct ReferenceEstimator {
+        spread: f64,
+        spread_direction: f64,
+        dt: u64,
+        last_price: f64,
+        last_inserted: Option<u64>,
+        live: Vec<(f64, u64)>,
+        finished: Vec<(u64, u64)>,
+    }
+
+    impl ReferenceEstimator {
+        fn new(spread: f64, spread_direction: f64, dt: u64) -> Self {
+            ReferenceEstimator {
+                spread,
+                spread_direction,
+                dt,
+                last_price: f64::NAN,
+                last_inserted: None,
+                live: Vec::new(),
+                finished: Vec::new(),
             }
         }
 
-        if !self.live_trackers.borrow().is_empty()
-            && (ts != self.live_trackers.borrow().last().unwrap().start_ts)
-        {
-            let lt = &mut self.live_trackers.borrow_mut();
-            for i in 0..lt.len() {
-                if let Some(&tr) = lt.get(i) {
-                    if window_start > tr.start_ts {
-                        lt.remove(i);
-                        self.live_trackers_start_time_sum -= tr.start_ts;
-                    }
+        fn on_tick(&mut self, ref_price: f64, fill_price: f64, ts: u64, window_start: u64) {
+            let mut last = self.last_inserted.unwrap_or(ts - self.dt);
+            while last + self.dt < ts {
+                last += self.dt;
+                self.live.push((self.last_price + self.spread, last));
+            }
+            if last + self.dt == ts {
+                last = ts;
+                self.live.push((ref_price + self.spread, ts));
+            }
+            self.last_inserted = Some(last);
+            self.last_price = ref_price;
+
+            let mut live = Vec::new();
+            for (price, start_ts) in self.live.drain(..) {
+                if start_ts < window_start {
+                    continue;
                 }
+                let filled = if self.spread_direction > 0.0 {
+                    fill_price > price
+                } else {
+                    fill_price < price
+                };
+                if filled {
+                    self.finished.push((start_ts, ts - start_ts));
+                } else {
+                    live.push((price, start_ts));
+                }
+            }
+            self.live = live;
+        }
+
+        fn estimate_intensity(&mut self, ts: u64, window_start: u64) -> f64 {
+            self.finished
+                .retain(|&(start_ts, _)| start_ts >= window_start);
+            if self
+                .live
+                .last()
+                .is_some_and(|&(_, start_ts)| start_ts != ts)
+            {
+                self.live.retain(|&(_, start_ts)| start_ts >= window_start);
             }
+            let live_wait: u64 = self.live.iter().map(|&(_, start_ts)| ts - start_ts).sum();
+            let finished_wait: u64 = self.finished.iter().map(|&(_, wait)| wait).sum();
+            self.dt as f64 * self.finished.len() as f64 / (live_wait + finished_wait) as f64
         }
+    }
+
+    /// Deterministic random walk of a quoted market with gaps between the ticks
+    fn ticks(n: usize) -> Vec<(f64, f64, u64)> {
+        let mut seed: u64 = 42;
+        let mut next = move || {
+            seed = seed
+                .wrapping_mul(6364136223846793005)
+                .wrapping_add(1442695040888963407);
+            (seed >> 33) as f64 / (1u64 << 31) as f64
+        };
+        let mut mid = 100.;
+        let mut ts = 1_000_000;
+        (0..n)
+            .map(|_| {
+                mid += ((next() * 7.) as i64 - 3) as f64 * 0.5;
+                let half_spread = 0.5 + (next() * 3.) as i64 as f64 * 0.5;
+                ts += 50 + (next() * 400.) as u64;
+                (mid - half_spread, mid + half_spread, ts)
+            })
+            .collect()
+    }
 
-        return self.dt as f64 * ft.len() as f64
-            / (self.live_trackers.borrow().len() as f64 * ts as f64
-                - self.live_trackers_start_time_sum as f64
-                + self.finished_trackers_wait_time_sum as f64);
+    #[test]
+    fn test_matches_reference() {
+        let (dt, w) = (100, 5_000);
+        for (spread, direction) in [(0., 1.), (1.5, 1.), (-0.5, -1.), (-3., -1.)] {
+            let mut estimator = EmpiricalIntensityEstimator::new(spread, direction, dt);
+            let mut reference = ReferenceEstimator::new(spread, direction, dt);
+            let mut filled = false;
+            for (i, (bid, ask, ts)) in ticks(3_000).into_iter().enumerate() {
+                let mid = (bid + ask) / 2.;
+                let fill = if direction > 0. { bid } else { ask };
+                let window_start = ts - w;
+                estimator.on_tick(mid, fill, ts, window_start);
+                reference.on_tick(mid, fill, ts, window_start);
+                if i % 10 == 0 {
+                    let expected = reference.estimate_intensity(ts, window_start);
+                    let actual = estimator.estimate_intensity(ts, window_start);
+                    filled |= actual > 0.;
+                    assert!(
+                        actual == expected || (actual.is_nan() && expected.is_nan()),
+                        "spread {} at {}: {} != {}",
+                        spread,
+                        ts,
+                        actual,
+                        expected
+                    );
+                }
+            }
+            assert!(filled, "spread {} never filled", spread);
+        }
     }
 }
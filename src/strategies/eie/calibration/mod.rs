@@ -1,6 +1,9 @@
 pub mod aksolver_factory;
+pub mod diagnostics;
 pub mod empirical_intensity_estimator;
 pub mod multicurve_aksolver;
+pub mod nls_aksolver;
+pub mod poisson_aksolver;
 pub mod regression_aksolver;
 pub mod spread_intensity_curve;
 pub mod traits;
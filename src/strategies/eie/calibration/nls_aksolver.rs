@@ -0,0 +1,127 @@
+use super::poisson_aksolver::MAX_DECAY;
+use super::traits::AbstractAkSolver;
+use log::debug;
+use std::time::Instant;
+
+/// Points of the coarse k scan before the golden section search
+const GRID_POINTS: usize = 64;
+
+/**
+ * A and k minimizing sum(E_i * (intensity_i - A * exp(-k * spread_i))^2), E_i being the
+ * exposure of level i. Fitting the intensities instead of their log keeps the levels
+ * without fills, and the exposure weights the levels by how much their estimate is worth.
+ */
+pub struct WeightedNlsAkSolver {
+    pub last_valid_value: (f64, f64),
+    pub spread_specification: Vec<f64>,
+}
+
+impl AbstractAkSolver for WeightedNlsAkSolver {
+    fn new(spread_specification: &[f64]) -> Box<dyn AbstractAkSolver> {
+        let mut solver = WeightedNlsAkSolver {
+            last_valid_value: (0f64, 0f64),
+            spread_specification: spread_specification.to_vec(),
+        };
+        solver.spread_specification = solver.abs_spread(spread_specification);
+        Box::new(solver)
+    }
+
+    /// Without exposures the levels weigh the same
+    fn solve_ak(&mut self, intensities: &[f64]) -> (f64, f64) {
+        let exposures = vec![1.0; intensities.len()];
+        self.solve_ak_with_exposure(intensities, &exposures)
+    }
+
+    fn solve_ak_with_exposure(&mut self, intensities: &[f64], exposures: &[f64]) -> (f64, f64) {
+        let ins = Instant::now();
+
+        // (spread, intensity, weight) of the levels with a usable estimate
+        let levels: Vec<(f64, f64, f64)> = self
+            .spread_specification
+            .iter()
+            .zip(intensities.iter().zip(exposures))
+            .filter(|(_, (intensity, exposure))| intensity.is_finite() && **exposure > 0.)
+            .map(|(&spread, (&intensity, &exposure))| (spread, intensity, exposure))
+            .collect();
+
+        match solve(&levels) {
+            Some(ak) => self.last_valid_value = ak,
+            None => debug!("WeightedNlsAkSolver kept the last value, no fills"),
+        }
+        debug!("WeightedNlsAkSolver time: {:?}", ins.elapsed());
+        self.last_valid_value
+    }
+}
+
+/// The best A for `k` and the weighted sum of squares it leaves
+fn fit_a(levels: &[(f64, f64, f64)], k: f64) -> (f64, f64) {
+    let (mut cross, mut norm, mut total) = (0., 0., 0.);
+    for &(spread, intensity, weight) in levels {
+        let decay = (-k * spread).exp();
+        cross += weight * intensity * decay;
+        norm += weight * decay * decay;
+        total += weight * intensity * intensity;
+    }
+    (cross / norm, total - cross * cross / norm)
+}
+
+fn solve(levels: &[(f64, f64, f64)]) -> Option<(f64, f64)> {
+    if !levels.iter().any(|&(_, intensity, _)| intensity > 0.) {
+        return None;
+    }
+    let min_spread = levels.iter().map(|l| l.0).fold(f64::INFINITY, f64::min);
+    let max_spread = levels.iter().map(|l| l.0).fold(f64::NEG_INFINITY, f64::max);
+    if max_spread <= min_spread {
+        return Some((fit_a(levels, 0.).0, 0.));
+    }
+
+    // A is linear given k, so only k is searched: a scan for the best cell, then a golden
+    // section search between its neighbours
+    let step = MAX_DECAY / (max_spread - min_spread) / GRID_POINTS as f64;
+    let best = (0..=GRID_POINTS)
+        .map(|i| (i, fit_a(levels, i as f64 * step).1))
+        .min_by(|a, b| a.1.total_cmp(&b.1))
+        .map_or(0, |(i, _)| i);
+    let (mut lo, mut hi) = (
+        best.saturating_sub(1) as f64 * step,
+        (best + 1).min(GRID_POINTS) as f64 * step,
+    );
+    let ratio = (5f64.sqrt() - 1.) / 2.;
+    for _ in 0..100 {
+        let left = hi - ratio * (hi - lo);
+        let right = lo + ratio * (hi - lo);
+        if fit_a(levels, left).1 <= fit_a(levels, right).1 {
+            hi = right;
+        } else {
+            lo = left;
+        }
+    }
+    let k = (lo + hi) / 2.;
+    Some((fit_a(levels, k).0, k))
+}
+
+#[cfg(test)]
+mod test {
+    use super::*;
+
+    #[test]
+    fn test_zero_fill_levels() {
+        let spreads: [f64; 5] = [0., -0.5, -1., -1.5, -2.];
+        let exposures = [1000., 1200., 1500., 1800., 2000.];
+        let (a, k): (f64, f64) = (0.8, 1.5);
+        let mut intensities: Vec<f64> = spreads
+            .iter()
+            .map(|spread| a * (-k * spread.abs()).exp())
+            .collect();
+        let mut solver = WeightedNlsAkSolver::new(&spreads);
+        let (a_hat, k_hat) = solver.solve_ak_with_exposure(&intensities, &exposures);
+        assert!((a_hat - a).abs() < 1e-6 && (k_hat - k).abs() < 1e-6);
+
+        intensities[4] = 0.;
+        intensities[2] = f64::NAN;
+        let (a_hat, k_hat) = solver.solve_ak_with_exposure(&intensities, &exposures);
+        assert!(a_hat.is_finite() && k_hat.is_finite() && k_hat > 0.);
+
+        assert_eq!(solver.solve_ak(&[0.; 5]), (a_hat, k_hat));
+    }
+}
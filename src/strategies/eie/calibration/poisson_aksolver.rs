@@ -0,0 +1,134 @@
+use super::traits::AbstractAkSolver;
+use log::debug;
+use std::time::Instant;
+
+/// k is searched up to where the intensity at the widest spread is exp(-MAX_DECAY) of the
+/// one at the narrowest
+pub const MAX_DECAY: f64 = 50.;
+
+/**
+ * Maximum likelihood A and k of fill counts n_i ~ Poisson(E_i * A * exp(-k * spread_i)),
+ * E_i being the exposure of level i. Levels without fills still pull k up through their
+ * exposure instead of breaking the fit.
+ */
+pub struct PoissonAkSolver {
+    pub last_valid_value: (f64, f64),
+    pub spread_specification: Vec<f64>,
+}
+
+impl AbstractAkSolver for PoissonAkSolver {
+    fn new(spread_specification: &[f64]) -> Box<dyn AbstractAkSolver> {
+        let mut solver = PoissonAkSolver {
+            last_valid_value: (0f64, 0f64),
+            spread_specification: spread_specification.to_vec(),
+        };
+        solver.spread_specification = solver.abs_spread(spread_specification);
+        Box::new(solver)
+    }
+
+    /// Without exposures every level counts as exposed for one dt
+    fn solve_ak(&mut self, intensities: &[f64]) -> (f64, f64) {
+        let exposures = vec![1.0; intensities.len()];
+        self.solve_ak_with_exposure(intensities, &exposures)
+    }
+
+    fn solve_ak_with_exposure(&mut self, intensities: &[f64], exposures: &[f64]) -> (f64, f64) {
+        let ins = Instant::now();
+
+        // (spread, fills, exposure) of the levels with a usable estimate
+        let levels: Vec<(f64, f64, f64)> = self
+            .spread_specification
+            .iter()
+            .zip(intensities.iter().zip(exposures))
+            .filter(|(_, (intensity, exposure))| intensity.is_finite() && **exposure > 0.)
+            .map(|(&spread, (&intensity, &exposure))| (spread, intensity * exposure, exposure))
+            .collect();
+
+        match solve(&levels) {
+            Some(ak) => self.last_valid_value = ak,
+            None => debug!("PoissonAkSolver kept the last value, no fills"),
+        }
+        debug!("PoissonAkSolver time: {:?}", ins.elapsed());
+        self.last_valid_value
+    }
+}
+
+fn solve(levels: &[(f64, f64, f64)]) -> Option<(f64, f64)> {
+    let total_fills: f64 = levels.iter().map(|&(_, fills, _)| fills).sum();
+    if total_fills <= 0. {
+        return None;
+    }
+    let min_spread = levels.iter().map(|l| l.0).fold(f64::INFINITY, f64::min);
+    let max_spread = levels.iter().map(|l| l.0).fold(f64::NEG_INFINITY, f64::max);
+    let fill_spread = levels
+        .iter()
+        .map(|&(spread, fills, _)| spread * fills)
+        .sum::<f64>()
+        / total_fills;
+
+    // Setting the derivative in A to 0 leaves A = sum(n) / sum(E * exp(-k * spread)), and in
+    // k the exposure weighted mean spread equal to the fill weighted one. The former only
+    // falls as k grows, so the root is bisected.
+    let exposed_spread = |k: f64| {
+        let (mut weighted, mut total) = (0., 0.);
+        for &(spread, _, exposure) in levels {
+            let weight = exposure * (-k * (spread - min_spread)).exp();
+            weighted += weight * spread;
+            total += weight;
+        }
+        weighted / total
+    };
+
+    let k = if max_spread <= min_spread || exposed_spread(0.) <= fill_spread {
+        0.
+    } else {
+        let (mut lo, mut hi) = (0., MAX_DECAY / (max_spread - min_spread));
+        if exposed_spread(hi) >= fill_spread {
+            // every fill at the narrowest spread
+            hi
+        } else {
+            for _ in 0..100 {
+                let mid = (lo + hi) / 2.;
+                if exposed_spread(mid) > fill_spread {
+                    lo = mid;
+                } else {
+                    hi = mid;
+                }
+            }
+            (lo + hi) / 2.
+        }
+    };
+
+    let exposure: f64 = levels
+        .iter()
+        .map(|&(spread, _, exposure)| exposure * (-k * spread).exp())
+        .sum();
+    Some((total_fills / exposure, k))
+}
+
+#[cfg(test)]
+mod test {
+    use super::*;
+
+    #[test]
+    fn test_zero_fill_levels() {
+        let spreads = [0., 0.5, 1., 1.5, 2.];
+        let exposures = [1000., 1200., 1500., 1800., 2000.];
+        let (a, k): (f64, f64) = (0.8, 1.5);
+        let mut intensities: Vec<f64> = spreads
+            .iter()
+            .map(|&spread| a * (-k * spread).exp())
+            .collect();
+        let mut solver = PoissonAkSolver::new(&spreads);
+        let (a_hat, k_hat) = solver.solve_ak_with_exposure(&intensities, &exposures);
+        assert!((a_hat - a).abs() < 1e-9 && (k_hat - k).abs() < 1e-9);
+
+        // the widest levels never filled and the log regression gives up on them
+        intensities[3] = 0.;
+        intensities[4] = 0.;
+        let (a_hat, k_hat) = solver.solve_ak_with_exposure(&intensities, &exposures);
+        assert!(a_hat.is_finite() && k_hat > k);
+
+        assert_eq!(solver.solve_ak(&[0.; 5]), (a_hat, k_hat));
+    }
+}
@@ -1,11 +1,16 @@
 use super::aksolver_factory::AkSolverFactory;
+use super::diagnostics::AkFit;
 use super::empirical_intensity_estimator::EmpiricalIntensityEstimator;
 use super::traits::AbstractAkSolver;
 
 pub struct SpreadIntensityCurve {
+    pub spread_specification: Vec<f64>,
     pub intensity_estimators: Vec<EmpiricalIntensityEstimator>,
     pub intensity_estimates: Vec<f64>,
+    /// Exposure of each level in units of dt
+    pub exposures: Vec<f64>,
     pub aksolver: Box<dyn AbstractAkSolver>,
+    dt: u64,
 }
 
 impl SpreadIntensityCurve {
@@ -18,6 +23,7 @@ impl SpreadIntensityCurve {
         let mut intensity_estimators = Vec::with_capacity(n_spreads);
         let mut spread_specification = vec![0.0; n_spreads];
         let intensity_estimates = vec![0.0; n_spreads];
+        let exposures = vec![0.0; n_spreads];
 
         for i in 0..n_spreads {
             spread_specification[i] = i as f64 * spread_step;
@@ -31,9 +37,12 @@ impl SpreadIntensityCurve {
         let aksolver = solver_factory.get_solver(&spread_specification);
 
         SpreadIntensityCurve {
+            spread_specification,
             intensity_estimators,
             intensity_estimates,
+            exposures,
             aksolver,
+            dt,
         }
     }
 
@@ -43,12 +52,25 @@ impl SpreadIntensityCurve {
         }
     }
 
-    pub fn estimate_ak(&mut self, ts: u64, window_start: u64) -> (f64, f64) {
+    pub fn estimate_ak(&mut self, ts: u64, window_start: u64) -> AkFit {
         for i in 0..self.intensity_estimates.len() {
             self.intensity_estimates[i] =
-                self.intensity_estimators[i].estimate_intensity(ts, window_start)
+                self.intensity_estimators[i].estimate_intensity(ts, window_start);
+            self.exposures[i] = self.intensity_estimators[i].exposure(ts) / self.dt as f64;
         }
 
-        return self.aksolver.solve_ak(&self.intensity_estimates);
+        let ak = self
+            .aksolver
+            .solve_ak_with_exposure(&self.intensity_estimates, &self.exposures);
+        AkFit::new(
+            ak,
+            &self.spread_specification,
+            &self.intensity_estimates,
+            &self.exposures,
+            self.intensity_estimators
+                .iter()
+                .map(|est| est.fills())
+                .collect(),
+        )
     }
 }
@@ -12,6 +12,13 @@ pub trait AbstractAkSolver: Sync + Send {
 
     fn solve_ak(&mut self, intensities: &[f64]) -> (f64, f64);
 
+    /// Solves with the time, in units of dt, each level was exposed to fills. Solvers that
+    /// do not weigh the levels by it use the intensities alone.
+    fn solve_ak_with_exposure(&mut self, intensities: &[f64], exposures: &[f64]) -> (f64, f64) {
+        let _ = exposures;
+        self.solve_ak(intensities)
+    }
+
     fn mean(&self, data: &[f64]) -> Option<f64> {
         let sum = data.iter().sum::<f64>();
         let count = data.len();
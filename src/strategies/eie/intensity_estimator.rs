@@ -1,3 +1,5 @@
+use serde::Deserialize;
+
 use super::{
     calibration::{
         aksolver_factory::AkSolverFactory, spread_intensity_curve::SpreadIntensityCurve,
@@ -5,6 +7,17 @@ use super::{
     intensity_info::IntensityInfo,
 };
 
+/// What fills the hypothetical orders of the spread intensity curves
+#[derive(Debug, Copy, Clone, Default, PartialEq, Deserialize)]
+#[serde(rename_all = "snake_case")]
+pub enum FillSource {
+    /// The top of book moving through the order, a sell fills once the bid is above it
+    #[default]
+    Book,
+    /// A trade printing through the order, the book only moves the reference price
+    Trades,
+}
+
 pub struct IntensityEstimator {
     sell_execution_intensity: SpreadIntensityCurve,
     buy_execution_intensity: SpreadIntensityCurve,
@@ -12,6 +25,10 @@ pub struct IntensityEstimator {
     is_initializing: bool,
     is_initialized: bool,
     w: u64,
+    fill_source: FillSource,
+    last_mid_price: f64,
+    /// Trades may be stamped before the last book, the curves never go back in time
+    last_ts: u64,
 }
 
 impl IntensityEstimator {
@@ -39,21 +56,56 @@ impl IntensityEstimator {
             is_initializing: true,
             is_initialized: false,
             w: w,
+            fill_source: FillSource::Book,
+            last_mid_price: f64::NAN,
+            last_ts: 0,
         }
     }
 
+    pub fn with_fill_source(mut self, fill_source: FillSource) -> Self {
+        self.fill_source = fill_source;
+        self
+    }
+
+    pub fn fill_source(&self) -> FillSource {
+        self.fill_source
+    }
+
     pub fn on_tick(&mut self, bid: f64, ask: f64, ts: u64) -> bool {
         if self.is_initializing {
             self.init(ts);
         }
 
         let mid_price = (bid + ask) / 2.0;
+        self.last_mid_price = mid_price;
+        match self.fill_source {
+            FillSource::Book => self.update(bid, ask, ts),
+            FillSource::Trades => self.update(f64::NAN, f64::NAN, ts),
+        }
+        return self.is_initialized;
+    }
+
+    /// A trade at `price`, fills the sell orders below it and the buy orders above it when
+    /// the fills come from trades. Trades before the first book are dropped.
+    pub fn on_trade(&mut self, price: f64, ts: u64) {
+        if self.fill_source != FillSource::Trades || self.last_mid_price.is_nan() {
+            return;
+        }
+        self.update(price, price, ts);
+    }
+
+    fn update(&mut self, sell_fill_price: f64, buy_fill_price: f64, ts: u64) {
+        let ts = ts.max(self.last_ts);
+        self.last_ts = ts;
         let window_start = ts - self.w;
-        self.sell_execution_intensity
-            .on_tick(mid_price, bid, ts, window_start);
+        self.sell_execution_intensity.on_tick(
+            self.last_mid_price,
+            sell_fill_price,
+            ts,
+            window_start,
+        );
         self.buy_execution_intensity
-            .on_tick(mid_price, ask, ts, window_start);
-        return self.is_initialized;
+            .on_tick(self.last_mid_price, buy_fill_price, ts, window_start);
     }
 
     pub fn init(&mut self, ts: u64) {
@@ -71,6 +123,7 @@ impl IntensityEstimator {
     }
 
     pub fn estimate(&mut self, ts: u64) -> IntensityInfo {
+        let ts = ts.max(self.last_ts);
         let window_start = ts - self.w;
         return IntensityInfo::new(
             self.buy_execution_intensity.estimate_ak(ts, window_start),
@@ -78,3 +131,36 @@ impl IntensityEstimator {
         );
     }
 }
+
+#[cfg(test)]
+mod test {
+    use super::*;
+    use crate::strategies::eie::calibration::aksolver_factory::SolverType;
+
+    #[test]
+    fn test_trade_fills() {
+        let estimator = || {
+            IntensityEstimator::new(
+                1.,
+                3,
+                10_000,
+                100,
+                AkSolverFactory::new(&SolverType::PoissonMle),
+            )
+        };
+        let mut book = estimator();
+        let mut trades = estimator().with_fill_source(FillSource::Trades);
+        // a still top of book with buyers lifting the offer and trading through 101
+        for ts in (1_000_000..1_020_000).step_by(50) {
+            book.on_tick(99., 101., ts);
+            trades.on_tick(99., 101., ts);
+            trades.on_trade(101.5, ts + 10);
+            book.on_trade(101.5, ts + 10);
+        }
+        let (book, trades) = (book.estimate(1_020_000), trades.estimate(1_020_000));
+        assert_eq!(book.sell_fit.fills, vec![0, 0, 0]);
+        assert!(trades.sell_fit.fills[0] > 0 && trades.sell_fit.fills[1] > 0);
+        assert_eq!(trades.sell_fit.fills[2], 0);
+        assert_eq!(trades.buy_fit.fills, vec![0, 0, 0]);
+    }
+}
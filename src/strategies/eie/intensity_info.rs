@@ -1,18 +1,24 @@
-#[derive(Debug, Copy, Clone)]
+use super::calibration::diagnostics::AkFit;
+
+#[derive(Debug, Clone)]
 pub struct IntensityInfo {
     pub buy_a: f64,
     pub buy_k: f64,
     pub sell_a: f64,
     pub sell_k: f64,
+    pub buy_fit: AkFit,
+    pub sell_fit: AkFit,
 }
 
 impl IntensityInfo {
-    pub fn new(buy_ak: (f64, f64), sell_ak: (f64, f64)) -> Self {
+    pub fn new(buy_fit: AkFit, sell_fit: AkFit) -> Self {
         IntensityInfo {
-            buy_a: buy_ak.0,
-            buy_k: buy_ak.1,
-            sell_a: sell_ak.0,
-            sell_k: sell_ak.1,
+            buy_a: buy_fit.a,
+            buy_k: buy_fit.k,
+            sell_a: sell_fit.a,
+            sell_k: sell_fit.k,
+            buy_fit,
+            sell_fit,
         }
     }
 
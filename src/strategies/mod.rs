@@ -2,3 +2,4 @@ pub mod avellaneda_stoikov;
 pub mod avellaneda_stoikov_okex;
 pub mod cross_exchange_arbitrage;
 pub mod eie;
+pub mod okex_account;
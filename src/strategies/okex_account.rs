@@ -0,0 +1,241 @@
+use async_trait::async_trait;
+use exrs::okex_v5::{
+    account::Account,
+    errors::Result,
+    rest_model::{
+        AlgoCancellation, AlgoOrderDetail, AlgoOrderRequest, AlgoOrdersRequest, AlgoTransaction,
+        AmendOrderRequest, Balance, OrderCancellation, OrderDetail, PendingOrdersRequest,
+        PositionDetail, PositionsRequest, RestResponse, TransactionResponse,
+    },
+    websockets::WsTrader,
+};
+use exrs::trading::{self, NewOrder, OpenOrder, OrderAck, Position, TradingClient, Venue};
+
+/// What the OKX strategy needs on top of `TradingClient`: batch cancels answered per order
+/// and the algo orders that keep the stops on the venue. Implemented by the live account and
+/// by `backtest::account::SimulatedAccount`, so the same strategy code runs against the
+/// exchange or against recorded data.
+#[async_trait]
+pub trait OkexAccount: TradingClient + Clone + 'static {
+    /// Cancels up to 20 orders in one request, the response has a result per order
+    async fn cancel_all_open_orders(
+        &self,
+        orders: Vec<OrderCancellation>,
+    ) -> Result<TransactionResponse>;
+
+    async fn place_algo_order(
+        &self,
+        order: AlgoOrderRequest,
+    ) -> Result<RestResponse<AlgoTransaction>>;
+
+    /// Conditional, oco and trigger orders
+    async fn cancel_algo_orders(
+        &self,
+        orders: Vec<AlgoCancellation>,
+    ) -> Result<RestResponse<AlgoTransaction>>;
+
+    /// Trailing stop, iceberg and twap orders
+    async fn cancel_advance_algo_orders(
+        &self,
+        orders: Vec<AlgoCancellation>,
+    ) -> Result<RestResponse<AlgoTransaction>>;
+}
+
+#[async_trait]
+impl OkexAccount for Account {
+    async fn cancel_all_open_orders(
+        &self,
+        orders: Vec<OrderCancellation>,
+    ) -> Result<TransactionResponse> {
+        Account::cancel_all_open_orders(self, orders).await
+    }
+
+    async fn place_algo_order(
+        &self,
+        order: AlgoOrderRequest,
+    ) -> Result<RestResponse<AlgoTransaction>> {
+        Account::place_algo_order(self, order).await
+    }
+
+    async fn cancel_algo_orders(
+        &self,
+        orders: Vec<AlgoCancellation>,
+    ) -> Result<RestResponse<AlgoTransaction>> {
+        Account::cancel_algo_orders(self, orders).await
+    }
+
+    async fn cancel_advance_algo_orders(
+        &self,
+        orders: Vec<AlgoCancellation>,
+    ) -> Result<RestResponse<AlgoTransaction>> {
+        Account::cancel_advance_algo_orders(self, orders).await
+    }
+}
+
+/// The live account, orders, amends and cancels go over the private websocket once `ws` is
+/// set. Everything else, closing positions and algo orders included, stays on REST.
+#[derive(Clone)]
+pub struct LiveAccount {
+    pub rest: Account,
+    pub ws: Option<WsTrader>,
+}
+
+#[async_trait]
+impl TradingClient for LiveAccount {
+    fn venue(&self) -> Venue {
+        Venue::Okex
+    }
+
+    async fn place_order(&self, order: NewOrder) -> trading::Result<OrderAck> {
+        match &self.ws {
+            Some(ws) => OrderAck::from_okex(ws.place_order(order.into()).await?.into()),
+            None => TradingClient::place_order(&self.rest, order).await,
+        }
+    }
+
+    async fn cancel_order(&self, symbol: &str, client_order_id: &str) -> trading::Result<()> {
+        match &self.ws {
+            Some(ws) => {
+                let response = ws
+                    .cancel_order(OrderCancellation {
+                        symbol: symbol.to_string(),
+                        order_id: None,
+                        orig_client_order_id: Some(client_order_id.to_string()),
+                    })
+                    .await?;
+                OrderAck::from_okex(response.into()).map(|_| ())
+            }
+            None => TradingClient::cancel_order(&self.rest, symbol, client_order_id).await,
+        }
+    }
+
+    async fn cancel_all(&self, symbol: &str) -> trading::Result<()> {
+        self.rest.cancel_all(symbol).await
+    }
+
+    async fn amend_order(
+        &self,
+        symbol: &str,
+        client_order_id: &str,
+        new_qty: Option<f64>,
+        new_price: Option<f64>,
+    ) -> trading::Result<OrderAck> {
+        match &self.ws {
+            Some(ws) => {
+                let response = ws
+                    .amend_order(AmendOrderRequest {
+                        symbol: symbol.to_string(),
+                        order_id: None,
+                        client_order_id: Some(client_order_id.to_string()),
+                        new_qty,
+                        new_price,
+                    })
+                    .await?;
+                OrderAck::from_okex(response.into())
+            }
+            None => {
+                TradingClient::amend_order(&self.rest, symbol, client_order_id, new_qty, new_price)
+                    .await
+            }
+        }
+    }
+
+    async fn close_position(&self, symbol: &str) -> trading::Result<()> {
+        TradingClient::close_position(&self.rest, symbol).await
+    }
+
+    async fn positions(&self, symbol: &str) -> trading::Result<Vec<Position>> {
+        self.rest.positions(symbol).await
+    }
+
+    async fn balances(&self) -> trading::Result<Vec<trading::Balance>> {
+        self.rest.balances().await
+    }
+
+    async fn open_orders(&self, symbol: &str) -> trading::Result<Vec<OpenOrder>> {
+        self.rest.open_orders(symbol).await
+    }
+}
+
+#[async_trait]
+impl OkexAccount for LiveAccount {
+    async fn cancel_all_open_orders(
+        &self,
+        orders: Vec<OrderCancellation>,
+    ) -> Result<TransactionResponse> {
+        match &self.ws {
+            Some(ws) => ws.cancel_orders(orders).await.map(Into::into),
+            None => self.rest.cancel_all_open_orders(orders).await,
+        }
+    }
+
+    async fn place_algo_order(
+        &self,
+        order: AlgoOrderRequest,
+    ) -> Result<RestResponse<AlgoTransaction>> {
+        self.rest.place_algo_order(order).await
+    }
+
+    async fn cancel_algo_orders(
+        &self,
+        orders: Vec<AlgoCancellation>,
+    ) -> Result<RestResponse<AlgoTransaction>> {
+        self.rest.cancel_algo_orders(orders).await
+    }
+
+    async fn cancel_advance_algo_orders(
+        &self,
+        orders: Vec<AlgoCancellation>,
+    ) -> Result<RestResponse<AlgoTransaction>> {
+        self.rest.cancel_advance_algo_orders(orders).await
+    }
+}
+
+/// The REST queries `AvellanedaStoikov::recover` rebuilds the state of a previous run from
+#[async_trait]
+pub trait OkexRecovery: Send + Sync {
+    async fn get_orders_pending(
+        &self,
+        request: PendingOrdersRequest,
+    ) -> Result<RestResponse<OrderDetail>>;
+
+    async fn get_algo_orders_pending(
+        &self,
+        request: AlgoOrdersRequest,
+    ) -> Result<RestResponse<AlgoOrderDetail>>;
+
+    async fn get_positions(
+        &self,
+        request: PositionsRequest,
+    ) -> Result<RestResponse<PositionDetail>>;
+
+    async fn get_balance(&self, ccy: Option<String>) -> Result<RestResponse<Balance>>;
+}
+
+#[async_trait]
+impl OkexRecovery for LiveAccount {
+    async fn get_orders_pending(
+        &self,
+        request: PendingOrdersRequest,
+    ) -> Result<RestResponse<OrderDetail>> {
+        self.rest.get_orders_pending(request).await
+    }
+
+    async fn get_algo_orders_pending(
+        &self,
+        request: AlgoOrdersRequest,
+    ) -> Result<RestResponse<AlgoOrderDetail>> {
+        self.rest.get_algo_orders_pending(request).await
+    }
+
+    async fn get_positions(
+        &self,
+        request: PositionsRequest,
+    ) -> Result<RestResponse<PositionDetail>> {
+        self.rest.get_positions(request).await
+    }
+
+    async fn get_balance(&self, ccy: Option<String>) -> Result<RestResponse<Balance>> {
+        self.rest.get_balance(ccy).await
+    }
+}